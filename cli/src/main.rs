@@ -1,8 +1,10 @@
 
 use std::fs::File;
 use std::io::BufWriter;
+use std::io::Read;
 
 use rpu::RPU;
+use rpu::prelude::Formatter;
 
 fn get_time() -> u128 {
     let stop = std::time::SystemTime::now()
@@ -11,8 +13,70 @@ fn get_time() -> u128 {
         stop.as_millis()
 }
 
+/// rpu fmt [--check] <files>: reprints the files canonically. With --check the files are not changed,
+/// files which are not formatted are listed and the exit code is 1. Without files stdin is formatted to stdout.
+fn fmt(args: &[String]) -> i32 {
+
+    let check = args.iter().any(|a| a == "--check");
+    let files : Vec<&String> = args.iter().filter(|a| a.starts_with("--") == false).collect();
+
+    let formatter = Formatter::new();
+
+    if files.is_empty() {
+        let mut code = String::new();
+        if std::io::stdin().read_to_string(&mut code).is_err() {
+            eprintln!("Could not read stdin.");
+            return 1;
+        }
+        return match formatter.format(code, None) {
+            Ok(formatted) => {
+                print!("{}", formatted);
+                0
+            },
+            Err(errors) => {
+                for error in &errors {
+                    eprintln!("{}", error.report());
+                }
+                1
+            }
+        };
+    }
+
+    let mut rc = 0;
+    for file in files {
+        let path = std::path::PathBuf::from(file);
+        let code = std::fs::read_to_string(&path).unwrap_or_default();
+        match formatter.format_path(path.clone()) {
+            Ok(formatted) => {
+                if formatted != code {
+                    if check {
+                        println!("{} is not formatted.", path.display());
+                        rc = 1;
+                    } else
+                    if let Err(err) = std::fs::write(&path, formatted) {
+                        eprintln!("Could not write {}: {}", path.display(), err);
+                        rc = 1;
+                    }
+                }
+            },
+            Err(errors) => {
+                for error in &errors {
+                    eprintln!("{}", error.report());
+                }
+                rc = 1;
+            }
+        }
+    }
+    rc
+}
+
 fn main() {
 
+    let args : Vec<String> = std::env::args().collect();
+    if args.get(1).map(|a| a.as_str()) == Some("fmt") {
+        std::process::exit(fmt(&args[2..]));
+    }
+
     let width = 300;
     let height = 300;
 
@@ -22,7 +86,9 @@ fn main() {
     _ = rpu.compile_from_path(std::path::PathBuf::new());
 
     let start = get_time();
-    rpu.render(&mut buffer[..], (0, 0, width, height), width);
+    if let Err(err) = rpu.render(&mut buffer[..], (0, 0, width, height), width) {
+        eprintln!("{}", err.error.report());
+    }
     let t = get_time() - start;
 
     // println!("{:?}", rc);
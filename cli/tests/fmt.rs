@@ -0,0 +1,27 @@
+use std::process::Command;
+
+const SOURCE: &str = "Texture\n  name = \"Bricks\"\n  Color\n      color=#A9957B\n";
+
+fn fmt(args: &[&str]) -> (i32, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_cli")).arg("fmt").args(args).output().unwrap();
+    (output.status.code().unwrap(), String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+#[test]
+fn check_fails_on_unformatted_files() {
+    let dir = std::env::temp_dir().join(format!("rpu-cli-fmt-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("scene.rpu");
+    std::fs::write(&path, SOURCE).unwrap();
+    let file = path.to_str().unwrap();
+
+    // --check only lists the file
+    assert_eq!(fmt(&["--check", file]), (1, format!("{} is not formatted.\n", file)));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), SOURCE);
+
+    assert_eq!(fmt(&[file]), (0, String::new()));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "Texture\n    name = \"Bricks\"\n    Color\n        color = #a9957b\n");
+    assert_eq!(fmt(&["--check", file]), (0, String::new()));
+
+    _ = std::fs::remove_dir_all(&dir);
+}
@@ -27,7 +27,9 @@ impl UI {
     pub fn draw(&mut self, frame: &mut [u8], rect: (usize, usize, usize, usize), stride: usize) {
         self.ctx.set_rect(rect);
 
-        self.ctx.rpu.render(frame, self.ctx.rpu_rect, rect.2);
+        if let Err(err) = self.ctx.rpu.render(frame, self.ctx.rpu_rect, rect.2) {
+            println!("{}", err);
+        }
         self.code_editor.draw(frame, self.ctx.code_rect, stride);
     }
 
@@ -0,0 +1,438 @@
+use rpu::prelude::*;
+
+use lsp_types::*;
+
+/// A parsed document and the documents it imports, answers the requests of the language server.
+pub struct Analysis {
+    pub uri                 : Url,
+    pub text                : String,
+    pub path                : Option<PathBuf>,
+
+    pub document            : Document,
+    /// The documents imported by the document, directly or indirectly.
+    pub imports             : Vec<Document>,
+}
+
+impl Analysis {
+
+    pub fn new(uri: Url, text: String) -> Self {
+        let path = uri.to_file_path().ok();
+        let (document, _) = Compiler::parse(text.clone(), path.clone());
+
+        let mut imports = vec![];
+        let mut visited : Vec<PathBuf> = path.iter().map(|p| p.canonicalize().unwrap_or(p.clone())).collect();
+        collect_imports(&document, &mut imports, &mut visited);
+
+        Self {
+            uri,
+            text,
+            path,
+
+            document,
+            imports,
+        }
+    }
+
+    /// Compiles the document and converts the errors and warnings into diagnostics. Errors in imported files
+    /// are reported at the start of the document, infos without a location are skipped.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        let mut compiler = Compiler::new();
+        _ = match &self.path {
+            Some(path) => compiler.compile_as_path(self.text.clone(), path.clone()),
+            None => compiler.compile(self.text.clone()),
+        };
+
+        compiler.diagnostics().entries().iter().filter(|d| d.severity != Severity::Info || d.line > 0).map(|d| self.diagnostic(d)).collect()
+    }
+
+    /// Completes element keywords, the properties and functions of the enclosing element and texture names.
+    pub fn completion(&self, position: Position) -> Vec<CompletionItem> {
+        let line = self.document.line_text(position.line as usize + 1);
+        let before : String = line.chars().take(char_index(&line, position.character)).collect();
+        let trimmed = before.trim_start();
+        let indent = before.len() - trimmed.len();
+
+        let mut items = vec![];
+
+        if let Some((name, value)) = trimmed.split_once('=') {
+            if name.trim() == "texture" && value.trim_start().starts_with('"') {
+                for (texture, _) in self.textures() {
+                    if let Some(name) = texture.name() {
+                        items.push(CompletionItem {
+                            label           : name,
+                            kind            : Some(CompletionItemKind::REFERENCE),
+                            detail          : Some(format!("Texture, line {}", texture.token.line)),
+                            ..Default::default()
+                        });
+                    }
+                }
+            }
+            return items;
+        }
+
+        if trimmed.chars().all(|c| c.is_alphanumeric() || c == '_') == false {
+            return items;
+        }
+
+        if indent == 0 {
+            for kind in TOP_LEVEL_ELEMENTS {
+                items.push(element_item(kind));
+            }
+            items.push(CompletionItem {
+                label               : "Import".to_string(),
+                kind                : Some(CompletionItemKind::KEYWORD),
+                detail              : Some("Import \"path.rpu\"".to_string()),
+                insert_text         : Some("Import \"".to_string()),
+                ..Default::default()
+            });
+        } else
+        if let Some(block) = self.enclosing_block(position.line as usize + 1, indent) {
+            if let Some(schema) = Compiler::schema(&block.kind) {
+                for p in &schema.properties {
+                    items.push(CompletionItem {
+                        label           : p.name.to_string(),
+                        kind            : Some(CompletionItemKind::PROPERTY),
+                        detail          : Some(p.kind.name().to_string()),
+                        documentation   : Some(Documentation::String(p.help.to_string())),
+                        insert_text     : Some(format!("{} = ", p.name)),
+                        ..Default::default()
+                    });
+                }
+                for f in &schema.functions {
+                    items.push(CompletionItem {
+                        label           : f.name.to_string(),
+                        kind            : Some(CompletionItemKind::FUNCTION),
+                        detail          : Some(format!("{}({})", f.name, f.args)),
+                        documentation   : Some(Documentation::String(f.help.to_string())),
+                        insert_text     : Some(format!("{}({})", f.name, f.args)),
+                        ..Default::default()
+                    });
+                }
+            }
+            if block.kind == "Texture" {
+                for kind in TEXTURE_ELEMENTS {
+                    items.push(element_item(kind));
+                }
+                // The layers defined with Layer blocks
+                for layer in std::iter::once(&self.document).chain(self.imports.iter()).flat_map(|d| d.blocks()).filter(|b| b.kind == "Layer") {
+                    if let Some(name) = layer.name() {
+                        items.push(element_item(&name));
+                    }
+                }
+            }
+        }
+
+        items
+    }
+
+    /// Documents elements, properties, code blocks, texture references and the symbols of layout maps.
+    pub fn hover(&self, position: Position) -> Option<Hover> {
+        let line_number = position.line as usize + 1;
+        let line = self.document.line_text(line_number);
+        let index = char_index(&line, position.character);
+
+        let text = if let Some((block, _)) = self.symbol_at(line_number, index) {
+            Some(format!("`{}'{}` defined on line {}", block.kind, block.symbol.clone().unwrap_or_default(), block.token.line))
+        } else
+        if let Some((texture, document)) = self.texture_at(line_number) {
+            Some(format!("Texture `{}` defined on line {}{}", texture.name().unwrap_or_default(), texture.token.line, self.file_suffix(document)))
+        } else {
+            let word = word_at(&line, index)?;
+            let mut text = None;
+            for block in all_blocks(&self.document) {
+                if block.token.line == line_number && block.kind == word {
+                    text = Compiler::schema(&block.kind).map(|schema| element_docs(&schema));
+                } else
+                if let Some(p) = block.properties.iter().find(|p| p.token.line == line_number && p.name == word) {
+                    text = Compiler::schema(&block.kind).and_then(|schema| schema.get(&p.name).map(property_docs));
+                } else
+                if let Some(f) = block.functions.iter().find(|f| f.token.line == line_number && f.name == word) {
+                    text = Compiler::schema(&block.kind).and_then(|schema| schema.get_function(&f.name).map(|f| format!("`{}({})`\n\n{}", f.name, f.args, f.help)));
+                }
+                if text.is_some() {
+                    break;
+                }
+            }
+            text
+        }?;
+
+        Some(Hover {
+            contents                : HoverContents::Markup(MarkupContent { kind: MarkupKind::Markdown, value: text }),
+            range                   : None,
+        })
+    }
+
+    /// The definition of the texture referenced on the line, of the prototype of the block on the line or of
+    /// the symbol under the cursor in a layout map.
+    pub fn definition(&self, position: Position) -> Option<Location> {
+        let line_number = position.line as usize + 1;
+        let line = self.document.line_text(line_number);
+        let index = char_index(&line, position.character);
+
+        let (block, document) = self.symbol_at(line_number, index)
+            .or_else(|| self.texture_at(line_number))
+            .or_else(|| self.prototype_at(line_number))?;
+        let uri = if std::ptr::eq(document, &self.document) {
+            self.uri.clone()
+        } else {
+            Url::from_file_path(document.file.as_ref()?).ok()?
+        };
+
+        Some(Location {
+            uri,
+            range                   : token_range(&block.token, &document.line_text(block.token.line)),
+        })
+    }
+
+    /// The textures of the document and its imports.
+    fn textures(&self) -> Vec<(&Block, &Document)> {
+        let mut textures = vec![];
+        for document in std::iter::once(&self.document).chain(self.imports.iter()) {
+            for block in document.blocks() {
+                if block.kind == "Texture" {
+                    textures.push((block, document));
+                }
+            }
+        }
+        textures
+    }
+
+    /// The texture referenced by a texture property on the given line.
+    fn texture_at(&self, line: usize) -> Option<(&Block, &Document)> {
+        let reference = all_blocks(&self.document).into_iter()
+            .flat_map(|b| b.properties.iter())
+            .find(|p| p.token.line == line && p.name == "texture")?;
+        let name = reference.value.trim_matches('"');
+        self.textures().into_iter().find(|(texture, _)| texture.name().as_deref() == Some(name))
+    }
+
+    /// The prototype of the block whose header is on the given line.
+    fn prototype_at(&self, line: usize) -> Option<(&Block, &Document)> {
+        let base = self.document.blocks().into_iter().find(|b| b.token.line == line)?.base.as_ref()?;
+        if base.symbol {
+            self.find_symbol(&base.name)
+        } else {
+            self.textures().into_iter().find(|(texture, _)| texture.name().as_ref() == Some(&base.name))
+        }
+    }
+
+    /// The block of the instance symbol at the given position of a layout map or of the legend entry on the line.
+    fn symbol_at(&self, line: usize, index: usize) -> Option<(&Block, &Document)> {
+        for block in self.document.blocks() {
+            if let Some(entry) = block.legend.as_ref().and_then(|l| l.entries.iter().find(|e| e.token.line == line)) {
+                return self.find_symbol(&legend_symbol(&entry.value)?);
+            }
+
+            let row = match block.map.as_ref().and_then(|m| m.rows.iter().find(|r| r.token.line == line)) {
+                Some(row) => row,
+                None => continue,
+            };
+
+            // With a legend the cells are as wide as the longest key
+            let width = block.legend.as_ref().map_or(1, |l| l.cell_width());
+            let cell = index.checked_sub(row.colons)? / width;
+            let text : String = row.text.chars().skip(cell * width).take(width).collect();
+            let symbol = text.trim();
+            if symbol.is_empty() {
+                return None;
+            }
+
+            let name = block.legend.as_ref()
+                .and_then(|l| l.get(symbol))
+                .and_then(|e| legend_symbol(&e.value))
+                .unwrap_or(symbol.to_string());
+            return self.find_symbol(&name);
+        }
+        None
+    }
+
+    /// The object with the given instance symbol.
+    fn find_symbol(&self, symbol: &str) -> Option<(&Block, &Document)> {
+        for document in std::iter::once(&self.document).chain(self.imports.iter()) {
+            if let Some(block) = all_blocks(document).into_iter().find(|b| b.symbol.as_deref() == Some(symbol)) {
+                return Some((block, document));
+            }
+        }
+        None
+    }
+
+    /// The block containing the given line with the given indentation, i.e. the closest line above it
+    /// which is indented less.
+    fn enclosing_block(&self, line: usize, indent: usize) -> Option<&Block> {
+        for l in (1..line).rev() {
+            let text = self.document.line_text(l);
+            let trimmed = text.trim_start();
+            if trimmed.is_empty() || trimmed.starts_with("//") {
+                continue;
+            }
+            if text.len() - trimmed.len() < indent {
+                return all_blocks(&self.document).into_iter().find(|b| b.token.line == l);
+            }
+        }
+        None
+    }
+
+    /// " in file" for blocks of imported documents.
+    fn file_suffix(&self, document: &Document) -> String {
+        if std::ptr::eq(document, &self.document) {
+            "".to_string()
+        } else {
+            document.file.as_ref().map(|f| format!(" of {}", f.display())).unwrap_or_default()
+        }
+    }
+
+    fn diagnostic(&self, error: &RPUError) -> Diagnostic {
+        let same_file = match (&error.file, &self.path) {
+            (None, _) => true,
+            (Some(file), Some(path)) => file.canonicalize().ok() == path.canonicalize().ok() || file == path,
+            _ => false,
+        };
+
+        let (range, message) = if same_file && error.line > 0 {
+            let line = error.line - 1;
+            let start = utf16_offset(&error.source_line, error.column.saturating_sub(1) as usize);
+            let length = error.lexeme.lines().next().unwrap_or("").encode_utf16().count().max(1) as u32;
+            (Range::new(Position::new(line, start), Position::new(line, start + length)), error.description.clone())
+        } else {
+            (Range::default(), format!("{}: {}", error.location(), error.description))
+        };
+
+        Diagnostic {
+            range,
+            severity                : Some(match error.severity {
+                Severity::Error => DiagnosticSeverity::ERROR,
+                Severity::Warning => DiagnosticSeverity::WARNING,
+                Severity::Info => DiagnosticSeverity::INFORMATION,
+            }),
+            code                    : Some(NumberOrString::String(error.code.to_string())),
+            source                  : Some("rpu".to_string()),
+            message,
+            ..Default::default()
+        }
+    }
+}
+
+/// Parses the imports of the document recursively, every file only once.
+fn collect_imports(document: &Document, imports: &mut Vec<Document>, visited: &mut Vec<PathBuf>) {
+    for import in document.imports() {
+        let mut path = document.file.as_ref().and_then(|f| f.parent()).map(|p| p.to_path_buf()).unwrap_or_default();
+        path.push(&import.path);
+
+        let canonical = path.canonicalize().unwrap_or(path.clone());
+        if visited.contains(&canonical) {
+            continue;
+        }
+        visited.push(canonical);
+
+        if let Ok(code) = std::fs::read_to_string(&path) {
+            let (imported, _) = Compiler::parse(code, Some(path));
+            collect_imports(&imported, imports, visited);
+            imports.push(imported);
+        }
+    }
+}
+
+/// The instance symbol of a legend entry like 'door, rotation = 90.
+fn legend_symbol(value: &str) -> Option<String> {
+    value.split(',').next()?.trim().strip_prefix('\'').map(|s| s.trim().to_string())
+}
+
+/// All blocks of the document including nested elements.
+fn all_blocks(document: &Document) -> Vec<&Block> {
+    fn add<'a>(block: &'a Block, blocks: &mut Vec<&'a Block>) {
+        blocks.push(block);
+        for child in &block.children {
+            add(child, blocks);
+        }
+    }
+    let mut blocks = vec![];
+    for block in document.blocks() {
+        add(block, &mut blocks);
+    }
+    blocks
+}
+
+/// A completion item for an element keyword.
+fn element_item(kind: &str) -> CompletionItem {
+    CompletionItem {
+        label                   : kind.to_string(),
+        kind                    : Some(CompletionItemKind::CLASS),
+        documentation           : Compiler::schema(kind).map(|schema| Documentation::MarkupContent(MarkupContent { kind: MarkupKind::Markdown, value: element_docs(&schema) })),
+        ..Default::default()
+    }
+}
+
+/// The markdown documentation of an element.
+fn element_docs(schema: &Schema) -> String {
+    let mut docs = format!("**{}**", schema.name);
+    if schema.properties.is_empty() == false {
+        docs += "\n\nProperties:\n";
+        for p in &schema.properties {
+            docs += format!("\n- `{}` *{}*: {}", p.name, p.kind.name(), p.help).as_str();
+        }
+    }
+    if schema.functions.is_empty() == false {
+        docs += "\n\nFunctions:\n";
+        for f in &schema.functions {
+            docs += format!("\n- `{}({})`: {}", f.name, f.args, f.help).as_str();
+        }
+    }
+    docs
+}
+
+/// The markdown documentation of a property.
+fn property_docs(property: &PropertyDef) -> String {
+    let mut docs = format!("`{}` *{}*\n\n{}", property.name, property.kind.name(), property.help);
+    if let Some(default) = &property.default {
+        docs += format!("\n\nDefault: `{}`", default).as_str();
+    }
+    if let Some((min, max)) = property.range {
+        docs += format!("\n\nRange: {} to {}", min, max).as_str();
+    }
+    docs
+}
+
+/// The identifier at the given character index of the line.
+fn word_at(line: &str, index: usize) -> Option<String> {
+    let chars : Vec<char> = line.chars().collect();
+    let is_word = |c: &char| c.is_alphanumeric() || *c == '_';
+
+    let mut start = index.min(chars.len());
+    while start > 0 && is_word(&chars[start - 1]) {
+        start -= 1;
+    }
+    let mut end = index.min(chars.len());
+    while end < chars.len() && is_word(&chars[end]) {
+        end += 1;
+    }
+
+    if start == end {
+        None
+    } else {
+        Some(chars[start..end].iter().collect())
+    }
+}
+
+/// The range of a token, LSP positions are 0 based and count UTF-16 code units.
+fn token_range(token: &Token, line: &str) -> Range {
+    let start = utf16_offset(line, token.column.saturating_sub(1));
+    let length = token.lexeme.encode_utf16().count() as u32;
+    Range::new(Position::new(token.line.saturating_sub(1) as u32, start), Position::new(token.line.saturating_sub(1) as u32, start + length))
+}
+
+/// The UTF-16 offset of the given character index of the line.
+fn utf16_offset(line: &str, index: usize) -> u32 {
+    line.chars().take(index).map(|c| c.len_utf16() as u32).sum()
+}
+
+/// The character index of the given UTF-16 offset of the line.
+fn char_index(line: &str, offset: u32) -> usize {
+    let mut units = 0;
+    for (index, c) in line.chars().enumerate() {
+        if units >= offset {
+            return index;
+        }
+        units += c.len_utf16() as u32;
+    }
+    line.chars().count()
+}
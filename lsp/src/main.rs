@@ -0,0 +1,139 @@
+mod analysis;
+
+use std::collections::HashMap;
+use std::error::Error;
+
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::notification::{DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, PublishDiagnostics};
+use lsp_types::request::{Completion, GotoDefinition, HoverRequest};
+use lsp_types::*;
+
+use analysis::Analysis;
+
+/// rpu-lsp, a language server for RPU scene files speaking LSP over stdio.
+fn main() -> Result<(), Box<dyn Error + Sync + Send>> {
+
+    // Logs go to stderr, stdout is used by the protocol
+    env_logger::init();
+
+    let (connection, io_threads) = Connection::stdio();
+
+    let capabilities = serde_json::to_value(ServerCapabilities {
+        text_document_sync      : Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        completion_provider     : Some(CompletionOptions {
+            trigger_characters  : Some(vec!["\"".to_string()]),
+            ..Default::default()
+        }),
+        hover_provider          : Some(HoverProviderCapability::Simple(true)),
+        definition_provider     : Some(OneOf::Left(true)),
+        ..Default::default()
+    })?;
+
+    connection.initialize(capabilities)?;
+    // The connection is dropped by the main loop, which ends the io threads
+    main_loop(connection)?;
+    io_threads.join()?;
+
+    Ok(())
+}
+
+fn main_loop(connection: Connection) -> Result<(), Box<dyn Error + Sync + Send>> {
+    use lsp_types::notification::Notification as _;
+
+    // The text of the open documents
+    let mut documents : HashMap<Url, String> = HashMap::new();
+
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    return Ok(());
+                }
+                let response = handle_request(&documents, request);
+                connection.sender.send(Message::Response(response))?;
+            },
+            Message::Notification(notification) => {
+                if let Some(uri) = handle_notification(&mut documents, notification) {
+                    let diagnostics = documents.get(&uri).map(|text| Analysis::new(uri.clone(), text.clone()).diagnostics()).unwrap_or_default();
+                    let params = PublishDiagnosticsParams::new(uri, diagnostics, None);
+                    connection.sender.send(Message::Notification(Notification::new(PublishDiagnostics::METHOD.to_string(), params)))?;
+                }
+            },
+            Message::Response(_) => {}
+        }
+    }
+    Ok(())
+}
+
+/// Answers completion, hover and definition requests.
+fn handle_request(documents: &HashMap<Url, String>, request: Request) -> Response {
+    use lsp_types::request::Request as _;
+
+    let id = request.id.clone();
+
+    let result = match request.method.as_str() {
+        Completion::METHOD => {
+            serde_json::from_value::<CompletionParams>(request.params).ok().map(|params| {
+                let position = params.text_document_position;
+                let items = get_analysis(documents, &position.text_document.uri).completion(position.position);
+                serde_json::to_value(CompletionResponse::Array(items))
+            })
+        },
+        HoverRequest::METHOD => {
+            serde_json::from_value::<HoverParams>(request.params).ok().map(|params| {
+                let position = params.text_document_position_params;
+                serde_json::to_value(get_analysis(documents, &position.text_document.uri).hover(position.position))
+            })
+        },
+        GotoDefinition::METHOD => {
+            serde_json::from_value::<GotoDefinitionParams>(request.params).ok().map(|params| {
+                let position = params.text_document_position_params;
+                let location = get_analysis(documents, &position.text_document.uri).definition(position.position);
+                serde_json::to_value(location.map(GotoDefinitionResponse::Scalar))
+            })
+        },
+        method => {
+            return Response::new_err(id, ErrorCode::MethodNotFound as i32, format!("Unknown request '{}'.", method));
+        }
+    };
+
+    match result {
+        Some(Ok(value)) => Response::new_ok(id, value),
+        _ => Response::new_err(id, ErrorCode::InvalidParams as i32, "Invalid parameters.".to_string()),
+    }
+}
+
+/// Keeps track of the open documents, returns the document whose diagnostics have to be published.
+fn handle_notification(documents: &mut HashMap<Url, String>, notification: Notification) -> Option<Url> {
+    use lsp_types::notification::Notification as _;
+
+    match notification.method.as_str() {
+        DidOpenTextDocument::METHOD => {
+            let params = serde_json::from_value::<DidOpenTextDocumentParams>(notification.params).ok()?;
+            documents.insert(params.text_document.uri.clone(), params.text_document.text);
+            Some(params.text_document.uri)
+        },
+        DidChangeTextDocument::METHOD => {
+            let params = serde_json::from_value::<DidChangeTextDocumentParams>(notification.params).ok()?;
+            // Full sync, the last change contains the whole document
+            let text = params.content_changes.into_iter().last()?.text;
+            documents.insert(params.text_document.uri.clone(), text);
+            Some(params.text_document.uri)
+        },
+        DidCloseTextDocument::METHOD => {
+            let params = serde_json::from_value::<DidCloseTextDocumentParams>(notification.params).ok()?;
+            documents.remove(&params.text_document.uri);
+            Some(params.text_document.uri)
+        },
+        _ => None,
+    }
+}
+
+/// The analysis of the given document, documents which are not open are read from disk.
+fn get_analysis(documents: &HashMap<Url, String>, uri: &Url) -> Analysis {
+    let text = match documents.get(uri) {
+        Some(text) => text.clone(),
+        None => uri.to_file_path().ok().and_then(|path| std::fs::read_to_string(path).ok()).unwrap_or_default(),
+    };
+    Analysis::new(uri.clone(), text)
+}
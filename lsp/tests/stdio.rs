@@ -0,0 +1,108 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{ChildStdin, ChildStdout, Command, Stdio};
+
+use serde_json::{json, Value};
+
+/// A minimal LSP client talking to rpu-lsp over stdio.
+struct Client {
+    stdin               : ChildStdin,
+    stdout              : BufReader<ChildStdout>,
+}
+
+impl Client {
+
+    fn send(&mut self, message: Value) {
+        let body = message.to_string();
+        write!(self.stdin, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    fn receive(&mut self) -> Value {
+        let mut length = 0;
+        loop {
+            let mut header = String::new();
+            self.stdout.read_line(&mut header).unwrap();
+            let header = header.trim();
+            if header.is_empty() {
+                break;
+            }
+            if let Some(value) = header.strip_prefix("Content-Length: ") {
+                length = value.parse().unwrap();
+            }
+        }
+        let mut body = vec![0; length];
+        self.stdout.read_exact(&mut body).unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    /// Sends a request and returns the result of its response.
+    fn request(&mut self, id: i32, method: &str, params: Value) -> Value {
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }));
+        loop {
+            let message = self.receive();
+            if message["id"] == json!(id) {
+                return message["result"].clone();
+            }
+        }
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+}
+
+#[test]
+fn diagnostics_completion_hover_definition() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rpu-lsp"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let mut client = Client {
+        stdin           : child.stdin.take().unwrap(),
+        stdout          : BufReader::new(child.stdout.take().unwrap()),
+    };
+
+    let capabilities = client.request(1, "initialize", json!({ "capabilities": {} }));
+    assert_eq!(capabilities["capabilities"]["hoverProvider"], json!(true));
+    client.notify("initialized", json!({}));
+
+    let uri = "file:///tmp/rpu-lsp-test/main.rpu";
+    let text = "Texture\n    name = \"Bricks\"\n    Color\n        colour = #a9957b\n\nVoxel'a\n    texture = \"Bricks\"\n\nGrid3D\n: a\n";
+    client.notify("textDocument/didOpen", json!({
+        "textDocument": { "uri": uri, "languageId": "rpu", "version": 1, "text": text }
+    }));
+
+    let diagnostics = client.receive();
+    assert_eq!(diagnostics["method"], json!("textDocument/publishDiagnostics"));
+    let diagnostics = diagnostics["params"]["diagnostics"].as_array().unwrap().clone();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0]["range"]["start"], json!({ "line": 3, "character": 8 }));
+
+    // Properties of the enclosing Color element
+    let completion = client.request(2, "textDocument/completion", json!({
+        "textDocument": { "uri": uri }, "position": { "line": 3, "character": 10 }
+    }));
+    assert!(completion.as_array().unwrap().iter().any(|item| item["label"] == json!("color")));
+
+    let hover = client.request(3, "textDocument/hover", json!({
+        "textDocument": { "uri": uri }, "position": { "line": 2, "character": 6 }
+    }));
+    assert!(hover["contents"]["value"].as_str().unwrap().contains("**Color**"));
+
+    // The texture reference and the map symbol both lead to their definitions
+    let definition = client.request(4, "textDocument/definition", json!({
+        "textDocument": { "uri": uri }, "position": { "line": 6, "character": 16 }
+    }));
+    assert_eq!(definition["range"]["start"], json!({ "line": 0, "character": 0 }));
+
+    let definition = client.request(5, "textDocument/definition", json!({
+        "textDocument": { "uri": uri }, "position": { "line": 9, "character": 2 }
+    }));
+    assert_eq!(definition["range"]["start"], json!({ "line": 5, "character": 0 }));
+
+    client.request(6, "shutdown", json!(null));
+    client.notify("exit", json!(null));
+    assert!(child.wait().unwrap().success());
+}
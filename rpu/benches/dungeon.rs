@@ -0,0 +1,64 @@
+//! Renders the brick dungeon of the README, once as is and once with a scripted layer, and prints the
+//! average time per frame. Run with `cargo bench -p rpu`.
+
+use rpu::prelude::*;
+use std::time::Instant;
+
+const DUNGEON: &str = r#"Pinhole
+    origin = F3(3.0, 0.5001, 9.5)
+    center = F3(3.0, 0.5, 0.0)
+
+Texture
+    name = "Bricks"
+    pixelate = 60.0
+    Color
+        color = #a9957b
+    Bricks
+        color = #bc4a3c
+    Noise
+        color = #444444
+        scale = F2(1.0, 1.0)
+
+Voxel'a
+    texture = "Bricks"
+
+Grid3D
+:    a
+:aaa a
+:
+: a  a
+: a  a
+: a  a
+"#;
+
+const SHADER: &str = r#"
+        shader(uv, size)
+            let d = length(uv - 0.5);
+            F4(0.2, 0.1, 0.1, smoothstep(0.3, 0.5, d) * 0.5)
+"#;
+
+const WIDTH: usize = 600;
+const HEIGHT: usize = 400;
+const FRAMES: u32 = 10;
+
+fn bench(name: &str, source: &str) {
+    let mut rpu = RPU::new(WIDTH, HEIGHT);
+    if let Err(errors) = rpu.compile(source.to_string()) {
+        panic!("{}", errors[0].report());
+    }
+
+    let mut frame = vec![0; WIDTH * HEIGHT * 4];
+    _ = rpu.render(&mut frame, (0, 0, WIDTH, HEIGHT), WIDTH);
+
+    let start = Instant::now();
+    for _ in 0..FRAMES {
+        _ = rpu.render(&mut frame, (0, 0, WIDTH, HEIGHT), WIDTH);
+    }
+    let ms = start.elapsed().as_secs_f64() * 1000.0 / FRAMES as f64;
+    println!("{:<24} {:>8.2} ms per frame ({}x{})", name, ms, WIDTH, HEIGHT);
+}
+
+fn main() {
+    bench("dungeon", DUNGEON);
+    bench("dungeon with shader", &DUNGEON.replace("        color = #a9957b\n", &format!("        color = #a9957b{}", SHADER)));
+}
@@ -30,6 +30,9 @@ pub mod prelude {
 
     pub use crate::rpu::script::*;
     pub use crate::rpu::script::engine::ScriptEngine;
+    pub use crate::rpu::script::limits::{ScriptLimits, ScriptLimit, ScriptBudget, RenderError};
+    pub use crate::rpu::script::clock::Clock;
+    pub use crate::rpu::script::schema::*;
     pub use crate::rpu::element2d::Element2D;
 
     pub use crate::rpu::camera::*;
@@ -38,20 +41,28 @@ pub mod prelude {
     pub use crate::rpu::sdf3d::SDF3D;
     pub use crate::rpu::sdf3d::sphere::SDF3DSphere;
     pub use crate::rpu::sdf3d::cube::SDF3DCube;
+    pub use crate::rpu::sdf3d::custom::SDF3DCustom;
 
     pub use crate::rpu::analytical::Analytical;
     pub use crate::rpu::analytical::voxel::AnalyticalVoxel;
     // pub use crate::rpu::analytical::sphere::AnalyticalSphere;
     // pub use crate::rpu::analytical::cube::AnalyticalCube;
 
-    pub use crate::rpu::layout3d::Layout3D;
+    pub use crate::rpu::layout3d::{Layout3D, MapCell};
     pub use crate::rpu::layout3d::grid3d::Grid3D;
+    pub use crate::rpu::layout3d::generator::Generator;
 
     pub use crate::rpu::compiler::Compiler;
     pub use crate::rpu::compiler::RPUError;
+    pub use crate::rpu::compiler::ErrorType;
+    pub use crate::rpu::compiler::diagnostics::{Diagnostics, Severity, WarningLevel};
+    pub use crate::rpu::compiler::{TOP_LEVEL_ELEMENTS, TEXTURE_ELEMENTS};
     pub use crate::rpu::compiler::Property;
     pub use crate::rpu::compiler::scanner::Scanner;
     pub use crate::rpu::compiler::scanner::Token;
+    pub use crate::rpu::compiler::ast::*;
+    pub use crate::rpu::compiler::parser::Parser;
+    pub use crate::rpu::compiler::formatter::Formatter;
     pub use crate::rpu::compiler::context::Context;
     pub use crate::rpu::compiler::node::*;
     pub use crate::rpu::compiler::object::Object;
@@ -65,7 +76,18 @@ pub mod prelude {
     pub use crate::rpu::element2d::vertical::Vertical;
     pub use crate::rpu::element2d::color::ColorElement;
     pub use crate::rpu::element2d::noise::Noise;
+    pub use crate::rpu::element2d::pattern::{Pattern, PatternLayer};
     pub use crate::rpu::element2d::bricks::Bricks;
+    pub use crate::rpu::element2d::checker::Checker;
+    pub use crate::rpu::element2d::gradient::Gradient;
+    pub use crate::rpu::element2d::voronoi::Voronoi;
+    pub use crate::rpu::element2d::tiles::Tiles;
+    pub use crate::rpu::element2d::hexagons::Hexagons;
+    pub use crate::rpu::element2d::stones::Stones;
+    pub use crate::rpu::element2d::planks::Planks;
+    pub use crate::rpu::element2d::marble::Marble;
+    pub use crate::rpu::element2d::stripes::Stripes;
+    pub use crate::rpu::element2d::layer::UserLayer;
     pub use crate::rpu::element2d::sprite::Sprite;
 
     pub use crate::rpu::hit::*;
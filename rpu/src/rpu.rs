@@ -11,12 +11,22 @@ pub mod sdf3d;
 pub mod renderer;
 
 use crate::prelude::*;
+use std::time::Instant;
 
 pub struct RPU {
 
     context             : Option<Context>,
 
+    diagnostics         : Diagnostics,
+    warning_levels      : Vec<(String, WarningLevel)>,
+    script_limits       : ScriptLimits,
+
     color               : ColorBuffer<F>,
+
+    /// The start of the clock of render(), the time of the first frame is 0.
+    start               : Option<Instant>,
+    /// The number of the next rendered frame.
+    frame               : I,
 }
 
 impl RPU {
@@ -25,34 +35,44 @@ impl RPU {
 
         Self {
 
-            context     : None,
+            context         : None,
 
-            color       : ColorBuffer::new(width, height, 0.0),
-        }
-    }
+            diagnostics     : Diagnostics::new(),
+            warning_levels  : vec![],
+            script_limits   : ScriptLimits::default(),
 
-    pub fn compile_from_path(&mut self, path_to_main: PathBuf) -> Result<(), RPUError> {
+            color           : ColorBuffer::new(width, height, 0.0),
 
-        let mut compiler = Compiler::new();
+            start           : None,
+            frame           : 0,
+        }
+    }
 
-        if let Some(main) = std::fs::read_to_string(path_to_main).ok() {
-            let rc = compiler.compile(main);
+    pub fn compile_from_path(&mut self, path_to_main: PathBuf) -> Result<(), Vec<RPUError>> {
 
-            if rc.is_err() {
-                return Err(rc.err().unwrap());
-            }
-            self.context = rc.ok();
+        let mut compiler = self.compiler();
 
-            // println!("{}", main_code);
+        let rc = match &mut self.context {
+            Some(previous) => compiler.recompile_from_path(path_to_main, previous),
+            None => compiler.compile_from_path(path_to_main),
+        };
+        if rc.is_err() {
+            return Err(rc.err().unwrap());
         }
+        self.context = rc.ok();
         Ok(())
     }
 
-    pub fn compile(&mut self, main_code: String) -> Result<(), RPUError> {
+    /// Compiles the source, blocks which did not change since the last successful compile keep their objects.
+    /// On failure the previously compiled scene is kept.
+    pub fn compile(&mut self, main_code: String) -> Result<(), Vec<RPUError>> {
 
-        let mut compiler = Compiler::new();
+        let mut compiler = self.compiler();
 
-        let rc = compiler.compile(main_code);
+        let rc = match &mut self.context {
+            Some(previous) => compiler.recompile(main_code, previous),
+            None => compiler.compile(main_code),
+        };
         if rc.is_err() {
             return Err(rc.err().unwrap());
         }
@@ -60,17 +80,113 @@ impl RPU {
         Ok(())
     }
 
-    pub fn render(&mut self, frame: &mut [u8], rect: (usize, usize, usize, usize), stride: usize) {
+    /// The errors, warnings and infos of the last compile and the errors of the scripts while rendering.
+    pub fn diagnostics(&self) -> Vec<RPUError> {
+        self.diagnostics.entries()
+    }
+
+    /// Sets how the warnings with the given code are reported, i.e. "unused-texture", used by the next compile.
+    pub fn set_warning_level(&mut self, code: &str, level: WarningLevel) {
+        self.warning_levels.retain(|(c, _)| c != code);
+        self.warning_levels.push((code.to_string(), level));
+    }
+
+    /// Sets the limits of the scripts, used by the next compile.
+    pub fn set_script_limits(&mut self, limits: ScriptLimits) {
+        self.script_limits = limits;
+    }
+
+    /// A compiler reporting into the diagnostics with the configured warning levels.
+    fn compiler(&self) -> Compiler {
+        let mut compiler = Compiler::new();
+        compiler.set_diagnostics(self.diagnostics.clone());
+        compiler.set_script_limits(self.script_limits.clone());
+        for (code, level) in &self.warning_levels {
+            compiler.set_warning_level(code, *level);
+        }
+        compiler
+    }
+
+    /// Compiles a scene written as JSON, see RPU::to_json.
+    pub fn compile_json(&mut self, json: &str) -> Result<(), Vec<RPUError>> {
+        match Document::from_json(json) {
+            Ok(document) => self.compile(Formatter::new().format_document(&document)),
+            Err(err) => Err(vec![RPUError::new(ErrorType::Syntax, err, 0)]),
+        }
+    }
+
+    /// Writes the compiled scene back to RPU source, with the current values of all properties.
+    pub fn to_source(&self) -> Option<String> {
+        self.context.as_ref().map(|context| context.to_source())
+    }
+
+    /// Writes the compiled scene as JSON.
+    pub fn to_json(&self) -> Option<String> {
+        self.context.as_ref().map(|context| context.to_json())
+    }
+
+    /// Renders the scene into the rect of the frame. If a script exceeds its limits the frame is rendered
+    /// without it and the error is returned, it is also reported in the diagnostics. The scripts see the
+    /// seconds since the first rendered frame as time.
+    pub fn render(&mut self, frame: &mut [u8], rect: (usize, usize, usize, usize), stride: usize) -> Result<(), RenderError> {
+        let start = *self.start.get_or_insert_with(Instant::now);
+        self.render_at(start.elapsed().as_secs_f64(), frame, rect, stride)
+    }
+
+    /// Renders the scene like render() at the given time in seconds, i.e. to write an animation as a sequence
+    /// of frames. The frame number seen by the scripts increases with every rendered frame.
+    pub fn render_at(&mut self, time: F, frame: &mut [u8], rect: (usize, usize, usize, usize), stride: usize) -> Result<(), RenderError> {
 
         if rect.2 != self.color.size[0] || rect.3 != self.color.size[1] {
             self.color = ColorBuffer::new(rect.2, rect.3, 0.0);
         }
 
+        let mut rc = Ok(());
         if let Some(context) = &mut self.context {
-            context.render_distributed(&mut self.color);
+            context.clock.set(time, self.frame);
+            rc = context.render_distributed(&mut self.color);
         }
 
+        self.frame += 1;
+
         self.copy_slice_float_to_u8(frame, &self.color.pixels[..], &rect, stride);
+        rc
+    }
+
+    /// Fires an event on an object or a texture, i.e. call("door", "open", &[Value::F(2.0)]) runs the
+    /// on_open(speed) code block of the object with the instance symbol door. The block changes the
+    /// properties of the object, the next rendered frame shows the changes.
+    pub fn call(&mut self, target: &str, event: &str, args: &[Value]) -> Result<(), RPUError> {
+        match &mut self.context {
+            Some(context) => context.call(target, event, args),
+            None => Err(RPUError::new(ErrorType::Script, "There is no compiled scene.".to_string(), 0)),
+        }
+    }
+
+    /// The paths of the objects whose properties can be read and set: the camera "Pinhole", the named
+    /// textures with their layers like "Bricks/Noise" and the objects with an instance symbol.
+    pub fn paths(&self) -> Vec<String> {
+        self.context.as_ref().map(|context| context.paths()).unwrap_or_default()
+    }
+
+    /// The properties of the object at the path with their types, ranges and current values.
+    pub fn properties(&self, path: &str) -> Vec<(PropertyDef, Option<Value>)> {
+        self.context.as_ref().map(|context| context.properties(path)).unwrap_or_default()
+    }
+
+    /// The current value of a property, i.e. get_property("Bricks/Noise.scale").
+    pub fn get_property(&self, path: &str) -> Option<Value> {
+        self.context.as_ref().and_then(|context| context.get_property(path))
+    }
+
+    /// Sets a property of the compiled scene without compiling it again, i.e.
+    /// set_property("Bricks/Noise.scale", Value::F2(GF2::new(2.0, 2.0))). The value is checked like in
+    /// the source, the next rendered frame shows the change.
+    pub fn set_property(&mut self, path: &str, value: Value) -> Result<(), RPUError> {
+        match &mut self.context {
+            Some(context) => context.set_property(path, value),
+            None => Err(RPUError::new(ErrorType::Script, "There is no compiled scene.".to_string(), 0)),
+        }
     }
 
     /// Copies rect from the source frame into the dest frame
@@ -17,6 +17,10 @@ impl Analytical for AnalyticalVoxel<'_> {
         }
     }
 
+    fn update(&mut self) {
+        self.engine.execute_block("update".to_string());
+    }
+
     /// https://www.shadertoy.com/view/4d2XWV
     fn get_distance(&self, _ray: &[nalgebra::Vector3<F>; 2]) -> Option<F>{
         Some(0.0)
@@ -33,14 +37,31 @@ impl Script for AnalyticalVoxel<'_> {
         &self.engine
     }
 
-    fn apply_properties(&mut self, props: Vec<Property>) -> Result<(), RPUError> {
-        self.engine.apply_properties(props)
+    fn schema(&self) -> Schema {
+        Schema::new("Voxel", vec![
+            PropertyDef::new("texture", PropertyType::String, None, None, "The name of the texture of the voxel."),
+        ], vec![
+            FunctionSignature::new("update", "", "Called before every frame, can modify the voxel properties."),
+        ])
+    }
+
+    fn apply_properties(&mut self, props: Vec<Property>) -> Result<(), Vec<RPUError>> {
+        let schema = self.schema();
+        self.engine.apply_properties(props, &schema)
     }
 
     fn execute(&mut self, code: String) {
         self.engine.execute(code);
     }
 
+    fn call(&mut self, event: &str, args: &[Value]) -> Result<bool, RPUError> {
+        self.engine.execute_event(event, args)
+    }
+
+    fn set_property(&mut self, name: &str, value: Value) {
+        self.engine.set_value(name, value);
+    }
+
     fn set_code_block(&mut self, name: String, code: String) {
         _ = self.engine.set_code_block(name, code);
     }
@@ -68,6 +68,9 @@ impl IndexBuffer3D {
 
     #[inline(always)]
     pub fn get(&self, x: usize, y: usize, z: usize) -> Option<usize> {
+        if x >= self.size[0] || y >= self.size[1] || z >= self.size[2] {
+            return None;
+        }
         let index = y * self.slice_size + x + z * self.size[0];
         self.elements[index]
     }
@@ -4,31 +4,37 @@ use crate::prelude::*;
 pub struct Pinhole<'a> {
 
     engine              : ScriptEngine<'a>,
+
+    origin              : GF3,
+    center              : GF3,
 }
 
 impl Camera3D for Pinhole<'_> {
 
     fn new() -> Self {
 
-        let mut engine = ScriptEngine::new();
-        engine.set_vector3("origin", Vector3::new(0.0, 2.0, 5.0));
-        engine.set_vector3("center", Vector3::new(0.0, 0.0, 0.0));
+        let mut pinhole = Self {
+            engine          : ScriptEngine::new(),
 
-        Self {
-            engine,
-        }
+            origin          : GF3::new(0.0, 2.0, 5.0),
+            center          : GF3::new(0.0, 0.0, 0.0),
+        };
+
+        pinhole.schema().apply_defaults(&mut pinhole.engine);
+        pinhole
     }
 
     fn update(&mut self) {
-        self.engine.execute_block("update".to_string());
+        if self.engine.execute_block("update".to_string()) {
+            self.read_properties();
+        }
     }
 
     #[inline(always)]
     fn gen_ray(&self, p: Vector2<F>) -> [Vector3<F>; 2] {
 
-        let origin = self.engine.get_vector3("origin").unwrap();
-        let center = self.engine.get_vector3("center").unwrap();
-
+        let origin = self.origin;
+        let center = self.center;
 
         let ww = (center - origin).normalize();
         let uu = ww.cross(&Vector3::new(0.0, 1.0, 0.0)).normalize();
@@ -51,16 +57,51 @@ impl Script for Pinhole<'_> {
         &self.engine
     }
 
-    fn apply_properties(&mut self, props: Vec<Property>) -> Result<(), RPUError> {
-        self.engine.apply_properties(props)
+    fn schema(&self) -> Schema {
+        Schema::new("Pinhole", vec![
+            PropertyDef::new("origin", PropertyType::F3, Some(Value::F3(GF3::new(0.0, 2.0, 5.0))), None, "The position of the camera."),
+            PropertyDef::new("center", PropertyType::F3, Some(Value::F3(GF3::new(0.0, 0.0, 0.0))), None, "The point the camera looks at."),
+        ], vec![
+            FunctionSignature::new("update", "", "Called before every frame, can modify the camera properties."),
+        ])
+    }
+
+    fn apply_properties(&mut self, props: Vec<Property>) -> Result<(), Vec<RPUError>> {
+        let schema = self.schema();
+        let rc = self.engine.apply_properties(props, &schema);
+        self.read_properties();
+        rc
     }
 
     fn execute(&mut self, code: String) {
         self.engine.execute(code);
     }
 
+    fn call(&mut self, event: &str, args: &[Value]) -> Result<bool, RPUError> {
+        let rc = self.engine.execute_event(event, args);
+        self.read_properties();
+        rc
+    }
+
+    fn set_property(&mut self, name: &str, value: Value) {
+        self.engine.set_value(name, value);
+        self.read_properties();
+    }
+
     fn set_code_block(&mut self, name: String, code: String) {
         _ = self.engine.set_code_block(name, code);
     }
 }
 
+impl Pinhole<'_> {
+
+    /// Copies the properties out of the scope, gen_ray() is called for every pixel.
+    fn read_properties(&mut self) {
+        if let Some(origin) = self.engine.get_vector3("origin") {
+            self.origin = origin;
+        }
+        if let Some(center) = self.engine.get_vector3("center") {
+            self.center = center;
+        }
+    }
+}
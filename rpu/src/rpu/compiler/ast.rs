@@ -0,0 +1,255 @@
+use crate::prelude::*;
+
+use serde::{Deserialize, Serialize};
+
+/// A parsed RPU source file.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Document {
+    /// The file the document was read from, None for a source string.
+    pub file                : Option<PathBuf>,
+    #[serde(skip)]
+    pub source              : String,
+
+    pub items               : Vec<Item>,
+    /// The // comments outside of code blocks and property values, in source order.
+    #[serde(skip)]
+    pub comments            : Vec<Comment>,
+}
+
+impl Document {
+
+    pub fn new(source: String, file: Option<PathBuf>) -> Self {
+        Self {
+            file,
+            source,
+            items           : vec![],
+            comments        : vec![],
+        }
+    }
+
+    /// Returns the top level blocks of the document.
+    pub fn blocks(&self) -> Vec<&Block> {
+        self.items.iter().filter_map(|item| match item {
+            Item::Block(block) => Some(block.as_ref()),
+            _ => None,
+        }).collect()
+    }
+
+    /// Returns the let bindings of the document.
+    pub fn lets(&self) -> Vec<&LetBinding> {
+        self.items.iter().filter_map(|item| match item {
+            Item::Let(binding) => Some(binding),
+            _ => None,
+        }).collect()
+    }
+
+    /// Returns the imports of the document.
+    pub fn imports(&self) -> Vec<&Import> {
+        self.items.iter().filter_map(|item| match item {
+            Item::Import(import) => Some(import),
+            _ => None,
+        }).collect()
+    }
+
+    /// Serializes the document to JSON, positions, comments and the source are not included.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    /// Reads a document serialized with to_json.
+    pub fn from_json(json: &str) -> Result<Document, String> {
+        serde_json::from_str(json).map_err(|err| err.to_string())
+    }
+
+    /// Returns the text of the given 1 based source line.
+    pub fn line_text(&self, line: usize) -> String {
+        if line == 0 {
+            return "".to_string();
+        }
+        self.source.lines().nth(line - 1).unwrap_or("").to_string()
+    }
+}
+
+/// A // comment of the document.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Comment {
+    /// The comment text, including the slashes.
+    pub text                : String,
+    /// True if code precedes the comment on its line.
+    pub trailing            : bool,
+    #[serde(skip)]
+    pub token               : Token,
+}
+
+/// A top level item of a document.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Item {
+    Import(Import),
+    Let(LetBinding),
+    Block(Box<Block>),
+}
+
+/// An import directive, i.e. Import "materials/bricks.rpu".
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Import {
+    /// The path as written in the source, relative to the importing file.
+    pub path                : String,
+    /// The token of the path string.
+    #[serde(skip)]
+    pub token               : Token,
+}
+
+/// A named constant at document scope, i.e. let wall = #a9957b. The value is kept as written in the source.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LetBinding {
+    pub name                : String,
+    pub value               : String,
+    /// A trailing // comment on the line of the binding.
+    pub comment             : Option<String>,
+    /// The let token, errors in the value are reported there.
+    #[serde(skip)]
+    pub token               : Token,
+}
+
+/// An element, object, camera or layout with its properties, code blocks and child elements.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Block {
+    /// The keyword of the block, i.e. Texture or Voxel.
+    pub kind                : String,
+    #[serde(skip)]
+    pub token               : Token,
+
+    /// The instance symbol of objects used in layouts, i.e. Voxel'a.
+    pub symbol              : Option<String>,
+    /// Output the block, i.e. Texture*.
+    pub output              : bool,
+    /// The prototype of the block, i.e. Texture : Bricks.
+    pub base                : Option<BaseRef>,
+
+    pub properties          : Vec<PropertyAssign>,
+    pub functions           : Vec<FunctionDef>,
+    pub children            : Vec<Block>,
+
+    pub map                 : Option<GridMap>,
+    /// The legend of a layout map.
+    pub legend              : Option<Legend>,
+}
+
+impl Block {
+
+    pub fn new(token: Token) -> Self {
+        Self {
+            kind            : token.lexeme.clone(),
+            token,
+
+            symbol          : None,
+            output          : false,
+            base            : None,
+
+            properties      : vec![],
+            functions       : vec![],
+            children        : vec![],
+
+            map             : None,
+            legend          : None,
+        }
+    }
+
+    /// Returns the assignment of the given property.
+    pub fn get_property(&self, name: &str) -> Option<&PropertyAssign> {
+        self.properties.iter().rev().find(|p| p.name == name)
+    }
+
+    /// The value of the name property without quotes, if any.
+    pub fn name(&self) -> Option<String> {
+        self.get_property("name").map(|p| p.value.trim_matches('"').to_string())
+    }
+}
+
+/// A reference to the prototype of a block. Textures derive from a texture name, objects from an
+/// instance symbol, i.e. Texture : Bricks or Voxel'b : 'a.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BaseRef {
+    /// The texture name or the instance symbol.
+    pub name                : String,
+    /// True if the prototype is referenced by its instance symbol.
+    pub symbol              : bool,
+    #[serde(skip)]
+    pub token               : Token,
+}
+
+/// A property assignment, i.e. color = #a9957b. The value is kept as written in the source.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PropertyAssign {
+    pub name                : String,
+    pub value               : String,
+    /// A trailing // comment on the line of the assignment.
+    pub comment             : Option<String>,
+    /// The token of the property name.
+    #[serde(skip)]
+    pub token               : Token,
+}
+
+/// A code block, i.e. shader(uv, size) followed by an indented Rhai body.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FunctionDef {
+    pub name                : String,
+    pub args                : Vec<String>,
+    pub body                : String,
+    /// The token of the function name.
+    #[serde(skip)]
+    pub token               : Token,
+}
+
+/// The map of a layout, each row is a line starting with one or more colons.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GridMap {
+    pub rows                : Vec<GridRow>,
+}
+
+/// A row of a layout map. Every colon after the first starts a new level, the text is kept byte for byte.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GridRow {
+    pub colons              : usize,
+    pub text                : String,
+    /// The token of the first colon.
+    #[serde(skip)]
+    pub token               : Token,
+}
+
+/// The legend of a layout map, maps glyphs or codes of several characters to instance symbols. The
+/// cells of the map are as wide as the longest key.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Legend {
+    pub entries             : Vec<LegendEntry>,
+    /// A trailing // comment on the line of the Legend keyword.
+    pub comment             : Option<String>,
+    /// The token of the Legend keyword.
+    #[serde(skip)]
+    pub token               : Token,
+}
+
+impl Legend {
+
+    /// The width of a map cell in characters.
+    pub fn cell_width(&self) -> usize {
+        self.entries.iter().map(|e| e.key.chars().count()).max().unwrap_or(1).max(1)
+    }
+
+    /// Returns the entry with the given key.
+    pub fn get(&self, key: &str) -> Option<&LegendEntry> {
+        self.entries.iter().find(|e| e.key == key)
+    }
+}
+
+/// An entry of a legend, i.e. D> = 'door, rotation = 90. The value is kept as written in the source.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LegendEntry {
+    pub key                 : String,
+    pub value               : String,
+    /// A trailing // comment on the line of the entry.
+    pub comment             : Option<String>,
+    /// The token of the key.
+    #[serde(skip)]
+    pub token               : Token,
+}
@@ -3,6 +3,21 @@ use std::collections::HashMap;
 use crate::prelude::*;
 use rayon::{slice::ParallelSliceMut, iter::{IndexedParallelIterator, ParallelIterator}};
 
+/// A node or the camera, addressed by a path.
+#[derive(PartialEq, Clone, Copy, Debug)]
+enum Target {
+    Node(usize),
+    Camera,
+}
+
+/// The place of an object in the context.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Slot {
+    Node(usize),
+    Sprite(usize),
+    Layout(usize),
+}
+
 pub struct Context {
     pub nodes                   : Vec<Node>,
 
@@ -12,7 +27,7 @@ pub struct Context {
 
     pub layouts                 : Vec<Object>,
 
-    pub symbols_node_index      : HashMap<char, usize>,
+    pub symbols_node_index      : HashMap<String, usize>,
 
     pub renderer                : Box<dyn Renderer>,
     pub camera                  : Box<dyn Camera3D>,
@@ -20,6 +35,21 @@ pub struct Context {
     pub size                    : [usize; 2],
 
     pub out_texture             : Option<usize>,
+
+    /// The constants of the documents, used when writing the context back to source.
+    pub constants               : Vec<(String, Value)>,
+    /// The Layer blocks of the documents, used when writing the context back to source.
+    pub layers                  : Vec<Block>,
+
+    /// The diagnostics of the compiler and of the scripts while rendering.
+    pub diagnostics             : Diagnostics,
+    /// The limits of the scripts, checked for every rendered frame.
+    pub budget                  : ScriptBudget,
+    /// The time and the frame the scripts see, set before every rendered frame.
+    pub clock                   : Clock,
+
+    /// The keys of the blocks the objects were created from, recompiling reuses the objects of unchanged blocks.
+    pub block_keys              : Vec<(Slot, u64)>,
 }
 
 impl Context {
@@ -41,23 +71,56 @@ impl Context {
             size                : [0, 0],
 
             out_texture         : None,
+
+            constants           : vec![],
+            layers              : vec![],
+
+            diagnostics         : Diagnostics::new(),
+            budget              : ScriptBudget::default(),
+            clock               : Clock::new(),
+
+            block_keys          : vec![],
         }
     }
 
-    pub fn init(&mut self) {
-        // Render textures
+    /// Takes the object out of the given slot, an empty object is left behind.
+    pub fn take_object(&mut self, slot: Slot) -> Object {
+        match slot {
+            Slot::Node(index) => std::mem::replace(&mut self.nodes[index].object, Object::Empty),
+            Slot::Sprite(index) => Object::Element2D(std::mem::replace(&mut self.sprites[index], Box::new(Sprite::new()))),
+            Slot::Layout(index) => std::mem::replace(&mut self.layouts[index], Object::Empty),
+        }
+    }
 
-        /*
-        for node_index in &self.textures {
-            match &mut self.nodes[*node_index].object {
-                Object::Element2D(el) => {
-                    el.render(*node_index, &self);
-                },
-                _ => {}
+    /// Puts the object back into the given slot.
+    pub fn put_object(&mut self, slot: Slot, object: Object) {
+        match (slot, object) {
+            (Slot::Node(index), object) => self.nodes[index].object = object,
+            (Slot::Sprite(index), Object::Element2D(sprite)) => self.sprites[index] = sprite,
+            (Slot::Layout(index), object) => self.layouts[index] = object,
+            _ => {},
+        }
+    }
+
+    pub fn init(&mut self) {
+        // Bake the textures with a size, textures reused by a recompile keep their bake
+        for index in self.textures.clone() {
+            if matches!(&self.nodes[index].object, Object::Element2D(element) if element.get_size() == [0, 0]) {
+                self.bake(index);
             }
-        }*/
+        }
     }
 
+    /// Renders the texture of the node into its buffer, the texture is moved out of the node while it renders.
+    fn bake(&mut self, index: usize) {
+        let mut object = std::mem::replace(&mut self.nodes[index].object, Object::Empty);
+        if let Object::Element2D(element) = &mut object {
+            element.render(index, self);
+        }
+        self.nodes[index].object = object;
+    }
+
+    /// Runs the update() code blocks of the camera and of all objects, textures and sprites.
     pub fn update(&mut self) {
         self.camera.update();
         for o in &mut self.nodes {
@@ -65,12 +128,202 @@ impl Context {
                 Object::AnalyticalObject(object) => {
                     object.update();
                 },
+                Object::SDF3D(object) => {
+                    object.update();
+                },
+                Object::Element2D(element) => {
+                    element.update();
+                },
                 _ => {},
             }
         }
+        for sprite in &mut self.sprites {
+            sprite.update();
+        }
+    }
+
+    /// Runs the on_<event>() code block of the object with the given instance symbol or of the texture with the
+    /// given name. The block changes the properties of its owner, the next frame shows the changes.
+    pub fn call(&mut self, target: &str, event: &str, args: &[Value]) -> Result<(), RPUError> {
+        let index = self.find_node(target);
+
+        let rc = match index.map(|index| &mut self.nodes[index].object) {
+            Some(Object::AnalyticalObject(object)) => object.call(event, args),
+            Some(Object::SDF3D(object)) => object.call(event, args),
+            Some(Object::Element2D(element)) => element.call(event, args),
+            _ => return Err(RPUError::new(ErrorType::Script, format!("No object with the instance symbol '{}' and no texture named '{}'.", target, target), 0).with_code("unknown-event")),
+        };
+
+        match rc {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(RPUError::new(ErrorType::Script, format!("'{}' has no on_{}() code block.", target, event), 0).with_code("unknown-event")),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// The paths of the camera, the named textures with their layers and the objects with an instance symbol.
+    pub fn paths(&self) -> Vec<String> {
+        let mut paths = vec!["Pinhole".to_string()];
+        for index in &self.textures {
+            if let Object::Element2D(texture) = &self.nodes[*index].object {
+                if let Some(name) = texture.get_engine().get_string("name") {
+                    self.layer_paths(*index, name, &mut paths);
+                }
+            }
+        }
+
+        let mut symbols : Vec<(&String, &usize)> = self.symbols_node_index.iter().filter(|(_, index)| self.objects.contains(index)).collect();
+        symbols.sort_by_key(|(_, index)| **index);
+        paths.extend(symbols.into_iter().map(|(symbol, _)| symbol.clone()));
+        paths
+    }
+
+    /// The properties of the object at the path with their current values.
+    pub fn properties(&self, path: &str) -> Vec<(PropertyDef, Option<Value>)> {
+        match self.find(path).and_then(|target| self.script(target)) {
+            Some((schema, engine)) => schema.properties.into_iter().map(|p| {
+                let value = engine.get_value(&p.name);
+                (p, value)
+            }).collect(),
+            None => vec![],
+        }
+    }
+
+    /// The value of a property, the path is the path of the object followed by the property, i.e. "Bricks/Noise.scale".
+    pub fn get_property(&self, path: &str) -> Option<Value> {
+        let (path, name) = path.rsplit_once('.')?;
+        let (schema, engine) = self.find(path).and_then(|target| self.script(target))?;
+        schema.get(name)?;
+        engine.get_value(name)
+    }
+
+    /// Sets a property, the value is checked against the type and the range of the property like a value in the source.
+    pub fn set_property(&mut self, path: &str, value: Value) -> Result<(), RPUError> {
+        let error = |message: String| RPUError::new(ErrorType::Syntax, message, 0);
+
+        let Some((path, name)) = path.rsplit_once('.') else {
+            return Err(error(format!("Expected a path like 'Bricks/Noise.scale', found '{}'.", path)));
+        };
+        let Some(target) = self.find(path) else {
+            return Err(error(format!("'{}' is neither a texture, a layer of a texture nor an instance symbol.", path)));
+        };
+        let Some((schema, _)) = self.script(target) else {
+            return Err(error(format!("'{}' has no properties.", path)));
+        };
+        let value = match schema.get(name) {
+            Some(def) => def.check(value).map_err(error)?,
+            None => return Err(error(schema.unknown_property(name))),
+        };
+
+        // Objects reference their texture by its node
+        let mut texture = None;
+        if let ("texture", Target::Node(_), Value::String(texture_name)) = (name, target, &value) {
+            match self.find_texture(texture_name) {
+                Some(index) => texture = Some(index),
+                None => return Err(error(format!("Unknown texture '{}'.", texture_name))),
+            }
+        }
+
+        match target {
+            Target::Camera => self.camera.set_property(name, value),
+            Target::Node(index) => match &mut self.nodes[index].object {
+                Object::AnalyticalObject(object) => object.set_property(name, value),
+                Object::SDF3D(object) => object.set_property(name, value),
+                Object::Element2D(element) => element.set_property(name, value),
+                _ => {},
+            },
+        }
+
+        if let (Target::Node(index), Some(texture)) = (target, texture) {
+            self.nodes[index].texture = Some(texture);
+        }
+
+        // The object no longer matches its block, recompiling creates it again
+        if let Target::Node(index) = target {
+            self.block_keys.retain(|(slot, _)| *slot != Slot::Node(index));
+        }
+
+        // A texture bakes its layers in, it is baked again and created again like the layer
+        if let Some(texture) = path.split('/').next().and_then(|name| self.find_node(name)).filter(|index| self.textures.contains(index)) {
+            self.block_keys.retain(|(slot, _)| *slot != Slot::Node(texture));
+            self.bake(texture);
+        }
+        Ok(())
     }
 
-    pub fn render_distributed(&mut self, color: &mut ColorBuffer<F>) {
+    /// Finds the object of a path. The path starts with an instance symbol or the name of a texture, the
+    /// layers of a texture follow separated by slashes, i.e. "Bricks/Noise". The second layer of the same
+    /// kind is "Noise[1]". "Pinhole" is the camera.
+    fn find(&self, path: &str) -> Option<Target> {
+        let mut parts = path.split('/');
+        let mut index = match self.find_node(parts.next()?) {
+            Some(index) => index,
+            None if path == "Pinhole" => return Some(Target::Camera),
+            None => return None,
+        };
+
+        for part in parts {
+            let (kind, nth) = match part.strip_suffix(']').and_then(|p| p.split_once('[')) {
+                Some((kind, nth)) => (kind, nth.parse::<usize>().ok()?),
+                None => (part, 0),
+            };
+            index = self.layers(index).into_iter().filter(|(name, _)| name == kind).nth(nth)?.1;
+        }
+        Some(Target::Node(index))
+    }
+
+    /// The node of the object with the given instance symbol or of the texture with the given name.
+    fn find_node(&self, name: &str) -> Option<usize> {
+        self.symbols_node_index.get(name).copied().filter(|index| self.objects.contains(index))
+            .or_else(|| self.find_texture(name))
+    }
+
+    /// The node of the texture with the given name.
+    fn find_texture(&self, name: &str) -> Option<usize> {
+        self.textures.iter().copied().find(|index| match &self.nodes[*index].object {
+            Object::Element2D(texture) => texture.get_engine().get_string("name").as_deref() == Some(name),
+            _ => false,
+        })
+    }
+
+    /// The layers and layouts of a texture or a layout with their names.
+    fn layers(&self, index: usize) -> Vec<(String, usize)> {
+        let node = &self.nodes[index];
+        node.childs.iter().chain(&node.elements).filter_map(|child| match &self.nodes[*child].object {
+            Object::Element2D(element) => Some((element.name(), *child)),
+            _ => None,
+        }).collect()
+    }
+
+    /// Adds the path of the node and the paths of its layers.
+    fn layer_paths(&self, index: usize, path: String, paths: &mut Vec<String>) {
+        paths.push(path.clone());
+        let layers = self.layers(index);
+        for (i, (name, child)) in layers.iter().enumerate() {
+            let nth = layers[..i].iter().filter(|(n, _)| n == name).count();
+            let part = if nth == 0 { name.clone() } else { format!("{}[{}]", name, nth) };
+            self.layer_paths(*child, format!("{}/{}", path, part), paths);
+        }
+    }
+
+    /// The schema and the script engine of the target, None if it has no properties.
+    fn script(&self, target: Target) -> Option<(Schema, &ScriptEngine<'_>)> {
+        match target {
+            Target::Camera => Some((self.camera.schema(), self.camera.get_engine())),
+            Target::Node(index) => match &self.nodes[index].object {
+                Object::AnalyticalObject(object) => Some((object.schema(), object.get_engine())),
+                Object::SDF3D(object) => Some((object.schema(), object.get_engine())),
+                Object::Element2D(element) => Some((element.schema(), element.get_engine())),
+                _ => None,
+            },
+        }
+    }
+
+    /// Renders the frame, fails if a script exceeded its limits. The frame is rendered nevertheless, without
+    /// the stopped scripts.
+    pub fn render_distributed(&mut self, color: &mut ColorBuffer<F>) -> Result<(), RenderError> {
+        self.budget.start_frame();
+
         self.size = color.size.clone();
         let [width, height] = color.size;
 
@@ -127,6 +380,8 @@ impl Context {
                     }
                 }
             });
+
+        self.budget.finish_frame()
     }
 
     /*
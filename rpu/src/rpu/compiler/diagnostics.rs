@@ -0,0 +1,105 @@
+use crate::prelude::*;
+
+use std::sync::{Arc, Mutex};
+
+/// The severity of a diagnostic.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+impl Severity {
+
+    /// The name of the severity as used in reports.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "info",
+        }
+    }
+}
+
+/// How the warnings with a given code are reported.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WarningLevel {
+    /// The warning is not reported.
+    Allow,
+    /// The warning is reported as a warning, the default.
+    Warn,
+    /// The warning is reported as an error and fails the compile.
+    Deny,
+}
+
+#[derive(Default)]
+struct Entries {
+    entries                 : Vec<RPUError>,
+    /// The keys of the diagnostics pushed with push_once().
+    once                    : Vec<String>,
+}
+
+/// Collects the errors, warnings and infos of the compiler and of the scripts while rendering. The collector
+/// is a shared handle, clones report into the same list, and identical diagnostics are only kept once.
+#[derive(Clone, Default)]
+pub struct Diagnostics {
+    entries                 : Arc<Mutex<Entries>>,
+}
+
+impl Diagnostics {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the diagnostic, unless an identical one was already reported.
+    pub fn push(&self, diagnostic: RPUError) {
+        let entries = &mut self.entries.lock().unwrap().entries;
+        let exists = entries.iter().any(|d| {
+            d.severity == diagnostic.severity && d.code == diagnostic.code && d.description == diagnostic.description &&
+            d.line == diagnostic.line && d.column == diagnostic.column && d.file == diagnostic.file
+        });
+        if !exists {
+            entries.push(diagnostic);
+        }
+    }
+
+    /// Adds the diagnostic created by the closure, unless a diagnostic with the same key was pushed since the
+    /// diagnostics were cleared, i.e. so that a shader failing for every pixel is reported a single time.
+    pub fn push_once(&self, key: String, diagnostic: impl FnOnce() -> RPUError) {
+        let mut entries = self.entries.lock().unwrap();
+        if !entries.once.contains(&key) {
+            entries.once.push(key);
+            entries.entries.push(diagnostic());
+        }
+    }
+
+    /// All diagnostics in the order they were reported.
+    pub fn entries(&self) -> Vec<RPUError> {
+        self.entries.lock().unwrap().entries.clone()
+    }
+
+    /// The diagnostics of the given severity.
+    pub fn with_severity(&self, severity: Severity) -> Vec<RPUError> {
+        self.entries.lock().unwrap().entries.iter().filter(|d| d.severity == severity).cloned().collect()
+    }
+
+    pub fn errors(&self) -> Vec<RPUError> {
+        self.with_severity(Severity::Error)
+    }
+
+    pub fn warnings(&self) -> Vec<RPUError> {
+        self.with_severity(Severity::Warning)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.lock().unwrap().entries.is_empty()
+    }
+
+    pub fn clear(&self) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.entries.clear();
+        entries.once.clear();
+    }
+}
@@ -0,0 +1,364 @@
+use crate::prelude::*;
+
+/// Reprints RPU source canonically. Nested elements are indented by a fixed number of spaces, property values
+/// get consistent spacing and lower case hex colors. Comments are kept and the rows of layout maps are
+/// reprinted byte for byte.
+pub struct Formatter {
+    /// The number of spaces per indentation level.
+    pub indent          : usize,
+}
+
+impl Default for Formatter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Formatter {
+
+    pub fn new() -> Self {
+        Self {
+            indent      : 4,
+        }
+    }
+
+    /// Formats the given source. Sources with syntax errors are not formatted, the errors are returned instead.
+    pub fn format(&self, code: String, file: Option<PathBuf>) -> Result<String, Vec<RPUError>> {
+        let (document, errors) = Compiler::parse(code, file);
+        if errors.is_empty() {
+            Ok(self.format_document(&document))
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Formats the file at the given path, the file itself is not changed.
+    pub fn format_path(&self, path: PathBuf) -> Result<String, Vec<RPUError>> {
+        if let Ok(code) = std::fs::read_to_string(&path) {
+            self.format(code, Some(path))
+        } else {
+            Err(vec![RPUError::new(ErrorType::FileNotFound, format!("File '{}' not found.", path.display()), 0)])
+        }
+    }
+
+    /// Reprints the given document.
+    pub fn format_document(&self, document: &Document) -> String {
+        let mut writer = Writer {
+            out             : String::new(),
+            lines           : document.source.lines().collect(),
+            indent          : self.indent,
+            comments        : document.comments.iter().filter(|c| !c.trailing).collect(),
+            next_comment    : 0,
+            last_line       : 0,
+            allow_blank     : false,
+            force_blank     : false,
+        };
+
+        let mut previous_single_line = false;
+        for item in &document.items {
+            match item {
+                Item::Import(import) => {
+                    writer.force_blank = !previous_single_line;
+                    let mut text = format!("Import \"{}\"", import.path);
+                    if let Some(comment) = trailing_comment(document, import.token.line) {
+                        text += " ";
+                        text += comment.as_str();
+                    }
+                    writer.comments_before(import.token.line, 0);
+                    writer.emit(import.token.line, 0, text);
+                    previous_single_line = true;
+                },
+                Item::Let(binding) => {
+                    // Consecutive bindings are kept together, like imports
+                    writer.force_blank = !previous_single_line;
+                    let mut text = format!("let {} = {}", binding.name, canonical_value(&binding.value));
+                    if let Some(comment) = &binding.comment {
+                        text += " ";
+                        text += comment.as_str();
+                    }
+                    writer.comments_before(binding.token.line, 0);
+                    writer.emit(binding.token.line, 0, text);
+                    previous_single_line = true;
+                },
+                Item::Block(block) => {
+                    writer.force_blank = true;
+                    writer.block(document, block, 0);
+                    previous_single_line = false;
+                }
+            }
+        }
+
+        writer.comments_before(usize::MAX, 0);
+        writer.out
+    }
+}
+
+/// Writes the lines of a document, blank lines of the source are kept inside blocks (at most one in a row).
+struct Writer<'a> {
+    out                 : String,
+    lines               : Vec<&'a str>,
+    indent              : usize,
+
+    /// The full line comments of the document, written before the next line following them.
+    comments            : Vec<&'a Comment>,
+    next_comment        : usize,
+
+    /// The last source line written.
+    last_line           : usize,
+    /// Keep a blank line preceding the next line in the source.
+    allow_blank         : bool,
+    /// Write a blank line before the next line.
+    force_blank         : bool,
+}
+
+/// A property, code block or child element, written in source order.
+enum Entry<'a> {
+    Property(&'a PropertyAssign),
+    Function(&'a FunctionDef),
+    Child(&'a Block),
+    Legend(&'a Legend),
+}
+
+impl Entry<'_> {
+    fn line(&self) -> usize {
+        match self {
+            Entry::Property(p) => p.token.line,
+            Entry::Function(f) => f.token.line,
+            Entry::Child(b) => b.token.line,
+            Entry::Legend(l) => l.token.line,
+        }
+    }
+}
+
+impl Writer<'_> {
+
+    /// Writes a block with all its entries and its map.
+    fn block(&mut self, document: &Document, block: &Block, depth: usize) {
+        let mut header = block.kind.clone();
+        if block.output {
+            header += "*";
+        }
+        if let Some(symbol) = &block.symbol {
+            header += "'";
+            header += symbol;
+        }
+        if let Some(base) = &block.base {
+            header += " : ";
+            if base.symbol {
+                header += "'";
+                header += base.name.as_str();
+            } else
+            if is_identifier(&base.name) {
+                header += base.name.as_str();
+            } else {
+                header += format!("\"{}\"", base.name).as_str();
+            }
+        }
+        if let Some(comment) = trailing_comment(document, block.token.line) {
+            header += " ";
+            header += comment.as_str();
+        }
+        self.comments_before(block.token.line, depth);
+        self.emit(block.token.line, depth, header);
+        self.allow_blank = false;
+
+        let mut entries : Vec<Entry> = vec![];
+        entries.extend(block.properties.iter().map(Entry::Property));
+        entries.extend(block.functions.iter().map(Entry::Function));
+        entries.extend(block.children.iter().map(Entry::Child));
+        entries.extend(block.legend.iter().map(Entry::Legend));
+        entries.sort_by_key(|e| e.line());
+
+        for entry in entries {
+            match entry {
+                Entry::Property(p) => {
+                    let mut text = format!("{} = {}", p.name, canonical_value(&p.value));
+                    if let Some(comment) = &p.comment {
+                        text += " ";
+                        text += comment.as_str();
+                    }
+                    self.comments_before(p.token.line, depth + 1);
+                    self.emit(p.token.line, depth + 1, text);
+                },
+                Entry::Function(f) => {
+                    self.comments_before(f.token.line, depth + 1);
+                    self.function(f, depth + 1);
+                },
+                Entry::Child(child) => {
+                    self.block(document, child, depth + 1);
+                },
+                Entry::Legend(legend) => {
+                    let mut header = "Legend".to_string();
+                    if let Some(comment) = &legend.comment {
+                        header += " ";
+                        header += comment.as_str();
+                    }
+                    self.comments_before(legend.token.line, depth + 1);
+                    self.emit(legend.token.line, depth + 1, header);
+                    self.allow_blank = false;
+
+                    for entry in &legend.entries {
+                        let mut text = format!("{} = {}", entry.key, canonical_value(&entry.value));
+                        if let Some(comment) = &entry.comment {
+                            text += " ";
+                            text += comment.as_str();
+                        }
+                        self.comments_before(entry.token.line, depth + 2);
+                        self.emit(entry.token.line, depth + 2, text);
+                    }
+                }
+            }
+        }
+
+        // Comments at the end of the block, indented deeper than the header
+        while let Some(comment) = self.comments.get(self.next_comment).copied() {
+            let line = comment.token.line;
+            let inside = comment.token.column > block.token.column && (self.last_line + 1..line).all(|l| {
+                let text = self.line_text(l).trim();
+                text.is_empty() || text.starts_with("//")
+            });
+            if !inside {
+                break;
+            }
+            self.comments_before(line + 1, depth + 1);
+        }
+
+        if let Some(map) = &block.map {
+            for row in &map.rows {
+                self.comments_before(row.token.line, 0);
+                self.emit(row.token.line, 0, format!("{}{}", ":".repeat(row.colons), row.text));
+            }
+        }
+    }
+
+    /// Writes a code block, the body is reindented and keeps its relative indentation.
+    fn function(&mut self, function: &FunctionDef, depth: usize) {
+        let mut lines = function.body.split('\n');
+
+        let mut header = format!("{}({})", function.name, function.args.join(", "));
+        let first = lines.next().unwrap_or("").trim();
+        if !first.is_empty() {
+            header += " ";
+            header += first;
+        }
+        self.emit(function.token.line, depth, header);
+
+        let body : Vec<&str> = lines.map(|l| l.trim_end()).collect();
+        let min_indent = body.iter().filter(|l| !l.is_empty()).map(|l| l.len() - l.trim_start().len()).min().unwrap_or(0);
+        let count = body.iter().rposition(|l| !l.is_empty()).map(|i| i + 1).unwrap_or(0);
+
+        let mut blank = false;
+        for (index, line) in body[..count].iter().enumerate() {
+            if line.is_empty() {
+                if !blank {
+                    self.out.push('\n');
+                }
+                blank = true;
+            } else {
+                let extra = line.len() - line.trim_start().len() - min_indent;
+                self.out += " ".repeat((depth + 1) * self.indent + extra).as_str();
+                self.out += line.trim_start();
+                self.out.push('\n');
+                blank = false;
+            }
+            self.last_line = function.token.line + index + 1;
+        }
+    }
+
+    /// Writes all full line comments preceding the given source line.
+    fn comments_before(&mut self, line: usize, depth: usize) {
+        while let Some(comment) = self.comments.get(self.next_comment).copied() {
+            if comment.token.line >= line {
+                break;
+            }
+            self.next_comment += 1;
+            self.emit(comment.token.line, depth, comment.text.clone());
+        }
+    }
+
+    /// Writes a line with the given indentation level.
+    fn emit(&mut self, line: usize, depth: usize, text: String) {
+        let blank_in_source = line > 1 && self.line_text(line - 1).trim().is_empty();
+        if (self.force_blank || (self.allow_blank && blank_in_source)) && !self.out.is_empty() {
+            self.out.push('\n');
+        }
+        self.force_blank = false;
+        self.allow_blank = true;
+
+        self.out += " ".repeat(depth * self.indent).as_str();
+        self.out += text.as_str();
+        self.out.push('\n');
+        self.last_line = line;
+    }
+
+    /// The text of the given 1 based source line.
+    fn line_text(&self, line: usize) -> &str {
+        if line == 0 {
+            return "";
+        }
+        self.lines.get(line - 1).copied().unwrap_or("")
+    }
+}
+
+/// True if the name can be written without quotes.
+fn is_identifier(name: &str) -> bool {
+    name.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_') && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// The trailing comment on the given line, if any.
+fn trailing_comment(document: &Document, line: usize) -> Option<String> {
+    document.comments.iter().find(|c| c.trailing && c.token.line == line).map(|c| c.text.clone())
+}
+
+/// Reprints a property value with one space after commas, no spaces inside parentheses, single spaces
+/// between other tokens and lower case hex colors. Strings are kept as they are.
+pub fn canonical_value(value: &str) -> String {
+    let mut out = String::new();
+    let mut string : Option<char> = None;
+    let mut space = false;
+    let mut hex = false;
+
+    for c in value.trim().chars() {
+        if let Some(quote) = string {
+            out.push(c);
+            if c == quote {
+                string = None;
+            }
+            continue;
+        }
+
+        match c {
+            ' ' | '\t' => {
+                space = true;
+                hex = false;
+            },
+            ',' => {
+                out.push(',');
+                space = true;
+                hex = false;
+            },
+            ')' => {
+                out.push(')');
+                space = false;
+                hex = false;
+            },
+            _ => {
+                if space && !out.ends_with('(') {
+                    out.push(' ');
+                }
+                space = false;
+
+                if c == '"' || c == '`' {
+                    string = Some(c);
+                }
+                if hex && c.is_ascii_hexdigit() {
+                    out.push(c.to_ascii_lowercase());
+                } else {
+                    hex = c == '#';
+                    out.push(c);
+                }
+            }
+        }
+    }
+    out
+}
@@ -1,4 +1,9 @@
 pub mod scanner;
+pub mod ast;
+pub mod parser;
+pub mod formatter;
+pub mod writer;
+pub mod diagnostics;
 pub mod context;
 pub mod node;
 pub mod object;
@@ -7,673 +12,1395 @@ use colors_transform::Color;
 
 use crate::prelude::*;
 
-use self::scanner::TokenType;
+/// The keywords of the blocks at the top level of a document.
+pub const TOP_LEVEL_ELEMENTS : [&str; 9] = ["Texture", "Layer", "Sprite", "Voxel", "sdfCube", "sdfSphere", "sdfCustom", "Pinhole", "Grid3D"];
 
-#[derive(Clone, Debug)]
+/// The keywords of the elements which can be used inside a texture.
+pub const TEXTURE_ELEMENTS : [&str; 14] = ["Color", "Noise", "Bricks", "Texture", "Vertical", "Checker", "Gradient",
+    "Voronoi", "Tiles", "Hexagons", "Stones", "Planks", "Marble", "Stripes"];
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum ErrorType {
     Syntax,
     FileNotFound,
+    /// An error of a script while rendering.
+    Script,
+}
+
+impl ErrorType {
+
+    /// The default code of diagnostics of this type.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ErrorType::Syntax => "syntax",
+            ErrorType::FileNotFound => "file-not-found",
+            ErrorType::Script => "script",
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
+/// An error, warning or info of the compiler or of a script, with its location in the source.
 pub struct RPUError {
     pub error_type              : ErrorType,
+    pub severity                : Severity,
+    /// Identifies the kind of diagnostic, i.e. "unknown-texture". The level of warnings can be set per code.
+    pub code                    : &'static str,
     pub description             : String,
     pub line                    : u32,
+    /// 1 based column of the error, 0 if unknown.
+    pub column                  : u32,
+    /// Byte span of the offending source code.
+    pub span                    : (usize, usize),
+    /// The offending lexeme.
+    pub lexeme                  : String,
+    /// The text of the source line, used for the report.
+    pub source_line             : String,
+    /// The file the error occured in, None for the main source string.
+    pub file                    : Option<PathBuf>,
 }
 
 impl RPUError {
     pub fn new(error_type: ErrorType, description: String, line: u32) -> Self {
         Self {
+            severity            : Severity::Error,
+            code                : error_type.code(),
             error_type,
             description,
-            line
+            line,
+            column              : 0,
+            span                : (0, 0),
+            lexeme              : "".to_string(),
+            source_line         : "".to_string(),
+            file                : None,
         }
     }
-}
 
-struct Parser {
-    current             : Token,
-    previous            : Token,
+    /// Creates an error located at the given token.
+    pub fn at_token(error_type: ErrorType, description: String, token: &Token, source_line: String) -> Self {
+        Self {
+            severity            : Severity::Error,
+            code                : error_type.code(),
+            error_type,
+            description,
+            line                : token.line as u32,
+            column              : token.column as u32,
+            span                : (token.start, token.end),
+            lexeme              : token.lexeme.clone(),
+            source_line,
+            file                : None,
+        }
+    }
 
-    error               : Option<RPUError>,
-}
+    /// Sets the code of the diagnostic.
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = code;
+        self
+    }
+
+    /// Sets the severity of the diagnostic.
+    pub fn with_severity(mut self, severity: Severity) -> Self {
+        self.severity = severity;
+        self
+    }
 
-impl Parser {
+    /// Sets the file the error occured in.
+    pub fn in_file(mut self, file: Option<PathBuf>) -> Self {
+        self.file = file;
+        self
+    }
 
-    pub fn new() -> Self {
-        Self {
-            current     : Token::synthetic("".to_owned()),
-            previous    : Token::synthetic("".to_owned()),
-            error       : None,
+    /// The location of the error as "file:line:column".
+    pub fn location(&self) -> String {
+        let mut location = match &self.file {
+            Some(file) => format!("{}:{}", file.display(), self.line),
+            None => format!("line {}", self.line),
+        };
+        if self.column > 0 {
+            if self.file.is_some() {
+                location += format!(":{}", self.column).as_str();
+            } else {
+                location += format!(", column {}", self.column).as_str();
+            }
+        }
+        location
+    }
+
+    /// Renders the error with the offending source line and a caret marker under the lexeme.
+    pub fn report(&self) -> String {
+        let mut report = format!("{}[{}]: {}\n", self.severity.name(), self.code, self.description);
+
+        if self.line == 0 {
+            return report;
+        }
+
+        report += format!(" --> {}\n", self.location()).as_str();
+
+        if !self.source_line.is_empty() {
+            let number = self.line.to_string();
+            let gutter = " ".repeat(number.len());
+
+            report += format!("{} |\n", gutter).as_str();
+            report += format!("{} | {}\n", number, self.source_line).as_str();
+
+            if self.column > 0 {
+                let width = self.lexeme.lines().next().unwrap_or("").chars().count().max(1);
+                report += format!("{} | {}{}\n", gutter, " ".repeat(self.column as usize - 1), "^".repeat(width)).as_str();
+            }
+        }
+        report
+    }
+}
+
+impl std::fmt::Display for RPUError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.line > 0 {
+            write!(f, "{} at {}", self.description, self.location())
+        } else {
+            write!(f, "{}", self.description)
         }
     }
 }
 
+/// A property assignment or code block of an object, the token is the name of the property.
+#[derive(Clone)]
 pub enum Property {
-    Property(String, String),
-    Function(String, String, String),
+    Property(String, String, Token),
+    /// A code block with its name, arguments and body, the token of its name and the source line of the name.
+    Function(String, String, String, Token, String),
+    /// A constant of the document, visible to the properties and code blocks of the object.
+    Constant(String, Value),
+    /// The diagnostics of the context and the file of the object, the errors of code blocks while rendering
+    /// are reported there.
+    Diagnostics(Diagnostics, Option<PathBuf>),
+    /// The limits of the scripts of the context, checked while rendering.
+    Limits(ScriptBudget),
+    /// The clock of the context, the time and the frame of the rendered frame.
+    Clock(Clock),
 }
 
+/// A reference from an object or sprite to a texture, resolved after all blocks are created.
+enum TextureRef {
+    Node(usize),
+    Sprite(usize),
+}
 
-pub struct Compiler {
-    scanner                 : Scanner,
+/// A layer defined by a Layer block, the properties of the block are its parameters.
+struct LayerDef {
+    name                    : String,
+    parameters              : Vec<PropertyDef>,
+    /// The color(uv, input) function with the source line of its name, None if the definition has errors.
+    function                : Option<(FunctionDef, String)>,
+    /// The file of the definition, the errors of the function are reported there.
+    file                    : Option<PathBuf>,
+    /// The Layer block, the keys of the blocks change with it.
+    block                   : Block,
+}
+
+impl LayerDef {
 
-    parser                  : Parser,
+    /// The properties which add the definition to a layer using it.
+    fn properties(&self, diagnostics: &Diagnostics) -> Vec<Property> {
+        let mut props = vec![Property::Diagnostics(diagnostics.clone(), self.file.clone())];
+        if let Some((f, source_line)) = &self.function {
+            props.push(Property::Function(f.name.clone(), f.args.join(","), f.body.clone(), f.token.clone(), source_line.clone()));
+        }
+        props
+    }
+}
 
+/// The compiler parses the source into a Document and creates the Context from it in a second pass,
+/// which resolves texture names and layout symbols, so they can be used before they are defined.
+pub struct Compiler {
     elements2d              : Vec<String>,
     objects3d               : Vec<String>,
 
-    curr_parent             : Option<usize>
+    errors                  : Vec<RPUError>,
+    warnings                : Vec<RPUError>,
+
+    /// Receives the errors, warnings and infos of every compile, shared with the compiled context.
+    diagnostics             : Diagnostics,
+    /// The warning levels by code, warnings without a level are reported as warnings.
+    warning_levels          : HashMap<String, WarningLevel>,
+
+    /// The limits of the scripts and the budget shared by the objects of the compiled context.
+    limits                  : ScriptLimits,
+    budget                  : ScriptBudget,
+    /// The clock shared by the objects of the compiled context.
+    clock                   : Clock,
+
+    /// The top level textures with their node index, the token of their name, the source line, the file and
+    /// if they are defined in the compiled document itself and not in an import.
+    texture_defs            : Vec<(usize, Token, String, Option<PathBuf>, bool)>,
+    /// Texture references with the texture name, the token and the source line of the reference.
+    texture_refs            : Vec<(TextureRef, String, Token, String, Option<PathBuf>)>,
+    /// Layouts with the index of their object, their maps wait for all symbols to be defined.
+    layout_maps             : Vec<(usize, Block, Document)>,
+
+    /// The file currently being compiled, None for a source string.
+    path                    : Option<PathBuf>,
+    /// The files currently being imported, used for cycle detection.
+    import_stack            : Vec<PathBuf>,
+    /// All files imported so far, each file is only imported once.
+    imported                : Vec<PathBuf>,
+
+    /// Evaluates the let bindings of the documents, the scope holds the constants defined so far.
+    constants               : ScriptEngine<'static>,
+
+    /// The top level blocks of all documents, the prototypes of derived blocks.
+    prototypes              : Vec<Block>,
+    /// The user defined layers, textures can use them after their definition.
+    layers                  : Vec<LayerDef>,
+
+    /// The objects of the previous context by the key of their block and the slot they came from.
+    reusable                : HashMap<u64, Vec<(Slot, Object)>>,
+    /// The reused objects, the slot in the previous context and the slot in the new one.
+    reused                  : Vec<(Slot, Slot)>,
 }
 
 impl Compiler {
 
     pub fn new() -> Self {
         Self {
-            scanner         : Scanner::new("".to_string()),
-            parser          : Parser::new(),
+            elements2d      : TEXTURE_ELEMENTS.iter().map(|e| e.to_string()).collect(),
+            objects3d       : vec!["Voxel".to_string(), "sdfCube".to_string(), "sdfSphere".to_string(), "sdfCustom".to_string()],
+
+            errors          : vec![],
+            warnings        : vec![],
+
+            diagnostics     : Diagnostics::new(),
+            warning_levels  : HashMap::new(),
+
+            limits          : ScriptLimits::default(),
+            budget          : ScriptBudget::default(),
+            clock           : Clock::new(),
+
+            texture_defs    : vec![],
+            texture_refs    : vec![],
+            layout_maps     : vec![],
+
+            path            : None,
+            import_stack    : vec![],
+            imported        : vec![],
+
+            constants       : ScriptEngine::new(),
 
-            elements2d      : vec!["Texture".to_string(), "Vertical".to_string(), "Color".to_string(), "Noise".to_string(), "Bricks"
-            .to_string()],
-            objects3d       : vec!["Voxel".to_string(), "sdfCube".to_string(), "sdfSphere".to_string()],
+            prototypes      : vec![],
+            layers          : vec![],
 
-            curr_parent     : None,
+            reusable        : HashMap::new(),
+            reused          : vec![],
         }
     }
 
-    pub fn compile(&mut self, main_code : String) -> Result<Context, RPUError> {
-        let mut context = Context::new();
+    /// Returns the schema of the given keyword, None if the keyword is not an element, object, camera or layout.
+    pub fn schema(kind: &str) -> Option<Schema> {
+        match kind {
+            "Texture" => Some(Texture::new().schema()),
+            "Layer" => Some(UserLayer::definition_schema()),
+            "Vertical" => Some(Vertical::new().schema()),
+            "Color" => Some(ColorElement::new().schema()),
+            "Noise" => Some(Noise::new().schema()),
+            "Bricks" => Some(Bricks::new().schema()),
+            "Checker" => Some(Checker::new().schema()),
+            "Gradient" => Some(Gradient::new().schema()),
+            "Voronoi" => Some(Voronoi::new().schema()),
+            "Tiles" => Some(Tiles::new().schema()),
+            "Hexagons" => Some(Hexagons::new().schema()),
+            "Stones" => Some(Stones::new().schema()),
+            "Planks" => Some(Planks::new().schema()),
+            "Marble" => Some(Marble::new().schema()),
+            "Stripes" => Some(Stripes::new().schema()),
+            "Sprite" => Some(Sprite::new().schema()),
+            "Voxel" => Some(AnalyticalVoxel::new().schema()),
+            "sdfCube" => Some(SDF3DCube::new().schema()),
+            "sdfSphere" => Some(SDF3DSphere::new().schema()),
+            "sdfCustom" => Some(SDF3DCustom::new().schema()),
+            "Pinhole" => Some(Pinhole::new().schema()),
+            "Grid3D" => Some(Grid3D::new().schema()),
+            _ => None,
+        }
+    }
+
+    /// Parses the given source into a Document without creating any objects, i.e. for tools like formatters.
+    /// The document contains everything which could be parsed, even if errors were found.
+    pub fn parse(code: String, file: Option<PathBuf>) -> (Document, Vec<RPUError>) {
+        Parser::new(code, file).parse()
+    }
 
-        self.scanner = Scanner::new(main_code);
+    /// Compiles the file at the given path. Imports are resolved relative to the directory of the file.
+    pub fn compile_from_path(&mut self, path: PathBuf) -> Result<Context, Vec<RPUError>> {
+        if let Ok(code) = std::fs::read_to_string(&path) {
+            self.compile_as_path(code, path)
+        } else {
+            Err(vec![RPUError::new(ErrorType::FileNotFound, format!("File '{}' not found.", path.display()), 0)])
+        }
+    }
 
-        self.curr_parent = None;
-        self.add_to_context(&mut context);
+    /// Compiles the given source as the content of the file at the given path, i.e. an unsaved file of an
+    /// editor. Imports are resolved relative to the directory of the file.
+    pub fn compile_as_path(&mut self, code: String, path: PathBuf) -> Result<Context, Vec<RPUError>> {
+        let canonical = path.canonicalize().unwrap_or(path.clone());
+        self.path = Some(path);
+        self.import_stack = vec![canonical.clone()];
+        self.imported = vec![canonical];
+        self.compile_source(code)
+    }
 
-        if self.parser.error.is_some() {
-            return Err(self.parser.error.clone().unwrap());
+    /// Compiles the given source into a Context. On failure returns all errors found in the source.
+    /// Imports are resolved relative to the current working directory.
+    pub fn compile(&mut self, main_code : String) -> Result<Context, Vec<RPUError>> {
+        self.path = None;
+        self.import_stack = vec![];
+        self.imported = vec![];
+        self.compile_source(main_code)
+    }
+
+    /// The diagnostics of the last compile, the compiled context reports the errors of its scripts there.
+    pub fn diagnostics(&self) -> &Diagnostics {
+        &self.diagnostics
+    }
+
+    /// Reports into the given diagnostics, i.e. to share them between compiles.
+    pub fn set_diagnostics(&mut self, diagnostics: Diagnostics) {
+        self.diagnostics = diagnostics;
+    }
+
+    /// Sets how the warnings with the given code are reported, i.e. "unused-texture".
+    pub fn set_warning_level(&mut self, code: &str, level: WarningLevel) {
+        self.warning_levels.insert(code.to_string(), level);
+    }
+
+    /// Sets the limits of the scripts of the compiled context.
+    pub fn set_script_limits(&mut self, limits: ScriptLimits) {
+        self.limits = limits;
+    }
+
+    /// Compiles the given source like compile(), the objects of blocks which did not change since the previous
+    /// context was compiled are moved into the new context instead of being created again, which keeps their
+    /// compiled code blocks, decoded images and texture bakes. Objects changed by set_property() are created
+    /// again. On success the previous context is left without these objects, on failure it is restored. The
+    /// diagnostics of the previous context are used, the reused objects report into them.
+    pub fn recompile(&mut self, main_code: String, previous: &mut Context) -> Result<Context, Vec<RPUError>> {
+        self.take_reusable(previous);
+        let rc = self.compile(main_code);
+        self.return_reusable(previous);
+        rc
+    }
+
+    /// Recompiles the file at the given path, see recompile().
+    pub fn recompile_from_path(&mut self, path: PathBuf, previous: &mut Context) -> Result<Context, Vec<RPUError>> {
+        self.take_reusable(previous);
+        let rc = self.compile_from_path(path);
+        self.return_reusable(previous);
+        rc
+    }
+
+    /// Moves the objects of the previous context into the pool of reusable objects. The camera is always
+    /// created again as its update() code changes it while rendering.
+    fn take_reusable(&mut self, previous: &mut Context) {
+        self.diagnostics = previous.diagnostics.clone();
+        self.budget = previous.budget.clone();
+        self.clock = previous.clock.clone();
+        self.reusable.clear();
+        for (slot, key) in std::mem::take(&mut previous.block_keys) {
+            let object = previous.take_object(slot);
+            self.reusable.entry(key).or_default().push((slot, object));
         }
+    }
 
-        // Initialize the context, like rendering static textures etc.
-        context.init();
+    /// Puts the objects which were not reused back into the previous context.
+    fn return_reusable(&mut self, previous: &mut Context) {
+        for (key, objects) in std::mem::take(&mut self.reusable) {
+            for (slot, object) in objects {
+                previous.put_object(slot, object);
+                previous.block_keys.push((slot, key));
+            }
+        }
+    }
 
-        // Log the project structure
+    /// Returns the object of an unchanged block from the previous context, if any. Objects with an update()
+    /// code block or event handlers change after compiling and are created again like the camera.
+    fn reuse(&mut self, block: &Block, key: u64, slot: Slot) -> Option<Object> {
+        if block.functions.iter().any(|f| f.name == "update" || f.name.starts_with("on_")) {
+            return None;
+        }
+        let (previous, object) = self.reusable.get_mut(&key)?.pop()?;
+        self.reused.push((previous, slot));
+        Some(object)
+    }
+
+    /// The key of the block, identical for blocks which create identical objects. Tokens are not serialized,
+    /// the file and the positions of the code blocks are hashed separately as the objects report the errors
+    /// of their code there, a block moved to another line is created again.
+    fn block_key(&self, block: &Block, document: &Document) -> u64 {
+        use std::hash::{Hash, Hasher};
 
-        struct StructureEnv { indent: u32 }
-        fn log_node (node_index: usize, env: &mut StructureEnv, ctx: &Context) {
-            let mut message = "".to_string();
-            for _ in 0..env.indent {
-                message += " ";
+        fn positions(block: &Block, hasher: &mut impl Hasher) {
+            (block.token.line, block.token.column).hash(hasher);
+            for function in &block.functions {
+                (function.token.line, function.token.column).hash(hasher);
             }
-            log::info!("{}{:?}, Elements: {}", message, ctx.nodes[node_index].get_node_type(), ctx.nodes[node_index].elements.len());
-            env.indent += 2;
-            for n in &ctx.nodes[node_index].childs {
-                log_node(*n, env, ctx);
+            for child in &block.children {
+                positions(child, hasher);
             }
-            env.indent -= 2;
         }
 
-        log::info!("Textures ---------------");
-
-        for n in &context.textures {
-            log_node(*n, &mut StructureEnv { indent: 0 }, &context);
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        serde_json::to_string(block).unwrap_or_default().hash(&mut hasher);
+        document.file.hash(&mut hasher);
+        positions(block, &mut hasher);
+        for (name, value) in self.constants.get_values() {
+            name.hash(&mut hasher);
+            value.to_string().hash(&mut hasher);
         }
+        for layer in &self.layers {
+            serde_json::to_string(&layer.block).unwrap_or_default().hash(&mut hasher);
+            positions(&layer.block, &mut hasher);
+        }
+        self.limits.hash(&mut hasher);
+        hasher.finish()
+    }
 
-        log::info!("Objects ---------------");
+    fn compile_source(&mut self, main_code : String) -> Result<Context, Vec<RPUError>> {
+        let mut context = Context::new();
 
-        for n in &context.objects {
-            log_node(*n, &mut StructureEnv { indent: 0 }, &context);
+        self.errors = vec![];
+        self.warnings = vec![];
+        self.texture_defs = vec![];
+        self.texture_refs = vec![];
+        self.layout_maps = vec![];
+        self.constants = ScriptEngine::new();
+
+        // Reused objects share the budget of the previous context, new limits recreate all objects
+        if self.budget.limits() != &self.limits {
+            self.budget = ScriptBudget::new(self.limits.clone());
         }
 
-        Ok(context)
-    }
+        self.prototypes = vec![];
+        self.layers = vec![];
+        self.reused = vec![];
+
+        let (document, errors) = Compiler::parse(main_code, self.path.clone());
+        self.errors.extend(errors);
+
+        self.add_document(&document, &mut context);
+        self.resolve(&mut context);
 
-    /// Logger
-    pub fn log(&self, msg: String) {
-        let mut message = "".to_string();
-        for _ in 0..self.parser.current.indent {
-            message += " ";
+        if !self.errors.is_empty() {
+            // The reused objects go back to the previous context
+            for (previous, slot) in std::mem::take(&mut self.reused) {
+                let key = context.block_keys.iter().find(|(s, _)| *s == slot).map_or(0, |(_, key)| *key);
+                let object = context.take_object(slot);
+                self.reusable.entry(key).or_default().push((previous, object));
+            }
+            self.report();
+            return Err(std::mem::take(&mut self.errors));
         }
-        message += msg.as_str();
-        message += format!(" on line {}", self.parser.current.line).as_str();
-        log::info!("{}", message);
-    }
 
-    pub fn add_to_context(&mut self, ctx: &mut Context) {
+        context.constants = self.constants.get_values();
+
+        // Initialize the context, like rendering static textures etc.
+        context.init();
 
-        self.advance();
+        let summary = format!("Compiled {} texture(s), {} object(s), {} sprite(s) and {} layout(s).", context.textures.len(), context.objects.len(), context.sprites.len(), context.layouts.len());
+        self.report();
+        self.diagnostics.push(RPUError::new(ErrorType::Syntax, summary, 0).with_code("summary").with_severity(Severity::Info));
+        context.diagnostics = self.diagnostics.clone();
+        context.budget = self.budget.clone();
+        context.clock = self.clock.clone();
 
-        while !self.matches(TokenType::Eof) {
+        Ok(context)
+    }
 
-            let camera3d = ["Pinhole"];
-            let layouts = ["Grid3D"];
-            let mut consumed = false;
+    /// Creates the objects of all blocks in the document, imports are compiled in place.
+    pub fn add_document(&mut self, document: &Document, ctx: &mut Context) {
 
-            if self.indent() == 0 {
+        let camera3d = ["Pinhole"];
+        let layouts = ["Grid3D"];
 
-                if self.parser.current.kind == TokenType::Identifier {
-                    let idl = self.parser.current.lexeme.clone();
-                    let id = idl.as_str();
+        // Blocks can derive from blocks defined later in the document
+        self.prototypes.extend(document.blocks().into_iter().cloned());
+
+        for item in &document.items {
+            match item {
+                Item::Import(import) => {
+                    self.import(import, document, ctx);
+                },
+                Item::Let(binding) => {
+                    if self.constants.get_value(&binding.name).is_some() {
+                        self.error_at(document, &binding.token, format!("Constant '{}' is already defined.", binding.name));
+                    } else
+                    if let Err(message) = self.constants.eval_constant(&binding.name, &convert_hex(&binding.value)) {
+                        self.error_at(document, &binding.token, message);
+                    }
+                },
+                Item::Block(block) => {
+                    let expanded;
+                    let block = if block.base.is_some() {
+                        expanded = self.expand_base(block, document);
+                        &expanded
+                    } else {
+                        block
+                    };
+
+                    let id = block.kind.as_str();
 
                     if camera3d.contains(&id){
-                        self.log(format!("Camera ({})", self.parser.current.lexeme));
-                        self.camera3d(ctx);
-                        consumed = true;
+                        self.camera3d(block, document, ctx);
                     } else
                     if self.objects3d.contains(&id.to_string()){
-                        self.log(format!("Object3D ({})", self.parser.current.lexeme));
-                        self.object3d(ctx);
-                        consumed = true;
+                        self.object3d(block, document, ctx);
                     } else
                     if layouts.contains(&id) {
-                        self.log(format!("Layout3D ({})", self.parser.current.lexeme));
-                        self.layout3d(ctx);
-                        consumed = true;
+                        self.layout3d(block, document, ctx);
                     } else
                     if id == "Texture" {
-                        self.log(format!("Element2D ({})", self.parser.current.lexeme));
-                        self.element2d(ctx);
-                        consumed = true;
+                        if let Some(index) = self.element2d(block, document, ctx) {
+                            if block.output {
+                                ctx.out_texture = Some(ctx.textures.len());
+                            }
+                            let token = block.get_property("name").map_or(block.token.clone(), |p| p.token.clone());
+                            let main = document.file == self.path;
+                            self.texture_defs.push((index, token.clone(), document.line_text(token.line), document.file.clone(), main));
+                            ctx.textures.push(index);
+                        }
                     } else
                     if id == "Sprite" {
-                        self.log(format!("Sprite ({})", self.parser.current.lexeme));
-                        self.sprite(ctx);
-                        consumed = true;
+                        self.sprite(block, document, ctx);
+                    } else
+                    if id == "Layer" {
+                        self.layer(block, document, ctx);
+                    } else {
+                        self.error_at(document, &block.token, format!("Unknown element '{}'.", id));
                     }
-                }
-            } else {
-                if self.parser.current.kind == TokenType::Identifier {
-                    let idl = self.parser.current.lexeme.clone();
 
-                    if self.elements2d.contains(&idl) {
-                        self.log(format!("Element2D ({})", self.parser.current.lexeme));
-                        self.element2d(ctx);
-                        consumed = true;
+                    if let Some(map) = &block.map {
+                        if !layouts.contains(&id) {
+                            self.error_at(document, &map.rows[0].token, format!("{} does not have a map, only layouts like Grid3D do.", id));
+                        }
+                    }
+                    if let Some(legend) = &block.legend {
+                        if !layouts.contains(&id) {
+                            self.error_at(document, &legend.token, format!("{} does not have a legend, only layouts like Grid3D do.", id));
+                        }
                     }
                 }
             }
+        }
+    }
+
+    /// Compiles the imported file into the context.
+    fn import(&mut self, import: &Import, document: &Document, ctx: &mut Context) {
+
+        let mut path = match &document.file {
+            Some(including) => including.parent().map(|p| p.to_path_buf()).unwrap_or(PathBuf::new()),
+            None => PathBuf::new(),
+        };
+        path.push(&import.path);
+
+        let canonical = path.canonicalize().unwrap_or(path.clone());
+
+        if let Some(index) = self.import_stack.iter().position(|p| *p == canonical) {
+            let mut cycle : Vec<String> = self.import_stack[index..].iter().map(|p| p.display().to_string()).collect();
+            cycle.push(canonical.display().to_string());
+            self.error_at(document, &import.token, format!("Import cycle detected: {}.", cycle.join(" -> ")));
+            return;
+        }
 
-            if consumed == false {
-                self.advance();
+        // Every file is only imported once
+        if self.imported.contains(&canonical) {
+            return;
+        }
+
+        let code = match std::fs::read_to_string(&path) {
+            Ok(code) => code,
+            Err(_) => {
+                let error = RPUError::at_token(ErrorType::FileNotFound, format!("Imported file '{}' not found.", path.display()), &import.token, document.line_text(import.token.line));
+                self.errors.push(error.in_file(document.file.clone()));
+                return;
+            }
+        };
+
+        self.imported.push(canonical.clone());
+        self.import_stack.push(canonical);
+
+        let (imported, errors) = Compiler::parse(code, Some(path));
+        self.errors.extend(errors);
+        self.add_document(&imported, ctx);
+
+        self.import_stack.pop();
+    }
+
+    /// Returns the block with the properties, code blocks and elements of its prototypes merged in. Properties
+    /// and code blocks of the block override the ones of its prototypes, elements are appended.
+    fn expand_base(&mut self, block: &Block, document: &Document) -> Block {
+        let mut merged = block.clone();
+        let mut current = block.clone();
+        let mut chain : Vec<String> = vec![];
+
+        while let Some(base) = current.base.clone() {
+            if chain.contains(&base.name) {
+                self.error_at(document, &block.base.as_ref().unwrap().token, format!("Prototype cycle detected: {} -> {}.", chain.join(" -> "), base.name));
+                break;
             }
+            chain.push(base.name.clone());
+
+            let prototype = self.prototypes.iter().find(|p| {
+                if base.symbol {
+                    p.symbol.as_ref() == Some(&base.name)
+                } else {
+                    p.kind == "Texture" && p.name().as_ref() == Some(&base.name)
+                }
+            }).cloned();
+
+            let prototype = match prototype {
+                Some(prototype) => prototype,
+                None => {
+                    let message = if base.symbol {
+                        format!("Unknown prototype, no object has the instance symbol '{}'.", base.name)
+                    } else {
+                        format!("Unknown prototype, no texture is named '{}'.", base.name)
+                    };
+                    self.error_at(document, &base.token, message);
+                    break;
+                }
+            };
 
-            if self.parser.error.is_some() {
+            if prototype.kind != current.kind {
+                self.error_at(document, &base.token, format!("{} can not derive from {} '{}'.", current.kind, prototype.kind, base.name));
                 break;
             }
+
+            // The name identifies the prototype itself and is not inherited
+            let mut properties : Vec<PropertyAssign> = prototype.properties.iter().filter(|p| p.name != "name").cloned().collect();
+            properties.extend(merged.properties);
+            merged.properties = properties;
+
+            let mut functions : Vec<FunctionDef> = prototype.functions.iter().filter(|f| !merged.functions.iter().any(|m| m.name == f.name)).cloned().collect();
+            functions.extend(merged.functions);
+            merged.functions = functions;
+
+            let mut children = prototype.children.clone();
+            children.extend(merged.children);
+            merged.children = children;
+
+            if merged.legend.is_none() {
+                merged.legend = prototype.legend.clone();
+            }
+
+            current = prototype;
+        }
+
+        merged
+    }
+
+    /// Resolves the references between blocks after all blocks have been created.
+    fn resolve(&mut self, ctx: &mut Context) {
+
+        let defs = std::mem::take(&mut self.texture_defs);
+        let names : Vec<Option<String>> = defs.iter().map(|(index, ..)| match &ctx.nodes[*index].object {
+            Object::Element2D(el) => el.get_engine().get_string("name"),
+            _ => None,
+        }).collect();
+
+        for (i, (_, token, source_line, file, _)) in defs.iter().enumerate() {
+            if let Some(name) = &names[i] {
+                if let Some(first) = names[..i].iter().position(|n| n.as_ref() == Some(name)) {
+                    let (_, first_token, _, first_file, _) = &defs[first];
+                    let location = RPUError::at_token(ErrorType::Syntax, "".to_string(), first_token, "".to_string()).in_file(first_file.clone()).location();
+                    let error = RPUError::at_token(ErrorType::Syntax, format!("A texture named '{}' is already defined at {}.", name, location), token, source_line.clone()).with_code("duplicate-texture");
+                    self.errors.push(error.in_file(file.clone()));
+                }
+            }
+        }
+
+        let candidates : Vec<&str> = names.iter().flatten().map(|n| n.as_str()).collect();
+        let mut referenced : Vec<String> = vec![];
+
+        for (reference, name, token, source_line, file) in std::mem::take(&mut self.texture_refs) {
+            let matches : Vec<usize> = names.iter().enumerate().filter(|(_, n)| n.as_ref() == Some(&name)).map(|(i, _)| defs[i].0).collect();
+            let texture = matches.first().cloned();
+
+            if matches.is_empty() {
+                let mut message = format!("Unknown texture '{}'.", name);
+                if let Some(closest) = closest_match(&name, &candidates) {
+                    message += format!(" Did you mean '{}'?", closest).as_str();
+                }
+                let error = RPUError::at_token(ErrorType::Syntax, message, &token, source_line).with_code("unknown-texture");
+                self.errors.push(error.in_file(file));
+            } else
+            if matches.len() > 1 {
+                let message = format!("Texture '{}' is ambiguous, {} textures have this name.", name, matches.len());
+                let error = RPUError::at_token(ErrorType::Syntax, message, &token, source_line).with_code("ambiguous-texture");
+                self.errors.push(error.in_file(file));
+            }
+            referenced.push(name);
+
+            match reference {
+                TextureRef::Node(index) => ctx.nodes[index].texture = texture,
+                TextureRef::Sprite(index) => ctx.sprites[index].set_texture(texture),
+            }
+        }
+
+        // Textures of imports are libraries, only the unused textures of the compiled document are reported
+        let bases : Vec<String> = self.prototypes.iter().filter_map(|p| p.base.as_ref()).filter(|b| !b.symbol).map(|b| b.name.clone()).collect();
+        for (i, (_, token, source_line, file, main)) in defs.into_iter().enumerate() {
+            if !main || ctx.out_texture == Some(i) {
+                continue;
+            }
+            let message = match &names[i] {
+                Some(name) if referenced.contains(name) || bases.contains(name) => continue,
+                Some(name) => format!("Texture '{}' is never used.", name),
+                None => "Texture is never used, name it and reference it with texture = \"Name\" or output it with Texture*.".to_string(),
+            };
+            self.warning(RPUError::at_token(ErrorType::Syntax, message, &token, source_line).in_file(file).with_code("unused-texture"));
+        }
+
+        for (index, block, document) in std::mem::take(&mut self.layout_maps) {
+            let map = self.layout_map(index, &block, &document, ctx);
+            match &mut ctx.layouts[index] {
+                Object::Layout3D(layout) => {
+                    // A reused layout keeps its buffers if its map did not change
+                    let reused = self.reused.iter().any(|(_, slot)| *slot == Slot::Layout(index));
+                    if !reused || layout.get_map3d() != map {
+                        layout.set_map3d(map);
+                    }
+                },
+                _ => {}
+            }
         }
     }
 
     /// Reads an 3D object (analytical or SDF).
-    fn object3d(&mut self, ctx: &mut Context) {
+    fn object3d(&mut self, block: &Block, document: &Document, ctx: &mut Context) {
+
+        let index = ctx.nodes.len();
+        let key = self.block_key(block, document);
+        let reused = self.reuse(block, key, Slot::Node(index));
 
         let mut object : Option<Object> = None;
-        let mut symbol : Option<char> = None;
 
-        // if self.parser.current.lexeme == "Cube" {
+        // if block.kind == "Cube" {
         //     object = Some(Object::AnalyticalObject(Box::new(AnalyticalCube::new())));
         // }
-        if self.parser.current.lexeme == "Voxel" {
+        if block.kind == "Voxel" {
             object = Some(Object::AnalyticalObject(Box::new(AnalyticalVoxel::new())));
         } else
-        if self.parser.current.lexeme == "sdfCube" {
+        if block.kind == "sdfCube" {
             object = Some(Object::SDF3D(Box::new(SDF3DCube::new())));
         } else
-        if self.parser.current.lexeme == "sdfSphere" {
+        if block.kind == "sdfSphere" {
             object = Some(Object::SDF3D(Box::new(SDF3DSphere::new())));
-        }
-
-        let mut node = Node::new(format!("{}, line {}", self.parser.current.lexeme, self.parser.current.line));
-
-        self.advance();
-
-        if object.is_some() && self.check(TokenType::Apostrophe) {
-            self.advance();
-            let c = self.parser.current.lexeme.chars().next();
-            if let Some(c) = c {
-                symbol = Some(c);
-                self.advance();
+        } else
+        if block.kind == "sdfCustom" {
+            object = Some(Object::SDF3D(Box::new(SDF3DCustom::new())));
+            if !block.functions.iter().any(|f| f.name == "distance") {
+                self.error_at(document, &block.token, "sdfCustom needs a distance(p) function which returns the distance to its surface.".to_string());
             }
         }
 
-        node.object = object.unwrap();
+        let mut node = Node::new(format!("{}, line {}", block.kind, block.token.line));
+        node.indent = block.token.indent;
 
-        let props = self.parse_object_properties(&mut node);
+        self.check_children(block, document);
 
-        match &mut node.object {
-            Object::AnalyticalObject(object) => {
-                self.parser.error = object.apply_properties(props).err();
-            },
-            Object::SDF3D(sdf) => {
-                self.parser.error = sdf.apply_properties(props).err();
-            },
-            _ => {},
-        }
+        if let Some(reused) = reused {
+            node.object = reused;
+        } else {
+            node.object = object.unwrap();
 
-        // Get the texture name if any
-        let mut texture : Option<usize> = None;
+            let props = self.get_properties(block, document);
 
-        match &mut node.object {
-            Object::AnalyticalObject(object) => {
-                if let Some(name) = object.get_engine().get_string("texture") {
-                    texture = self.get_texture_index(name.clone(), ctx);
-                    println!("result {}, {:?}", name, texture);
-                }
-            },
-            Object::SDF3D(sdf) => {
-                if let Some(name) = sdf.get_engine().get_string("texture") {
-                    texture = self.get_texture_index(name.clone(), ctx);
-                    println!("result {}, {:?}", name, texture);
-                }
-            },
-            _ => {}
+            match &mut node.object {
+                Object::AnalyticalObject(object) => {
+                    let rc = object.apply_properties(props);
+                    self.add_result(rc, document);
+                },
+                Object::SDF3D(sdf) => {
+                    let rc = sdf.apply_properties(props);
+                    self.add_result(rc, document);
+                },
+                _ => {},
+            }
         }
 
-        node.texture = texture;
+        // Get the texture name if any, resolved after all textures are known
+        let name = match &node.object {
+            Object::AnalyticalObject(object) => object.get_engine().get_string("texture"),
+            Object::SDF3D(sdf) => sdf.get_engine().get_string("texture"),
+            _ => None,
+        };
+        if let Some(name) = name {
+            self.add_texture_ref(TextureRef::Node(index), name, block, document);
+        }
 
-        if let Some(symbol) = symbol {
-            ctx.symbols_node_index.insert(symbol, ctx.nodes.len());
+        if let Some(symbol) = &block.symbol {
+            ctx.symbols_node_index.insert(symbol.clone(), index);
         }
 
-        ctx.objects.push(ctx.nodes.len());
+        ctx.block_keys.push((Slot::Node(index), key));
+        ctx.objects.push(index);
         ctx.nodes.push(node);
     }
 
     /// Reads a 3d layout.
-    fn layout3d(&mut self, ctx: &mut Context) {
+    fn layout3d(&mut self, block: &Block, document: &Document, ctx: &mut Context) {
 
         let mut object : Option<Object> = None;
-        let mut symbol : Option<char> = None;
 
-        // if self.parser.current.lexeme.to_lowercase() == "grid2d" {
+        // if block.kind.to_lowercase() == "grid2d" {
         //     object = Some(Object::Layout3D(Box::new(Grid2D::new())));
         // } else
-        if self.parser.current.lexeme.to_lowercase() == "grid3d" {
+        if block.kind.to_lowercase() == "grid3d" {
             object = Some(Object::Layout3D(Box::new(Grid3D::new())));
         }
 
-        self.advance();
+        self.check_children(block, document);
 
-        if object.is_some() && self.check(TokenType::Apostrophe) {
-            self.advance();
-            let c = self.parser.current.lexeme.chars().next();
-            if let Some(c) = c {
-                symbol = Some(c);
-                self.advance();
-            }
+        let key = self.block_key(block, document);
+        if let Some(reused) = self.reuse(block, key, Slot::Layout(ctx.layouts.len())) {
+            object = Some(reused);
+        } else
+        if let Some(Object::Layout3D(layout)) = &mut object {
+            let props = self.get_properties(block, document);
+            let rc = layout.apply_properties(props);
+            self.add_result(rc, document);
         }
+        ctx.block_keys.push((Slot::Layout(ctx.layouts.len()), key));
 
-        if let Some(object) = &mut object {
-            let mut map : HashMap<(i32, i32, i32), usize> = HashMap::new();
+        // The map is resolved after all symbols are defined
+        self.layout_maps.push((ctx.layouts.len(), block.clone(), document.clone()));
 
-            let mut x = 0;
-            let mut y = 0;
-            let mut z = 0;
+        if let Some(symbol) = &block.symbol {
+            ctx.symbols_node_index.insert(symbol.clone(), ctx.nodes.len());
+        }
+        ctx.layouts.push(object.unwrap());
+    }
 
-            let mut first = true;
+    /// Converts the rows of a layout into the 3d map of cells. With a legend the cells are as wide as the
+    /// longest key, the text of a cell is looked up in the legend first and is an instance symbol otherwise.
+    /// The generate() code block of the layout runs afterwards and changes the map.
+    fn layout_map(&mut self, index: usize, block: &Block, document: &Document, ctx: &Context) -> HashMap<(i32, i32, i32), MapCell> {
+        let mut map : HashMap<(i32, i32, i32), MapCell> = HashMap::new();
+        let legend = block.legend.as_ref();
+        let rows = block.map.as_ref().map_or(&[][..], |m| &m.rows[..]);
+
+        let mut entries : HashMap<String, MapCell> = HashMap::new();
+        if let Some(legend) = legend {
+            for entry in &legend.entries {
+                if let Some(cell) = self.legend_entry(entry, document, ctx) {
+                    entries.insert(entry.key.clone(), cell);
+                }
+            }
+        }
+        let width = legend.map_or(1, |l| l.cell_width());
 
-            loop {
-                if self.check(TokenType::Colon) {
+        let mut x = 0;
+        let mut y = 0;
+        let mut z = 0;
 
-                    let line = self.parser.current.line;
-                    let mut count = 0;
+        let mut first = true;
 
-                    while self.check(TokenType::Colon) {
+        for row in rows {
+            for count in 0..row.colons {
 
-                        // Next level up
-                        if count > 0 && self.parser.current.line == line {
-                            y += 1;
-                            x = 0;
-                            z = 0;
-                        }
+                // Next level up
+                if count > 0 {
+                    y += 1;
+                    x = 0;
+                    z = 0;
+                }
 
-                        // Next line
-                        if first == true {
-                            first = false;
-                        } else {
-                            z += 1;
-                            x = 0;
-                        }
-                        self.advance_with_whitespace();
-                        count += 1;
-                    }
-                } else
-                if self.check(TokenType::Space) {
-                    x += 1;
-                    self.advance_with_whitespace();
-                } else
-                if self.check(TokenType::Identifier) != true {
-                    break;
+                // Next line
+                if first {
+                    first = false;
+                } else {
+                    z += 1;
+                    x = 0;
                 }
+            }
 
-                let symbols = self.parser.current.lexeme.chars();
-                //println!("{:?}", symbols);
+            let chars : Vec<(usize, char)> = row.text.char_indices().collect();
+            for (index, cell) in chars.chunks(width).enumerate() {
+                let text : String = cell.iter().map(|(_, c)| *c).collect();
+                let symbol = text.trim();
 
-                for c in symbols {
-                    if c == ' ' {
-                        x += 1;
+                if !symbol.is_empty() {
+                    if let Some(cell) = entries.get(symbol) {
+                        map.insert((x, y, z), *cell);
                     } else
-                    if let Some(index) = ctx.symbols_node_index.get(&c) {
-                        map.insert((x, y, z), *index);
-                        x+= 1;
-                    } else {
-                        self.error_at_current(format!("Undefined instance symbol '{}'.", c).as_str());
-                        break;
+                    if let Some(index) = ctx.symbols_node_index.get(symbol) {
+                        map.insert((x, y, z), MapCell::new(*index));
+                    } else
+                    if legend.and_then(|l| l.get(symbol)).is_none() {
+                        // Report every undefined symbol at its own column
+                        let skip = text.chars().take_while(|c| *c == ' ').count();
+                        let mut token = row.token.clone();
+                        token.lexeme = symbol.to_string();
+                        token.column = row.colons + index * width + skip + 1;
+                        token.start = row.token.start + row.colons + cell[skip].0;
+                        token.end = token.start + token.lexeme.len();
+                        self.error_at(document, &token, format!("Undefined instance symbol '{}'.", symbol));
                     }
                 }
+                x += 1;
+            }
+        }
 
-                self.advance_with_whitespace();
+        if let Object::Layout3D(layout) = &ctx.layouts[index] {
+            let engine = layout.get_engine();
+            if let Some(code) = engine.get_code_block("generate") {
+                let token = block.functions.iter().find(|f| f.name == "generate").map_or(block.token.clone(), |f| f.token.clone());
+                let mut generator = Generator::new(engine.get_int("seed").unwrap_or(0));
+                generator.set_limits(&self.limits);
+
+                match generator.run(code) {
+                    Ok(cells) => {
+                        // Sorted so that errors are reported in the same order every time
+                        let mut cells : Vec<((i32, i32, i32), Option<String>)> = cells.into_iter().collect();
+                        cells.sort_by_key(|(position, _)| *position);
+
+                        let mut undefined : Vec<String> = vec![];
+                        let mut outside = false;
+
+                        for ((x, y, z), symbol) in cells {
+                            if x < 0 || y < 0 || z < 0 {
+                                if !outside {
+                                    self.error_at(document, &token, format!("generate() placed a cell at ({}, {}, {}), coordinates start at 0.", x, y, z));
+                                }
+                                outside = true;
+                                continue;
+                            }
+
+                            let symbol = match symbol {
+                                Some(symbol) => symbol,
+                                None => {
+                                    map.remove(&(x, y, z));
+                                    continue;
+                                }
+                            };
+
+                            if let Some(cell) = entries.get(&symbol) {
+                                map.insert((x, y, z), *cell);
+                            } else
+                            if let Some(index) = ctx.symbols_node_index.get(&symbol) {
+                                map.insert((x, y, z), MapCell::new(*index));
+                            } else
+                            if !undefined.contains(&symbol) {
+                                self.error_at(document, &token, format!("generate() placed the undefined instance symbol '{}'.", symbol));
+                                undefined.push(symbol);
+                            }
+                        }
+                    },
+                    Err(err) => {
+                        self.errors.push(engine.script_error("generate", &err));
+                    }
+                }
             }
+        }
 
-            //println!("{:?}", map);
+        map
+    }
 
-            match object {
-                Object::Layout3D(layout) => layout.set_map3d(map),
-                _ => {}
+    /// Resolves a legend entry like 'door, rotation = 90 into a map cell, None if the entry has errors.
+    fn legend_entry(&mut self, entry: &LegendEntry, document: &Document, ctx: &Context) -> Option<MapCell> {
+        let modifiers = ["rotation", "height"];
+
+        let mut parts = split_arguments(&entry.value).into_iter();
+        let symbol = parts.next().unwrap_or_default();
+
+        let mut cell = match symbol.strip_prefix('\'').and_then(|s| ctx.symbols_node_index.get(s.trim())) {
+            Some(index) => MapCell::new(*index),
+            None => {
+                let message = match symbol.strip_prefix('\'') {
+                    Some(name) => format!("Undefined instance symbol '{}'.", name.trim()),
+                    None => format!("Expected an instance symbol for '{}', i.e. {} = 'a.", entry.key, entry.key),
+                };
+                self.error_at(document, &entry.token, message);
+                return None;
             }
-        }
+        };
+
+        for part in parts {
+            let (name, value) = match part.split_once('=') {
+                Some((name, value)) => (name.trim(), value.trim()),
+                None => {
+                    self.error_at(document, &entry.token, format!("Expected a modifier like rotation = 90, found '{}'.", part));
+                    return None;
+                }
+            };
+
+            if !modifiers.contains(&name) {
+                let mut message = format!("Unknown legend modifier '{}'.", name);
+                if let Some(closest) = closest_match(name, &modifiers) {
+                    message += format!(" Did you mean '{}'?", closest).as_str();
+                } else {
+                    message += format!(" Expected one of: {}.", modifiers.join(", ")).as_str();
+                }
+                self.error_at(document, &entry.token, message);
+                return None;
+            }
+
+            let value = match self.constants.eval(value).map(|v| v.as_float()) {
+                Ok(Some(value)) => value,
+                Ok(None) => {
+                    self.error_at(document, &entry.token, format!("Modifier '{}' expects a number, found '{}'.", name, value));
+                    return None;
+                },
+                Err(message) => {
+                    self.error_at(document, &entry.token, format!("Invalid value for modifier '{}': {}.", name, message));
+                    return None;
+                }
+            };
 
-        if let Some(symbol) = symbol {
-            ctx.symbols_node_index.insert(symbol, ctx.nodes.len());
+            if name == "rotation" {
+                cell.rotation = value;
+            } else
+            if value.abs() > 1.0 {
+                self.error_at(document, &entry.token, format!("Modifier 'height' must be in the range -1 to 1, found {}.", value));
+                return None;
+            } else {
+                cell.height = value;
+            }
         }
-        ctx.layouts.push(object.unwrap());
+
+        Some(cell)
     }
 
-    /// Reads a texture or 2d element
-    fn element2d(&mut self, ctx: &mut Context) {
+    /// Reads a texture or 2d element, returns the index of its node.
+    fn element2d(&mut self, block: &Block, document: &Document, ctx: &mut Context) -> Option<usize> {
         let mut object : Option<Object> = None;
 
-        let mut is_layout = false;
-        let mut is_texture = false;
-
-        if self.parser.current.lexeme == "Texture" {
-            is_layout = true;
-            is_texture = true;
+        if block.kind == "Texture" {
             object = Some(Object::Element2D(Box::new(Texture::new())));
         } else
-        if self.parser.current.lexeme == "Vertical" {
-            is_layout = true;
+        if block.kind == "Vertical" {
             object = Some(Object::Element2D(Box::new(Vertical::new())));
         } else
-        if self.parser.current.lexeme == "Color" {
+        if block.kind == "Color" {
             object = Some(Object::Element2D(Box::new(ColorElement::new())));
         } else
-        if self.parser.current.lexeme == "Noise" {
+        if block.kind == "Noise" {
             object = Some(Object::Element2D(Box::new(Noise::new())));
         } else
-        if self.parser.current.lexeme == "Bricks" {
+        if block.kind == "Bricks" {
             object = Some(Object::Element2D(Box::new(Bricks::new())));
+        } else
+        if block.kind == "Checker" {
+            object = Some(Object::Element2D(Box::new(Checker::new())));
+        } else
+        if block.kind == "Gradient" {
+            object = Some(Object::Element2D(Box::new(Gradient::new())));
+        } else
+        if block.kind == "Voronoi" {
+            object = Some(Object::Element2D(Box::new(Voronoi::new())));
+        } else
+        if block.kind == "Tiles" {
+            object = Some(Object::Element2D(Box::new(Tiles::new())));
+        } else
+        if block.kind == "Hexagons" {
+            object = Some(Object::Element2D(Box::new(Hexagons::new())));
+        } else
+        if block.kind == "Stones" {
+            object = Some(Object::Element2D(Box::new(Stones::new())));
+        } else
+        if block.kind == "Planks" {
+            object = Some(Object::Element2D(Box::new(Planks::new())));
+        } else
+        if block.kind == "Marble" {
+            object = Some(Object::Element2D(Box::new(Marble::new())));
+        } else
+        if block.kind == "Stripes" {
+            object = Some(Object::Element2D(Box::new(Stripes::new())));
+        } else
+        if let Some(layer) = self.layers.iter().find(|l| l.name == block.kind) {
+            // The errors of a broken definition are already reported
+            layer.function.as_ref()?;
+            let mut element = UserLayer::new();
+            element.set_definition(layer.name.clone(), layer.parameters.clone());
+            object = Some(Object::Element2D(Box::new(element)));
         }
 
-        let mut node = Node::new(format!("{}, line {}", self.parser.current.lexeme, self.parser.current.line));
-        node.object = object.unwrap();
-
-        if self.parser.current.indent == 0 {
-            self.curr_parent = None;
+        if object.is_none() {
+            let message = if block.kind == "Layer" {
+                "Layers are defined at the top level, not inside of a texture.".to_string()
+            } else
+            if self.prototypes.iter().any(|p| p.kind == "Layer" && p.name().as_ref() == Some(&block.kind)) {
+                format!("Layer '{}' is used before it is defined.", block.kind)
+            } else {
+                format!("Unknown element '{}'.", block.kind)
+            };
+            self.error_at(document, &block.token, message);
+            return None;
         }
 
-        // if let Some(parent_index) = self.curr_parent {
-        //     if ctx.nodes[parent_index].indent < self.parser.current.indent {
-        //         self.curr_parent = None;
-        //     }
-        // }
+        let mut node = Node::new(format!("{}, line {}", block.kind, block.token.line));
+        node.indent = block.token.indent;
 
-        self.advance();
+        // The key covers the child elements, a texture is only reused if its layers did not change
+        let index = ctx.nodes.len();
+        let key = self.block_key(block, document);
 
-        // * on a texture means we should output it
-        if is_texture && self.check(TokenType::Star) {
-            ctx.out_texture = Some(ctx.textures.len());
-            self.advance();
-        }
+        if let Some(reused) = self.reuse(block, key, Slot::Node(index)) {
+            node.object = reused;
+        } else {
+            node.object = object.unwrap();
 
-        let props = self.parse_object_properties(&mut node);
+            let mut props = self.get_properties(block, document);
 
-        match &mut node.object {
-            Object::Element2D(texture) => {
-                self.parser.error = texture.apply_properties(props).err();
-            },
-            _ => {}
-        }
+            // User defined layers get the function of their definition
+            let definition = self.layers.iter().find(|l| l.name == block.kind).map(|l| l.properties(&self.diagnostics));
+            if let Some(definition) = definition {
+                for f in &block.functions {
+                    self.error_at(document, &f.token, format!("{} can not define code blocks, the color() function of the layer is part of its Layer block.", block.kind));
+                }
+                props.extend(definition);
+            }
 
-        if is_layout {
-            let index = ctx.nodes.len();
-            ctx.nodes.push(node);
-            if let Some(parent_index) = self.curr_parent {
-                ctx.nodes[parent_index].childs.push(index);
-            } else {
-                ctx.textures.push(index);
-                println!("Added as texture: {}", self.get_name_of_object(&ctx.nodes[index].object));
+            match &mut node.object {
+                Object::Element2D(texture) => {
+                    let rc = texture.apply_properties(props);
+                    self.add_result(rc, document);
+                },
+                _ => {}
             }
-            self.curr_parent = Some(index);
-        } else
-        if let Some(parent_index) = self.curr_parent {
-            let index = ctx.nodes.len();
-            ctx.nodes.push(node);
-            ctx.nodes[parent_index].elements.push(index);
-            println!("Added {}({}) to: {}({})", self.get_name_of_object(&ctx.nodes[index].object), index, self.get_name_of_object(&ctx.nodes[parent_index].object), parent_index);
         }
-        //self.debug_current("end of texture");
-    }
 
-    /// Reads a sprite, a special case Element2D
-    fn sprite(&mut self, ctx: &mut Context) {
-        let mut object = Box::new(Sprite::new());
+        ctx.block_keys.push((Slot::Node(index), key));
+        ctx.nodes.push(node);
 
-        let mut node = Node::new(format!("{}, line {}", self.parser.current.lexeme, self.parser.current.line));
+        for child in &block.children {
+            if let Some(child_index) = self.element2d(child, document, ctx) {
+                // Layouts like Vertical are childs, layers are elements
+                if child.kind == "Texture" || child.kind == "Vertical" {
+                    ctx.nodes[index].childs.push(child_index);
+                } else {
+                    ctx.nodes[index].elements.push(child_index);
+                }
+            }
+        }
 
-        self.advance();
+        Some(index)
+    }
 
-        let props = self.parse_object_properties(&mut node);
-        self.parser.error = object.apply_properties(props).err();
+    /// Reads a sprite, a special case Element2D
+    fn sprite(&mut self, block: &Block, document: &Document, ctx: &mut Context) {
+        self.check_children(block, document);
+
+        let key = self.block_key(block, document);
+        let object : Box<dyn Element2D> = match self.reuse(block, key, Slot::Sprite(ctx.sprites.len())) {
+            Some(Object::Element2D(sprite)) => sprite,
+            _ => {
+                let mut object = Box::new(Sprite::new());
+                let props = self.get_properties(block, document);
+                let rc = object.apply_properties(props);
+                self.add_result(rc, document);
+                object
+            }
+        };
 
-        let mut texture : Option<usize> = None;
         if let Some(name) = object.get_engine().get_string("texture") {
-            texture = self.get_texture_index(name.clone(), ctx);
+            self.add_texture_ref(TextureRef::Sprite(ctx.sprites.len()), name, block, document);
         }
-        object.texture = texture;
+        ctx.block_keys.push((Slot::Sprite(ctx.sprites.len()), key));
         ctx.sprites.push(object);
     }
 
     /// Reads a camera
-    fn camera3d(&mut self, ctx: &mut Context) {
+    fn camera3d(&mut self, block: &Block, document: &Document, ctx: &mut Context) {
         let mut object = Box::new(Pinhole::new());
 
-        let mut node = Node::new(format!("{}, line {}", self.parser.current.lexeme, self.parser.current.line));
-
-        self.advance();
+        self.check_children(block, document);
 
-        let props = self.parse_object_properties(&mut node);
-        self.parser.error = object.apply_properties(props).err();
+        let props = self.get_properties(block, document);
+        let rc = object.apply_properties(props);
+        self.add_result(rc, document);
 
         ctx.camera = object;
     }
 
-    /// Returns the index of the texture with the given name
-    fn get_texture_index(&self, name: String, ctx: &mut Context) -> Option<usize> {
-        for (index, node_index) in ctx.textures.iter().enumerate() {
-            match &ctx.nodes[*node_index].object {
-                Object::Element2D(el) => {
-                    //texture.alloc();
-                    if let Some(rc) = el.get_engine().get_string("name") {
-                        if rc == name {
-                            return Some(ctx.textures[index])
-                        }
-                    }
-                },
-                _ => {}
-            }
-        }
-        None
-    }
-
-    /// Parses the properties for the given object
-    fn parse_object_properties(&mut self, node: &mut Node) -> Vec<Property> {
+    /// Reads a Layer block, which defines a layer textures can use like the built in layers.
+    fn layer(&mut self, block: &Block, document: &Document, ctx: &mut Context) {
+        self.check_children(block, document);
 
-        node.indent = self.parser.current.indent;
-        //println!("object on line {}", self.parser.current.line);
-
-        let mut props : Vec<Property> = vec![];
+        let name = match block.name() {
+            Some(name) => name,
+            None => {
+                self.error_at(document, &block.token, "Layer needs a name, i.e. name = \"Bands\".".to_string());
+                return;
+            }
+        };
+        let token = block.get_property("name").map_or(block.token.clone(), |p| p.token.clone());
 
-        loop {
-            let property = self.parser.current.lexeme.clone();
-            let indention = self.parser.current.indent;
+        let keyword = name.chars().next().is_some_and(|c| c.is_alphabetic()) && name.chars().all(|c| c.is_alphanumeric() || c == '_');
+        if !keyword {
+            self.error_at(document, &token, format!("Layer name '{}' can not be used as a keyword, use letters, digits and underscores.", name));
+            return;
+        }
+        if TOP_LEVEL_ELEMENTS.contains(&name.as_str()) || self.elements2d.contains(&name) {
+            self.error_at(document, &token, format!("'{}' is a built in element and can not be the name of a layer.", name));
+            return;
+        }
+        if self.layers.iter().any(|l| l.name == name) {
+            self.error_at(document, &token, format!("A layer named '{}' is already defined.", name));
+            return;
+        }
 
-            if self.elements2d.contains(&property) || self.objects3d.contains(&property) || self.parser.current.indent < node.indent {
-                self.debug_current(format!("prop break for {}", node.id).as_str());
-                break;
+        // The parameters are typed by their default values
+        let mut parameters : Vec<PropertyDef> = vec![];
+        let mut valid = true;
+        for p in block.properties.iter().filter(|p| p.name != "name") {
+            match self.constants.eval(&convert_hex(&p.value)) {
+                Ok(value) => {
+                    parameters.retain(|d| d.name != p.name);
+                    parameters.push(PropertyDef::new(&p.name, value.get_type(), Some(value), None, "A parameter of the layer."));
+                },
+                Err(message) => {
+                    self.error_at(document, &p.token, format!("Invalid value for parameter '{}': {}.", p.name, message));
+                    valid = false;
+                }
             }
+        }
 
-            self.consume(TokenType::Identifier, "Expected identifier.");
+        let function = block.functions.iter().rev().find(|f| f.name == "color").map(|f| (f.clone(), document.line_text(f.token.line)));
+        if function.is_none() {
+            self.error_at(document, &block.token, format!("Layer '{}' needs a color(uv, input) function.", name));
+            valid = false;
+        }
 
-            if self.check(TokenType::Equal) {
-                let mut value = self.scanner.scanline(1);
-                println!("assignment to {:?}, line {}: {} = {}", node.id, self.parser.current.line, property, value);
-                if value.starts_with('#') {
-                    //println!("Color {}", value);
-                    let mut chars = value.chars();
-                    chars.next();
-                    let color = chars.as_str();
+        // Checks the code blocks of the definition once, instead of at every use
+        if valid {
+            let mut layer = UserLayer::new();
+            layer.set_definition(name.clone(), parameters.clone());
+            let mut props = self.get_properties(block, document);
+            props.retain(|p| !matches!(p, Property::Property(..)));
+            let rc = layer.apply_properties(props);
+            valid = rc.is_ok();
+            self.add_result(rc, document);
+        }
 
-                    use colors_transform::{Rgb};
+        ctx.layers.push(block.clone());
+        self.layers.push(LayerDef {
+            name,
+            parameters,
+            function            : if valid { function } else { None },
+            file                : document.file.clone(),
+            block               : block.clone(),
+        });
+    }
 
-                    if let Some(rgb) = Rgb::from_hex_str(color).ok() {
-                        println!("{:?}", rgb);
-                        value = format!("F4({:.3}, {:.3}, {:.3}, 1.0)", rgb.get_red() as F / 255.0, rgb.get_green() as F / 255.0, rgb.get_blue() as F / 255.0 );
-                        println!("{}", value);
-                    }
-                }
-                props.push(Property::Property(property, value));
-                self.advance();
-                if self.indent() == 0 {
-                    break;
-                }
-            } else
-            if self.check(TokenType::LeftParen) {
-                let mut args = "".to_string();
-                self.advance();
-                loop {
-                    if self.check(TokenType::Identifier) {
-                        args += self.parser.current.lexeme.clone().as_str();
-                        self.advance();
-                    } else
-                    if self.check(TokenType::RightParen) {
-                        break;
-                    } else
-                    if self.check(TokenType::Comma) {
-                        args += ",";
-                        self.advance();
-                    } else {
-                        self.error_at_current("Invalid function arguments");
-                        break;
-                    }
-                }
-                let code = self.scanner.scan_indention_block(1, indention);
-                //println!("function, line {}: {}, {:?}", line, args, code.ok());
-                if let Some(code) = code.ok() {
-                    props.push(Property::Function(property, args, code));
-                }
-                self.advance();
-                if self.indent() <= node.indent {
-                    break;
-                }
+    /// Only textures can contain child elements.
+    fn check_children(&mut self, block: &Block, document: &Document) {
+        for child in &block.children {
+            if self.elements2d.contains(&child.kind) || self.layers.iter().any(|l| l.name == child.kind) {
+                self.error_at(document, &child.token, format!("{} can not contain the element '{}'.", block.kind, child.kind));
             } else {
-                break;
+                self.error_at(document, &child.token, format!("Expected '=' or a function definition after '{}'.", child.kind));
             }
         }
-        props
     }
 
-    /// Returns the name of an object
-    fn get_name_of_object(&self, object: &Object) -> String {
-        match object {
-            Object::Element2D(el) => {
-                el.name()
-            },
-            _ => { "??".to_string() }
-        }
+    /// Remembers a texture reference of the given block, resolved after all textures are known.
+    fn add_texture_ref(&mut self, reference: TextureRef, name: String, block: &Block, document: &Document) {
+        let token = block.get_property("texture").map_or(block.token.clone(), |p| p.token.clone());
+        let source_line = document.line_text(token.line);
+        self.texture_refs.push((reference, name, token, source_line, document.file.clone()));
     }
 
-    /// Advance one token
-    fn advance(&mut self) {
-        self.parser.previous = self.parser.current.clone();
+    /// Converts the property assignments and code blocks of the block into properties.
+    fn get_properties(&self, block: &Block, document: &Document) -> Vec<Property> {
 
-        loop {
-            self.parser.current = self.scanner.scan_token(false);
+        let mut props : Vec<Property> = vec![Property::Diagnostics(self.diagnostics.clone(), document.file.clone()), Property::Limits(self.budget.clone()), Property::Clock(self.clock.clone())];
 
-            if self.parser.current.kind != TokenType::Error {
-                break;
-            }
+        // The constants defined so far come first, so that properties and code blocks can use them
+        for (name, value) in self.constants.get_values() {
+            props.push(Property::Constant(name, value));
         }
-    }
 
-    /// Advance one token and allow whitespace
-    fn advance_with_whitespace(&mut self) {
-        self.parser.previous = self.parser.current.clone();
-
-        loop {
-            self.parser.current = self.scanner.scan_token(true);
+        for p in &block.properties {
+            props.push(Property::Property(p.name.clone(), convert_hex(&p.value), p.token.clone()));
+        }
 
-            if self.parser.current.kind != TokenType::Error {
-                break;
-            }
+        for f in &block.functions {
+            props.push(Property::Function(f.name.clone(), f.args.join(","), f.body.clone(), f.token.clone(), document.line_text(f.token.line)));
         }
-    }
 
-    /// Prints the current Token.
-    fn debug_current(&mut self, msg: &str) {
-        println!("{} {:?}", msg, self.parser.current);
+        props
     }
 
-    /// Consume the current token if the type matches
-    fn consume(&mut self, kind: TokenType, message: &str) {
-        if self.parser.current.kind == kind {
-            self.advance();
-            return;
+    /// Records the warning according to the warning level of its code.
+    fn warning(&mut self, warning: RPUError) {
+        match self.warning_levels.get(warning.code).copied().unwrap_or(WarningLevel::Warn) {
+            WarningLevel::Allow => {},
+            WarningLevel::Warn => self.warnings.push(warning.with_severity(Severity::Warning)),
+            WarningLevel::Deny => self.errors.push(warning.with_severity(Severity::Error)),
         }
-        self.error_at_current(message);
     }
 
-    /// Consume the current token if the type matches
-    fn _consume_with_whitespace(&mut self, kind: TokenType, message: &str) {
-        if self.parser.current.kind == kind {
-            self.advance_with_whitespace();
-            return;
+    /// Replaces the diagnostics with the errors and warnings of this compile.
+    fn report(&mut self) {
+        self.diagnostics.clear();
+        for error in &self.errors {
+            self.diagnostics.push(error.clone());
         }
-        self.error_at_current(message);
-    }
-
-    /// If the current token matches advance, else do not.
-    fn matches(&mut self, kind: TokenType) -> bool {
-        if !self.check(kind) {
-            false
-        } else {
-            self.advance();
-            true
+        for warning in std::mem::take(&mut self.warnings) {
+            self.diagnostics.push(warning);
         }
     }
 
-    /// Check if the current token matches.
-    fn check(&self, kind: TokenType) -> bool {
-        self.parser.current.kind == kind
+    /// Error at the given token of the document
+    fn error_at(&mut self, document: &Document, token: &Token, message: String) {
+        let source_line = document.line_text(token.line);
+        self.errors.push(RPUError::at_token(ErrorType::Syntax, message, token, source_line).in_file(document.file.clone()));
     }
 
-    /// get_The indent level of the current token.
-    fn indent(&self) -> usize {
-        self.parser.current.indent
+    /// Records the errors of the given result, if any.
+    fn add_result(&mut self, rc: Result<(), Vec<RPUError>>, document: &Document) {
+        if let Err(errors) = rc {
+            for mut err in errors {
+                if err.source_line.is_empty() {
+                    err.source_line = document.line_text(err.line as usize);
+                }
+                self.errors.push(err.in_file(document.file.clone()));
+            }
+        }
     }
+}
 
-    /// Error at the current token
-    fn error_at_current(&mut self, message: &str) {
-        self.error_at(self.parser.current.clone(), message)
+/// Splits a comma separated list, commas inside parentheses and strings are kept.
+fn split_arguments(value: &str) -> Vec<String> {
+    let mut parts = vec![];
+    let mut current = String::new();
+    let mut depth = 0;
+    let mut in_string = false;
+
+    for c in value.chars() {
+        match c {
+            '"' | '`' => in_string = !in_string,
+            '(' if !in_string => depth += 1,
+            ')' if !in_string => depth -= 1,
+            ',' if !in_string && depth == 0 => {
+                parts.push(current.trim().to_string());
+                current = String::new();
+                continue;
+            },
+            _ => {}
+        }
+        current.push(c);
     }
+    parts.push(current.trim().to_string());
+    parts
+}
 
-    /// Error at the previous token
-    fn _error(&mut self, message: &str) {
-        self.error_at(self.parser.previous.clone(), message)
-    }
+/// Converts a hex color value like #a9957b into an F4, other values are returned unchanged.
+fn convert_hex(value: &str) -> String {
+    if let Some(color) = value.strip_prefix('#') {
+        use colors_transform::{Rgb};
 
-    /// Error at the given token
-    fn error_at(&mut self, _token: Token, message: &str) {
-        if self.parser.error.is_some() { return; }
-        self.parser.error = Some(RPUError::new(ErrorType::Syntax, message.to_string(), self.parser.current.line as u32));
+        if let Ok(rgb) = Rgb::from_hex_str(color) {
+            return format!("F4({:.3}, {:.3}, {:.3}, 1.0)", rgb.get_red() as F / 255.0, rgb.get_green() as F / 255.0, rgb.get_blue() as F / 255.0 );
+        }
     }
-}
\ No newline at end of file
+    value.to_string()
+}
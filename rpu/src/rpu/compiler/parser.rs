@@ -0,0 +1,444 @@
+use crate::prelude::*;
+
+use super::scanner::TokenType;
+
+/// Parses RPU source into a Document without creating any objects. Errors are collected and parsing
+/// continues on the next line, so the document contains everything which could be parsed.
+pub struct Parser {
+    scanner             : Scanner,
+    file                : Option<PathBuf>,
+
+    current             : Token,
+    previous            : Token,
+
+    errors              : Vec<RPUError>,
+    /// The full line comments inside legends, which are not seen by the scanner.
+    comments            : Vec<Comment>,
+}
+
+impl Parser {
+
+    pub fn new(code: String, file: Option<PathBuf>) -> Self {
+        Self {
+            scanner     : Scanner::new(code),
+            file,
+
+            current     : Token::synthetic("".to_owned()),
+            previous    : Token::synthetic("".to_owned()),
+
+            errors      : vec![],
+            comments    : vec![],
+        }
+    }
+
+    /// Parses the source into a document.
+    pub fn parse(mut self) -> (Document, Vec<RPUError>) {
+        let mut document = Document::new(self.scanner.code().to_string(), self.file.clone());
+
+        self.advance();
+
+        while !self.check(TokenType::Eof) {
+            if self.check(TokenType::Identifier) {
+                if self.current.lexeme == "Import" {
+                    if let Some(import) = self.import() {
+                        document.items.push(Item::Import(import));
+                    }
+                } else {
+                    let token = self.current.clone();
+                    self.advance();
+
+                    if self.check(TokenType::Equal) || self.check(TokenType::LeftParen) {
+                        self.error_at(token.clone(), format!("'{}' has to be indented below an element.", token.lexeme).as_str());
+                        self.skip_line();
+                    } else {
+                        let block = self.block(token);
+                        document.items.push(Item::Block(Box::new(block)));
+                    }
+                }
+            } else
+            if self.check(TokenType::Let) {
+                if let Some(binding) = self.let_binding() {
+                    document.items.push(Item::Let(binding));
+                }
+            } else {
+                self.error_at_current(format!("Unexpected '{}'.", self.current.lexeme).as_str());
+                self.skip_line();
+            }
+        }
+
+        for token in self.scanner.take_comments() {
+            let line = self.scanner.line_text(token.line);
+            let before : String = line.chars().take(token.column.saturating_sub(1)).collect();
+            document.comments.push(Comment {
+                text        : token.lexeme.trim_end().to_string(),
+                trailing    : !before.trim().is_empty(),
+                token,
+            });
+        }
+        document.comments.append(&mut self.comments);
+        document.comments.sort_by_key(|c| c.token.start);
+
+        (document, self.errors)
+    }
+
+    /// Reads an import directive.
+    fn import(&mut self) -> Option<Import> {
+        let line = self.current.line;
+        self.advance();
+
+        if !self.check(TokenType::String) || self.current.line != line {
+            self.error_at_current("Expected the path of the file to import, i.e. Import \"materials/bricks.rpu\".");
+            self.skip_line();
+            return None;
+        }
+
+        let token = self.current.clone();
+        self.advance();
+
+        Some(Import {
+            path        : token.lexeme.trim_matches('"').trim_matches('`').to_string(),
+            token,
+        })
+    }
+
+    /// Reads a let binding, i.e. let wall = #a9957b.
+    fn let_binding(&mut self) -> Option<LetBinding> {
+        let keyword = self.current.clone();
+        let line = keyword.line;
+        self.advance();
+
+        if !self.check(TokenType::Identifier) || self.current.line != line {
+            self.error_at_current("Expected the name of the constant, i.e. let wall = #a9957b.");
+            self.skip_line();
+            return None;
+        }
+
+        let token = self.current.clone();
+        self.advance();
+
+        if !self.check(TokenType::Equal) || self.current.line != line {
+            self.error_at(token.clone(), format!("Expected '=' after '{}'.", token.lexeme).as_str());
+            self.skip_line();
+            return None;
+        }
+
+        let (value, comment) = split_comment(&self.scanner.scanline(0));
+        self.advance();
+
+        if value.is_empty() {
+            self.error_at(token.clone(), format!("Expected a value for '{}'.", token.lexeme).as_str());
+            return None;
+        }
+
+        Some(LetBinding {
+            name        : token.lexeme.clone(),
+            value,
+            comment,
+            token       : keyword,
+        })
+    }
+
+    /// Reads a block with the given keyword token. Everything indented deeper than the keyword belongs to the block.
+    fn block(&mut self, token: Token) -> Block {
+        let mut block = Block::new(token.clone());
+
+        let indent = token.indent;
+        let line = token.line;
+
+        // * outputs the block
+        if self.check(TokenType::Star) && self.current.line == line {
+            block.output = true;
+            self.advance();
+        }
+
+        // 'a defines the instance symbol
+        if self.check(TokenType::Apostrophe) && self.current.line == line {
+            self.advance();
+            if self.current.line == line && !self.check(TokenType::Eof) {
+                block.symbol = Some(symbol_name(&self.current));
+                self.advance();
+            } else {
+                self.error_at(self.previous.clone(), "Expected an instance symbol after '.");
+            }
+        }
+
+        // : Name derives a top level block from a prototype
+        if indent == 0 && self.check(TokenType::Colon) && self.current.line == line {
+            self.advance();
+            if self.current.line == line && (self.check(TokenType::Identifier) || self.check(TokenType::String)) {
+                block.base = Some(BaseRef {
+                    name        : self.current.lexeme.trim_matches('"').to_string(),
+                    symbol      : false,
+                    token       : self.current.clone(),
+                });
+                self.advance();
+            } else
+            if self.current.line == line && self.check(TokenType::Apostrophe) {
+                self.advance();
+                if self.current.line == line && !self.check(TokenType::Eof) {
+                    block.base = Some(BaseRef {
+                        name        : symbol_name(&self.current),
+                        symbol      : true,
+                        token       : self.current.clone(),
+                    });
+                    self.advance();
+                } else {
+                    self.error_at(self.previous.clone(), "Expected an instance symbol after '.");
+                }
+            } else {
+                self.error_at(self.previous.clone(), "Expected the prototype after ':', i.e. Texture : Bricks or Voxel'b : 'a.");
+            }
+        }
+
+        if self.current.line == line && !self.check(TokenType::Eof) {
+            self.error_at_current(format!("Unexpected '{}' after {}.", self.current.lexeme, block.kind).as_str());
+            self.skip_line();
+        }
+
+        while !self.check(TokenType::Eof) && self.current.indent > indent {
+
+            if !self.check(TokenType::Identifier) {
+                self.error_at_current(format!("Expected a property, function or element, found '{}'.", self.current.lexeme).as_str());
+                self.skip_line();
+                continue;
+            }
+
+            // The entries of a legend are not tokens of the language and are read line by line
+            if indent == 0 && self.current.lexeme == "Legend" {
+                let legend = self.legend();
+                if block.legend.is_some() {
+                    self.error_at(legend.token.clone(), format!("{} already has a legend.", block.kind).as_str());
+                }
+                block.legend = Some(legend);
+                continue;
+            }
+
+            let name = self.current.clone();
+            self.advance();
+
+            if self.check(TokenType::Equal) {
+                let (value, comment) = split_comment(&self.scanner.scanline(0));
+                if value.is_empty() {
+                    self.error_at_current(format!("Expected a value for '{}'.", name.lexeme).as_str());
+                } else {
+                    block.properties.push(PropertyAssign {
+                        name        : name.lexeme.clone(),
+                        value,
+                        comment,
+                        token       : name,
+                    });
+                }
+                self.advance();
+            } else
+            if self.check(TokenType::LeftParen) {
+                let mut args = vec![];
+                self.advance();
+                loop {
+                    if self.check(TokenType::Identifier) {
+                        args.push(self.current.lexeme.clone());
+                        self.advance();
+                    } else
+                    if self.check(TokenType::RightParen) {
+                        break;
+                    } else
+                    if self.check(TokenType::Comma) {
+                        self.advance();
+                    } else {
+                        self.error_at_current("Invalid function arguments.");
+                        break;
+                    }
+                }
+
+                // The body starts right after the closing parenthesis, code on the same line stays on it
+                if let Ok(body) = self.scanner.scan_indention_block(0, name.indent) {
+                    block.functions.push(FunctionDef {
+                        name        : name.lexeme.clone(),
+                        args,
+                        body,
+                        token       : name,
+                    });
+                }
+                self.advance();
+            } else {
+                let child = self.block(name);
+                block.children.push(child);
+            }
+        }
+
+        // The map rows of a layout, lines at the top level starting with a colon
+        if indent == 0 {
+            while self.check(TokenType::Colon) && self.current.column == 1 {
+                let row_token = self.current.clone();
+                let line = self.scanner.line_text(row_token.line);
+                let colons = line.chars().take_while(|c| *c == ':').count();
+
+                let map = block.map.get_or_insert(GridMap { rows: vec![] });
+                map.rows.push(GridRow {
+                    colons,
+                    text        : line[colons..].trim_end_matches('\r').to_string(),
+                    token       : row_token,
+                });
+
+                self.scanner.scanline(0);
+                self.advance();
+            }
+        }
+
+        block
+    }
+
+    /// Reads the legend of a layout, the current token is the Legend keyword. Every line indented deeper
+    /// is an entry like ## = 'a, the key is any text without spaces.
+    fn legend(&mut self) -> Legend {
+        let token = self.current.clone();
+        let body = self.scanner.scan_indention_block(0, token.indent).unwrap_or_default();
+        self.advance();
+
+        let mut legend = Legend {
+            entries     : vec![],
+            comment     : None,
+            token       : token.clone(),
+        };
+
+        // The body starts right after the keyword
+        let mut offset = token.end;
+        for (index, text) in body.split('\n').enumerate() {
+            let start = offset;
+            offset += text.len() + 1;
+
+            let text = text.trim_end_matches('\r');
+            let trimmed = text.trim_start();
+            let column = text.chars().count() - trimmed.chars().count() + 1;
+            let line = token.line + index;
+
+            let mut entry_token = token.clone();
+            entry_token.kind = TokenType::Identifier;
+            entry_token.line = line;
+            entry_token.indent = column - 1;
+            entry_token.column = column;
+            entry_token.start = start + text.len() - trimmed.len();
+
+            if trimmed.starts_with("//") {
+                entry_token.kind = TokenType::SingeLineComment;
+                entry_token.lexeme = trimmed.to_string();
+                entry_token.end = entry_token.start + trimmed.len();
+                if index == 0 {
+                    legend.comment = Some(trimmed.trim_end().to_string());
+                } else {
+                    self.comments.push(Comment {
+                        text        : trimmed.trim_end().to_string(),
+                        trailing    : false,
+                        token       : entry_token,
+                    });
+                }
+                continue;
+            }
+            if trimmed.is_empty() {
+                continue;
+            }
+
+            let key = trimmed.split_whitespace().next().unwrap_or("");
+            entry_token.lexeme = key.to_string();
+            entry_token.end = entry_token.start + key.len();
+
+            if index == 0 {
+                self.error_at(entry_token, format!("Unexpected '{}' after Legend.", key).as_str());
+                continue;
+            }
+
+            let rest = trimmed[key.len()..].trim_start();
+            if !rest.starts_with('=') {
+                self.error_at(entry_token, "Expected a legend entry, i.e. ## = 'a.");
+                continue;
+            }
+
+            let (value, comment) = split_comment(&rest[1..]);
+            if value.is_empty() {
+                self.error_at(entry_token, format!("Expected a value for '{}'.", key).as_str());
+                continue;
+            }
+            if legend.get(key).is_some() {
+                self.error_at(entry_token, format!("Legend key '{}' is already defined.", key).as_str());
+                continue;
+            }
+
+            legend.entries.push(LegendEntry {
+                key         : key.to_string(),
+                value,
+                comment,
+                token       : entry_token,
+            });
+        }
+
+        legend
+    }
+
+    /// Skips the rest of the line of the current token.
+    fn skip_line(&mut self) {
+        if !self.check(TokenType::Eof) {
+            self.scanner.scanline(0);
+            self.advance();
+        }
+    }
+
+    /// Advance one token
+    fn advance(&mut self) {
+        self.previous = self.current.clone();
+
+        loop {
+            self.current = self.scanner.scan_token(false);
+
+            if self.current.kind != TokenType::Error {
+                break;
+            }
+        }
+    }
+
+    /// Check if the current token matches.
+    fn check(&self, kind: TokenType) -> bool {
+        self.current.kind == kind
+    }
+
+    /// Error at the current token
+    fn error_at_current(&mut self, message: &str) {
+        self.error_at(self.current.clone(), message)
+    }
+
+    /// Error at the given token
+    fn error_at(&mut self, token: Token, message: &str) {
+        let source_line = self.scanner.line_text(token.line);
+        self.errors.push(RPUError::at_token(ErrorType::Syntax, message.to_string(), &token, source_line).in_file(self.file.clone()));
+    }
+}
+
+/// Splits a trailing // comment from a property value, comments inside strings are kept.
+fn split_comment(value: &str) -> (String, Option<String>) {
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut prev = ' ';
+    for (index, c) in value.char_indices() {
+        // The character after a backslash in a string is escaped
+        if in_string && (escaped || c == '\\') {
+            escaped = !escaped;
+            prev = c;
+            continue;
+        }
+        if c == '"' || c == '`' {
+            in_string = !in_string;
+        } else
+        if c == '/' && prev == '/' && !in_string {
+            return (value[..index - 1].trim().to_string(), Some(value[index - 1..].trim_end().to_string()));
+        }
+        prev = c;
+    }
+    (value.trim().to_string(), None)
+}
+
+/// The name of an instance symbol, identifiers are names like 'door, otherwise the first character is the symbol.
+fn symbol_name(token: &Token) -> String {
+    if token.kind == TokenType::Identifier {
+        token.lexeme.clone()
+    } else {
+        token.lexeme.chars().next().map(|c| c.to_string()).unwrap_or_default()
+    }
+}
@@ -71,6 +71,17 @@ pub struct Token {
     pub line        : usize,
     pub lexeme      : String,
     pub indent      : usize,
+    /// 1 based column of the first character of the token.
+    pub column      : usize,
+    /// Byte span of the token in the source.
+    pub start       : usize,
+    pub end         : usize,
+}
+
+impl Default for Token {
+    fn default() -> Self {
+        Token::synthetic("".to_owned())
+    }
 }
 
 #[allow(dead_code)]
@@ -81,6 +92,9 @@ impl Token {
             lexeme  : text,
             line    : 0,
             indent  : 0,
+            column  : 0,
+            start   : 0,
+            end     : 0,
         }
     }
 }
@@ -92,7 +106,10 @@ pub struct Scanner {
     start           : usize,
     current         : usize,
     line            : usize,
+    line_start      : usize,
     indent          : usize,
+    /// The // comments skipped outside of code blocks and property values.
+    comments        : Vec<Token>,
 }
 
 #[allow(dead_code)]
@@ -122,7 +139,9 @@ impl<'sourcecode> Scanner {
             start       : 0,
             current     : 0,
             line        : 1,
+            line_start  : 0,
             indent      : 0,
+            comments    : vec![],
         }
     }
 
@@ -164,7 +183,13 @@ impl<'sourcecode> Scanner {
             b'`' => self.string2(),
             c if is_digit(c) => self.number(),
             c if is_alpha(c) => self.identifier(),
-            _ => self.make_token(TokenType::Unknown),//self.error_token("Unexpected character."),
+            _ => {
+                // Keep multi byte characters in one token
+                while !self.is_at_end() && (self.peek() & 0xC0) == 0x80 {
+                    self.advance();
+                }
+                self.make_token(TokenType::Unknown)//self.error_token("Unexpected character."),
+            }
         }
     }
 
@@ -182,7 +207,34 @@ impl<'sourcecode> Scanner {
             lexeme      : self.lexeme(),
             line        : self.line,
             indent      : self.indent,
+            column      : self.column(self.start),
+            start       : self.start,
+            end         : self.current,
+        }
+    }
+
+    /// The 1 based column of the given byte offset on the current line.
+    fn column(&self, offset: usize) -> usize {
+        let start = self.line_start.min(offset);
+        self.code[start..offset].chars().count() + 1
+    }
+
+    /// Returns the text of the given 1 based source line, used for error reports.
+    pub fn line_text(&self, line: usize) -> String {
+        if line == 0 {
+            return "".to_string();
         }
+        self.code.lines().nth(line - 1).unwrap_or("").to_string()
+    }
+
+    /// Returns the comments skipped so far and clears the list.
+    pub fn take_comments(&mut self) -> Vec<Token> {
+        std::mem::take(&mut self.comments)
+    }
+
+    /// Returns the source code of the scanner.
+    pub fn code(&self) -> &str {
+        &self.code
     }
 
     pub fn peek(&self) -> u8 {
@@ -193,7 +245,7 @@ impl<'sourcecode> Scanner {
         }
     }
     pub fn peek_next(&self) -> u8 {
-        if self.current > self.code.len() - 2 {
+        if self.current + 1 >= self.code.len() {
             b'\0'
         } else {
             self.code.as_bytes()[self.current + 1]
@@ -206,6 +258,9 @@ impl<'sourcecode> Scanner {
             lexeme      : message,
             line        : self.line,
             indent      : self.indent,
+            column      : self.column(self.start),
+            start       : self.start,
+            end         : self.current,
         }
     }
 
@@ -240,43 +295,35 @@ impl<'sourcecode> Scanner {
                 b'\n' => {
                     self.line += 1;
                     self.advance();
+                    self.line_start = self.current;
                     self.indent = 0;
                     after_lf = true;
                 }
                 b'/' if self.peek_next() == b'/' => {
+                    self.start = self.current;
                     while self.peek() != b'\n' && !self.is_at_end() {
                         self.advance();
                     }
+                    let comment = self.make_token(TokenType::SingeLineComment);
+                    self.comments.push(comment);
                 }
                 _ => return,
             }
         }
     }
 
+    /// Scans the rest of the line, the line feed is left for the next token.
     pub fn scanline(&mut self, advance: usize) -> String {
-        let mut string = "".to_string();
-
-        let start = self.current + advance;
-        while !self.is_at_end() {
-            match self.peek() {
-                b'\n' => {
-                    string = self.code[start..self.current].to_string();
-                    self.advance();
-                    self.line += 1;
-                    break;
-                },
-                _ => {
-                    self.advance();
-                },
-            }
+        let start = (self.current + advance).min(self.code.len());
+        while !self.is_at_end() && self.peek() != b'\n' {
+            self.advance();
         }
-        string
+        self.code[start..self.current].trim_end_matches('\r').to_string()
     }
 
+    /// Scans all lines which are indented deeper than min_indent. The line feed of the last line of the block is left for the next token.
     pub fn scan_indention_block(&mut self, advance: usize, min_indent: usize) -> Result<String, String> {
-        let mut string = "".to_string();
-
-        let start = self.current + advance;
+        let start = (self.current + advance).min(self.code.len());
 
         let mut newline = false;
         let mut indent = min_indent + 1;
@@ -292,21 +339,22 @@ impl<'sourcecode> Scanner {
                     newline = true;
                     self.advance();
                     self.line += 1;
+                    self.line_start = self.current;
                     indent = 0;
                 },
                 _ => {
                     newline = false;
                     if indent <= min_indent {
-//                        return Err("Indention of function block too small.".to_owned());
-                        string = self.code[start..self.current].to_string();
-                        self.current -= 1;
-                        break;
+                        // Rewind to the line feed so that the indention of the next token is correct
+                        self.current = self.line_start - 1;
+                        self.line -= 1;
+                        return Ok(self.code[start.min(self.current)..self.current].to_string());
                     }
                     self.advance();
                 }
             }
         }
-        return Ok(string);
+        Ok(self.code[start..self.current].to_string())
     }
 
 
@@ -0,0 +1,220 @@
+use crate::prelude::*;
+
+/// Writes a compiled context back to a document, i.e. to save a scene which was changed through the API.
+/// Properties are written with their current values, code blocks as they were written in the source.
+impl Context {
+
+    /// Converts the context into a document. Constants come first, followed by the layer definitions, the
+    /// textures, objects, sprites, the camera and the layouts.
+    pub fn to_document(&self) -> Document {
+        let mut document = Document::new(String::new(), None);
+
+        for (name, value) in &self.constants {
+            document.items.push(Item::Let(LetBinding {
+                name        : name.clone(),
+                value       : value.to_string(),
+                comment     : None,
+                token       : Token::default(),
+            }));
+        }
+
+        for layer in &self.layers {
+            document.items.push(Item::Block(Box::new(layer_block(layer))));
+        }
+
+        for (index, node_index) in self.textures.iter().enumerate() {
+            if let Some(mut block) = self.element_block(*node_index) {
+                block.output = self.out_texture == Some(index);
+                document.items.push(Item::Block(Box::new(block)));
+            }
+        }
+
+        // Sorted, so that a node with several symbols always gets the same one
+        let mut sorted : Vec<(&String, &usize)> = self.symbols_node_index.iter().collect();
+        sorted.sort();
+        let mut symbols : HashMap<usize, String> = HashMap::new();
+        for (symbol, index) in sorted {
+            symbols.entry(*index).or_insert(symbol.clone());
+        }
+
+        for node_index in &self.objects {
+            let mut block = match &self.nodes[*node_index].object {
+                Object::AnalyticalObject(object) => engine_block(object.schema(), object.get_engine()),
+                Object::SDF3D(object) => engine_block(object.schema(), object.get_engine()),
+                _ => continue,
+            };
+            block.symbol = symbols.get(node_index).cloned();
+            document.items.push(Item::Block(Box::new(block)));
+        }
+
+        for sprite in &self.sprites {
+            document.items.push(Item::Block(Box::new(engine_block(sprite.schema(), sprite.get_engine()))));
+        }
+
+        document.items.push(Item::Block(Box::new(engine_block(self.camera.schema(), self.camera.get_engine()))));
+
+        for layout in &self.layouts {
+            if let Object::Layout3D(layout) = layout {
+                let mut block = engine_block(layout.schema(), layout.get_engine());
+                map_block(&mut block, &layout.get_map3d(), &symbols);
+                document.items.push(Item::Block(Box::new(block)));
+            }
+        }
+
+        document
+    }
+
+    /// Writes the context as RPU source.
+    pub fn to_source(&self) -> String {
+        Formatter::new().format_document(&self.to_document())
+    }
+
+    /// Writes the context as JSON, see Document::to_json.
+    pub fn to_json(&self) -> String {
+        self.to_document().to_json()
+    }
+
+    /// The block of a texture or element with all its child elements.
+    fn element_block(&self, node_index: usize) -> Option<Block> {
+        let node = &self.nodes[node_index];
+        match &node.object {
+            Object::Element2D(element) => {
+                let mut block = engine_block(element.schema(), element.get_engine());
+                // Textures draw their child textures before their layers
+                for child in node.childs.iter().chain(node.elements.iter()) {
+                    if let Some(child) = self.element_block(*child) {
+                        block.children.push(child);
+                    }
+                }
+                Some(block)
+            },
+            _ => None,
+        }
+    }
+}
+
+/// The block of an object with the current values of its properties and its code blocks.
+fn engine_block(schema: Schema, engine: &ScriptEngine) -> Block {
+    let mut block = Block::new(Token::synthetic(schema.name.to_string()));
+
+    for property in &schema.properties {
+        if let Some(value) = engine.get_value(&property.name) {
+            block.properties.push(PropertyAssign {
+                name        : property.name.to_string(),
+                value       : value.to_string(),
+                comment     : None,
+                token       : Token::default(),
+            });
+        }
+    }
+
+    for (name, args, body) in engine.get_functions() {
+        block.functions.push(FunctionDef {
+            name        : name.clone(),
+            args        : args.split(',').map(|a| a.trim().to_string()).filter(|a| !a.is_empty()).collect(),
+            body        : body.clone(),
+            token       : Token::default(),
+        });
+    }
+
+    block
+}
+
+/// The Layer block without its positions in the source, the written document has no source.
+fn layer_block(layer: &Block) -> Block {
+    let mut block = layer.clone();
+    block.token = Token::synthetic(block.kind.clone());
+    for property in &mut block.properties {
+        property.token = Token::default();
+    }
+    for function in &mut block.functions {
+        function.token = Token::default();
+    }
+    block
+}
+
+/// Writes the cells of a layout as map rows. Cells with modifiers or named symbols are written with a legend.
+/// The first row of a level above the ground is its second row, cells in front of it can only be created by
+/// the generate() code block of the layout, which is written as well.
+fn map_block(block: &mut Block, cells: &HashMap<(i32, i32, i32), MapCell>, symbols: &HashMap<usize, String>) {
+    if cells.is_empty() {
+        return;
+    }
+
+    // Single character symbols are written as they are, everything else gets a numbered legend key
+    let mut distinct : Vec<MapCell> = vec![];
+    for cell in cells.values() {
+        let plain = !cell.is_modified() && symbols.get(&cell.node).is_some_and(|s| s.chars().count() == 1);
+        if !plain && !distinct.contains(cell) {
+            distinct.push(*cell);
+        }
+    }
+    distinct.sort_by(|a, b| (a.node, a.rotation, a.height).partial_cmp(&(b.node, b.rotation, b.height)).unwrap_or(std::cmp::Ordering::Equal));
+
+    let digits = (distinct.len().max(1) - 1).to_string().len().max(2);
+    let mut keys : Vec<(MapCell, String)> = vec![];
+    if !distinct.is_empty() {
+        let mut legend = Legend {
+            entries     : vec![],
+            comment     : None,
+            token       : Token::default(),
+        };
+        for (index, cell) in distinct.iter().enumerate() {
+            let symbol = match symbols.get(&cell.node) {
+                Some(symbol) => symbol,
+                None => continue,
+            };
+            let key = format!("{:0width$}", index, width = digits);
+            let mut value = format!("'{}", symbol);
+            if cell.rotation != 0.0 {
+                value += format!(", rotation = {:?}", cell.rotation).as_str();
+            }
+            if cell.height != 0.0 {
+                value += format!(", height = {:?}", cell.height).as_str();
+            }
+            legend.entries.push(LegendEntry {
+                key         : key.clone(),
+                value,
+                comment     : None,
+                token       : Token::default(),
+            });
+            keys.push((*cell, key));
+        }
+        block.legend = Some(legend);
+    }
+    let width = if keys.is_empty() { 1 } else { digits };
+
+    let text = |y: i32, z: i32| -> String {
+        let max_x = cells.keys().filter(|k| k.1 == y && k.2 == z).map(|k| k.0).max().unwrap_or(-1);
+        let mut text = String::new();
+        for x in 0..=max_x {
+            let key = cells.get(&(x, y, z)).and_then(|cell| {
+                keys.iter().find(|(c, _)| c == cell).map(|(_, key)| key.clone()).or(symbols.get(&cell.node).cloned())
+            }).unwrap_or_default();
+            text += format!("{:width$}", key, width = width).as_str();
+        }
+        text.trim_end().to_string()
+    };
+
+    let mut rows = vec![];
+    let max_y = cells.keys().map(|k| k.1).max().unwrap_or(0);
+    let mut level = 0;
+    for y in 0..=max_y {
+        let max_z = cells.keys().filter(|k| k.1 == y).map(|k| k.2).max();
+        if y == 0 {
+            for z in 0..=max_z.unwrap_or(0) {
+                rows.push(GridRow { colons: 1, text: text(y, z), token: Token::default() });
+            }
+        } else
+        if let Some(max_z) = max_z {
+            // Every colon after the first goes up one level
+            for z in 1..=max_z.max(1) {
+                let colons = if z == 1 { 1 + (y - level) as usize } else { 1 };
+                rows.push(GridRow { colons, text: text(y, z), token: Token::default() });
+            }
+            level = y;
+        }
+    }
+
+    block.map = Some(GridMap { rows });
+}
@@ -1,51 +1,74 @@
 use crate::prelude::*;
+use super::pattern::{read_color, read_float, Pattern, PatternLayer};
 
-pub struct Bricks<'a> {
-    engine                  : ScriptEngine<'a>,
+/// Rows of bricks with beveled, rounded corners in the color, the mortar shows the layers below.
+pub type Bricks<'a> = PatternLayer<'a, BricksPattern>;
+
+pub struct BricksPattern {
     color                   : GF4,
+    cells                   : F,
+    ratio                   : F,
+    offset                  : F,
+    gap                     : F,
+    bevel                   : F,
+    roundness               : F,
 }
 
-impl Element2D for Bricks<'_> {
-    fn new() -> Self {
-
-        let engine = ScriptEngine::new();
+impl Pattern for BricksPattern {
+    const NAME: &'static str = "Bricks";
 
+    fn new() -> Self {
         Self {
-            engine,
             color           : Vector4::new(1.0, 1.0, 1.0, 1.0),
+            cells           : 4.0,
+            ratio           : 2.0,
+            offset          : 0.5,
+            gap             : 0.08,
+            bevel           : 0.07,
+            roundness       : 0.25,
         }
     }
 
-    fn name(&self) -> String {
-        "Bricks".to_string()
+    fn properties() -> Vec<PropertyDef> {
+        vec![
+            PropertyDef::new("color", PropertyType::F4, Some(Value::F4(GF4::new(1.0, 1.0, 1.0, 1.0))), None, "The color of the bricks."),
+            PropertyDef::new("cells", PropertyType::F, Some(Value::F(4.0)), Some((0.01, 1024.0)), "The number of rows of bricks per unit."),
+            PropertyDef::new("ratio", PropertyType::F, Some(Value::F(2.0)), Some((0.01, 100.0)), "The width of a brick relative to its height."),
+            PropertyDef::new("offset", PropertyType::F, Some(Value::F(0.5)), Some((0.0, 1.0)), "How far every row is shifted against the row below, in bricks. 0 stacks the bricks."),
+            PropertyDef::new("gap", PropertyType::F, Some(Value::F(0.08)), Some((0.0, 0.5)), "The width of the mortar, relative to the height of a brick."),
+            PropertyDef::new("bevel", PropertyType::F, Some(Value::F(0.07)), Some((0.001, 0.5)), "The width of the bevel of a brick, relative to its height."),
+            PropertyDef::new("roundness", PropertyType::F, Some(Value::F(0.25)), Some((0.0, 0.5)), "The radius of the rounded corners of a brick, relative to its height."),
+        ]
     }
 
-    fn compute_color_at(&self, uv : &UV, color: &mut GF4, _node: usize, ctx: &Context) {
+    fn read(&mut self, engine: &ScriptEngine) {
+        read_color(engine, "color", &mut self.color);
+        read_float(engine, "cells", &mut self.cells);
+        read_float(engine, "ratio", &mut self.ratio);
+        read_float(engine, "offset", &mut self.offset);
+        read_float(engine, "gap", &mut self.gap);
+        read_float(engine, "bevel", &mut self.bevel);
+        read_float(engine, "roundness", &mut self.roundness);
+    }
 
-        let cell = 1.0;
-        let ratio = 2.0;
-        let brick = 1.0;
-        let gap_x = 0.08;
+    fn pattern_at(&self, uv: &UV, ctx: &Context) -> (F, GF4) {
 
         let mut uv_local = uv.world;
         let rr = ctx.size[0] as F / ctx.size[1] as F;
         uv_local.x *= rr;
         uv_local.y *= rr;
 
-        let mut u = uv_local * 4.0 + GF2::new(10000.0, 10000.0);
+        let mut u = uv_local * self.cells + GF2::new(10000.0, 10000.0);
 
-        let bevelx = 0.07;
-        let bevel = GF2::new(bevelx, bevelx);
-        let gap = GF2::new(gap_x, gap_x);
-        let round = 0.25;
+        let bevel = GF2::new(self.bevel, self.bevel);
+        let gap = GF2::new(self.gap, self.gap);
+        let round = self.roundness;
         //let missing = 0.0;
 
-        let w = GF2::new(ratio,1.0);
-        u = u.component_mul(&GF2::new(cell, cell).component_div(&w));
+        let w = GF2::new(self.ratio, 1.0);
+        u = u.component_mul(&GF2::new(1.0, 1.0).component_div(&w));
 
-        if brick == 1.0 {
-            u.x += 0.5 * u.y.floor() % 2.0;
-        }
+        u.x += self.offset * u.y.floor() % 2.0;
 
         //hash = hash21(floor(U))
 
@@ -63,36 +86,6 @@ impl Element2D for Bricks<'_> {
         //    isMissing = true
         //}
 
-        *color = glm::mix(&color, &self.color, (self.color.w * m).clamp(0.0, 1.0));
-
-        //*color = GF4::new(m, m, m, 1.0);
-        self.engine.execute_shader(uv, color);
-    }
-}
-
-impl Script for Bricks<'_> {
-
-    fn get_scope<'a>(&mut self) -> &'a Scope {
-        self.engine.get_scope()
-    }
-
-    fn get_engine<'a>(&self) -> &'a ScriptEngine {
-        &self.engine
-    }
-
-    fn apply_properties(&mut self, props: Vec<Property>) -> Result<(), RPUError> {
-        let rc = self.engine.apply_properties(props);
-        if let Some(color) = self.engine.get_vector4("color") {
-            self.color = color;
-        }
-        rc
-    }
-
-    fn execute(&mut self, code: String) {
-        self.engine.execute(code);
-    }
-
-    fn set_code_block(&mut self, name: String, code: String) {
-        _ = self.engine.set_code_block(name, code);
+        (m, self.color)
     }
 }
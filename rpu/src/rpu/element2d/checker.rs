@@ -0,0 +1,39 @@
+use crate::prelude::*;
+use super::pattern::{read_color, read_int, Pattern, PatternLayer};
+
+/// A checkerboard, every other square has the color, the others show the layers below.
+pub type Checker<'a> = PatternLayer<'a, CheckerPattern>;
+
+pub struct CheckerPattern {
+    color                   : GF4,
+    cells                   : I,
+}
+
+impl Pattern for CheckerPattern {
+    const NAME: &'static str = "Checker";
+
+    fn new() -> Self {
+        Self {
+            color           : GF4::new(1.0, 1.0, 1.0, 1.0),
+            cells           : 4,
+        }
+    }
+
+    fn properties() -> Vec<PropertyDef> {
+        vec![
+            PropertyDef::new("color", PropertyType::F4, Some(Value::F4(GF4::new(1.0, 1.0, 1.0, 1.0))), None, "The color of the squares."),
+            PropertyDef::new("cells", PropertyType::Int, Some(Value::Int(4)), Some((1.0, 1024.0)), "The number of squares per unit."),
+        ]
+    }
+
+    fn read(&mut self, engine: &ScriptEngine) {
+        read_color(engine, "color", &mut self.color);
+        read_int(engine, "cells", &mut self.cells);
+    }
+
+    fn pattern_at(&self, uv: &UV, _ctx: &Context) -> (F, GF4) {
+        let p = uv.world * self.cells as F;
+        let m = if (p.x.floor() + p.y.floor()).rem_euclid(2.0) == 0.0 { 1.0 } else { 0.0 };
+        (m, self.color)
+    }
+}
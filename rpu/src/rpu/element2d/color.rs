@@ -20,6 +20,12 @@ impl Element2D for ColorElement<'_> {
         "Color".to_string()
     }
 
+    fn update(&mut self) {
+        if self.engine.execute_block("update".to_string()) {
+            self.read_properties();
+        }
+    }
+
     fn compute_color_at(&self, uv : &UV, color: &mut GF4, _node: usize, _ctx: &Context) {
         *color = self.color;
         self.engine.execute_shader(uv, color);
@@ -36,11 +42,19 @@ impl Script for ColorElement<'_> {
         &self.engine
     }
 
-    fn apply_properties(&mut self, props: Vec<Property>) -> Result<(), RPUError> {
-        let rc = self.engine.apply_properties(props);
-        if let Some(color) = self.engine.get_vector4("color") {
-            self.color = color;
-        }
+    fn schema(&self) -> Schema {
+        Schema::new("Color", vec![
+            PropertyDef::new("color", PropertyType::F4, Some(Value::F4(GF4::new(0.0, 0.0, 0.0, 1.0))), None, "The color of the layer."),
+        ], vec![
+            FunctionSignature::new("shader", "uv, size", "Returns the F4 color of the layer at the given uv."),
+            FunctionSignature::new("update", "", "Called before every frame, can modify the layer properties."),
+        ])
+    }
+
+    fn apply_properties(&mut self, props: Vec<Property>) -> Result<(), Vec<RPUError>> {
+        let schema = self.schema();
+        let rc = self.engine.apply_properties(props, &schema);
+        self.read_properties();
         rc
     }
 
@@ -48,7 +62,28 @@ impl Script for ColorElement<'_> {
         self.engine.execute(code);
     }
 
+    fn call(&mut self, event: &str, args: &[Value]) -> Result<bool, RPUError> {
+        let rc = self.engine.execute_event(event, args);
+        self.read_properties();
+        rc
+    }
+
+    fn set_property(&mut self, name: &str, value: Value) {
+        self.engine.set_value(name, value);
+        self.read_properties();
+    }
+
     fn set_code_block(&mut self, name: String, code: String) {
         _ = self.engine.set_code_block(name, code);
     }
 }
+
+impl ColorElement<'_> {
+
+    /// Copies the color out of the scope, compute_color_at() is called for every pixel.
+    fn read_properties(&mut self) {
+        if let Some(color) = self.engine.get_vector4("color") {
+            self.color = color;
+        }
+    }
+}
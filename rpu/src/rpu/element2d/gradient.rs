@@ -0,0 +1,50 @@
+use crate::prelude::*;
+use super::pattern::{kind_index, read_color, Pattern, PatternLayer};
+
+/// A linear or radial gradient across the texture, from the layers below to the color.
+pub type Gradient<'a> = PatternLayer<'a, GradientPattern>;
+
+pub struct GradientPattern {
+    color                   : GF4,
+    radial                  : bool,
+    direction               : GF2,
+}
+
+impl Pattern for GradientPattern {
+    const NAME: &'static str = "Gradient";
+    const KINDS: &'static [&'static str] = &["linear", "radial"];
+
+    fn new() -> Self {
+        Self {
+            color           : GF4::new(0.0, 0.0, 0.0, 1.0),
+            radial          : false,
+            direction       : GF2::new(0.0, 1.0),
+        }
+    }
+
+    fn properties() -> Vec<PropertyDef> {
+        vec![
+            PropertyDef::new("color", PropertyType::F4, Some(Value::F4(GF4::new(0.0, 0.0, 0.0, 1.0))), None, "The color at the end of the gradient."),
+            PropertyDef::new("kind", PropertyType::String, Some(Value::String("linear".to_string())), None, "The kind of the gradient, \"linear\" or \"radial\" from the center.").with_values(Self::KINDS),
+            PropertyDef::new("angle", PropertyType::F, Some(Value::F(90.0)), Some((-360.0, 360.0)), "The direction of a linear gradient in degrees, 0 is from left to right, 90 from bottom to top."),
+        ]
+    }
+
+    fn read(&mut self, engine: &ScriptEngine) {
+        read_color(engine, "color", &mut self.color);
+        self.radial = kind_index(engine, "kind", Self::KINDS) == 1;
+        if let Some(angle) = engine.get_float("angle") {
+            self.direction = GF2::new(angle.to_radians().cos(), angle.to_radians().sin());
+        }
+    }
+
+    fn pattern_at(&self, uv: &UV, _ctx: &Context) -> (F, GF4) {
+        let p = uv.p;
+        let m = if self.radial {
+            p.norm() * 2.0
+        } else {
+            p.dot(&self.direction) / (self.direction.x.abs() + self.direction.y.abs()) + 0.5
+        };
+        (m.clamp(0.0, 1.0), self.color)
+    }
+}
@@ -0,0 +1,69 @@
+use crate::prelude::*;
+use super::pattern::{bevel, cell_random, read_color, read_float, read_int, read_seed, seed_property, shade, Pattern, PatternLayer};
+
+/// Hexagonal tiles in the color with beveled edges, the gaps between them show the layers below. To tile the
+/// hexagons are slightly stretched so that a whole number of rows fits into a unit.
+pub type Hexagons<'a> = PatternLayer<'a, HexagonsPattern>;
+
+pub struct HexagonsPattern {
+    color                   : GF4,
+    cells                   : I,
+    gap                     : F,
+    bevel                   : F,
+    variation               : F,
+    seed                    : u32,
+}
+
+impl Pattern for HexagonsPattern {
+    const NAME: &'static str = "Hexagons";
+
+    fn new() -> Self {
+        Self {
+            color           : GF4::new(1.0, 1.0, 1.0, 1.0),
+            cells           : 4,
+            gap             : 0.05,
+            bevel           : 0.1,
+            variation       : 0.0,
+            seed            : 0,
+        }
+    }
+
+    fn properties() -> Vec<PropertyDef> {
+        vec![
+            PropertyDef::new("color", PropertyType::F4, Some(Value::F4(GF4::new(1.0, 1.0, 1.0, 1.0))), None, "The color of the hexagons."),
+            PropertyDef::new("cells", PropertyType::Int, Some(Value::Int(4)), Some((1.0, 1024.0)), "The number of hexagons per unit in a row."),
+            PropertyDef::new("gap", PropertyType::F, Some(Value::F(0.05)), Some((0.0, 0.5)), "The width of the gap around a hexagon, relative to its size."),
+            PropertyDef::new("bevel", PropertyType::F, Some(Value::F(0.1)), Some((0.0, 0.5)), "The width of the bevel of a hexagon, relative to its size."),
+            PropertyDef::new("variation", PropertyType::F, Some(Value::F(0.0)), Some((0.0, 1.0)), "How much the brightness of the hexagons varies."),
+            seed_property(),
+        ]
+    }
+
+    fn read(&mut self, engine: &ScriptEngine) {
+        read_color(engine, "color", &mut self.color);
+        read_int(engine, "cells", &mut self.cells);
+        read_float(engine, "gap", &mut self.gap);
+        read_float(engine, "bevel", &mut self.bevel);
+        read_float(engine, "variation", &mut self.variation);
+        read_seed(engine, &mut self.seed);
+    }
+
+    fn pattern_at(&self, uv: &UV, _ctx: &Context) -> (F, GF4) {
+        let r = GF2::new(1.0, 3.0_f64.sqrt());
+        let h = r * 0.5;
+        let rows = ((self.cells as F / r.y).round() as I).max(1);
+        let p = GF2::new(uv.world.x * self.cells as F, uv.world.y * rows as F * r.y);
+
+        // The closest center of the two interleaved grids of hexagons
+        let a = GF2::new(p.x.rem_euclid(r.x), p.y.rem_euclid(r.y)) - h;
+        let b = GF2::new((p.x - h.x).rem_euclid(r.x), (p.y - h.y).rem_euclid(r.y)) - h;
+        let local = if a.norm_squared() < b.norm_squared() { a } else { b };
+        let center = p - local;
+
+        let q = glm::abs(&local);
+        let m = bevel(0.5 - self.gap - (q.x * 0.5 + q.y * h.y).max(q.x), self.bevel);
+        let cell = [(center.x * 2.0).round() as I, (center.y / h.y).round() as I];
+        let random = cell_random(self.seed, cell, [self.cells * 2, rows * 2], 0);
+        (m, shade(self.color, 1.0 - self.variation * random))
+    }
+}
@@ -0,0 +1,132 @@
+use crate::prelude::*;
+
+/// A layer defined in the source by a Layer block. The parameters of the definition are properties of the
+/// layer, their values are defined in front of the body of the color(uv, input) function.
+pub struct UserLayer<'a> {
+    engine                  : ScriptEngine<'a>,
+    name                    : String,
+    parameters              : Vec<PropertyDef>,
+    /// The constants and the color() function of the definition, compiled again when a parameter changes.
+    definition              : Vec<Property>,
+}
+
+impl Element2D for UserLayer<'_> {
+    fn new() -> Self {
+
+        let engine = ScriptEngine::new();
+
+        Self {
+            engine,
+            name            : "Layer".to_string(),
+            parameters      : vec![],
+            definition      : vec![],
+        }
+    }
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn compute_color_at(&self, uv : &UV, color: &mut GF4, _node: usize, _ctx: &Context) {
+        self.engine.execute_layer(uv, color);
+    }
+}
+
+impl UserLayer<'_> {
+
+    /// Sets the name and the parameters of the definition, before the properties are applied.
+    pub fn set_definition(&mut self, name: String, parameters: Vec<PropertyDef>) {
+        self.name = name;
+        self.parameters = parameters;
+        self.schema().apply_defaults(&mut self.engine);
+    }
+
+    /// The schema of the Layer block defining a layer, the other properties of the block are the parameters.
+    pub fn definition_schema() -> Schema {
+        Schema::new("Layer", vec![
+            PropertyDef::new("name", PropertyType::String, None, None, "The name of the layer, textures use it like the built in layers, i.e. Bands."),
+        ], vec![color_signature()])
+    }
+}
+
+impl Script for UserLayer<'_> {
+
+    fn get_scope<'a>(&mut self) -> &'a Scope {
+        self.engine.get_scope()
+    }
+
+    fn get_engine<'a>(&self) -> &'a ScriptEngine {
+        &self.engine
+    }
+
+    fn schema(&self) -> Schema {
+        Schema::new(&self.name, self.parameters.clone(), vec![color_signature()])
+    }
+
+    fn apply_properties(&mut self, props: Vec<Property>) -> Result<(), Vec<RPUError>> {
+        let schema = self.schema();
+
+        // The function is compiled with the final values of the parameters
+        let mut definition = vec![];
+        let mut rest = vec![];
+        for p in props {
+            match p {
+                Property::Function(..) => definition.push(p),
+                Property::Constant(..) => {
+                    definition.push(p.clone());
+                    rest.push(p);
+                },
+                _ => rest.push(p),
+            }
+        }
+
+        // Setting a single parameter later compiles the function of the previous definition again
+        if definition.iter().any(|p| matches!(p, Property::Function(..))) {
+            self.definition = definition;
+        }
+
+        let mut errors = self.engine.apply_properties(rest, &schema).err().unwrap_or_default();
+
+        let mut constants : Vec<Property> = self.definition.iter().filter(|p| matches!(p, Property::Constant(..))).cloned().collect();
+        for p in &self.parameters {
+            if let Some(value) = self.engine.get_value(&p.name) {
+                constants.push(Property::Constant(p.name.clone(), value));
+            }
+        }
+        constants.extend(self.definition.iter().filter(|p| matches!(p, Property::Function(..))).cloned());
+        if let Err(function_errors) = self.engine.apply_properties(constants, &schema) {
+            errors.extend(function_errors);
+        }
+
+        // The code is part of the definition, not of this layer
+        self.engine.clear_functions();
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn execute(&mut self, code: String) {
+        self.engine.execute(code);
+    }
+
+    fn call(&mut self, event: &str, args: &[Value]) -> Result<bool, RPUError> {
+        self.engine.execute_event(event, args)
+    }
+
+    fn set_property(&mut self, name: &str, value: Value) {
+        self.engine.set_value(name, value);
+        // The function of the definition is compiled again with the new value of the parameter
+        _ = self.apply_properties(vec![]);
+    }
+
+    fn set_code_block(&mut self, name: String, code: String) {
+        _ = self.engine.set_code_block(name, code);
+    }
+}
+
+fn color_signature() -> FunctionSignature {
+    FunctionSignature::new("color", "uv, input", "Returns the F4 color of the layer at the given world uv, input is the color of the layers below.")
+}
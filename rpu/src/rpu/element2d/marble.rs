@@ -0,0 +1,53 @@
+use crate::prelude::*;
+use super::pattern::{periodic_fbm, read_color, read_float, read_int, read_seed, seed_property, Pattern, PatternLayer};
+
+use std::f64::consts::{PI, TAU};
+
+/// Veins of marble in the color on top of the layers below, turbulent diagonal bands of periodic noise.
+pub type Marble<'a> = PatternLayer<'a, MarblePattern>;
+
+pub struct MarblePattern {
+    color                   : GF4,
+    frequency               : I,
+    turbulence              : F,
+    sharpness               : F,
+    seed                    : u32,
+}
+
+impl Pattern for MarblePattern {
+    const NAME: &'static str = "Marble";
+
+    fn new() -> Self {
+        Self {
+            color           : GF4::new(0.3, 0.3, 0.3, 1.0),
+            frequency       : 2,
+            turbulence      : 2.0,
+            sharpness       : 6.0,
+            seed            : 0,
+        }
+    }
+
+    fn properties() -> Vec<PropertyDef> {
+        vec![
+            PropertyDef::new("color", PropertyType::F4, Some(Value::F4(GF4::new(0.3, 0.3, 0.3, 1.0))), None, "The color of the veins."),
+            PropertyDef::new("frequency", PropertyType::Int, Some(Value::Int(2)), Some((1.0, 1024.0)), "The number of veins per unit."),
+            PropertyDef::new("turbulence", PropertyType::F, Some(Value::F(2.0)), Some((0.0, 100.0)), "How much the veins are distorted."),
+            PropertyDef::new("sharpness", PropertyType::F, Some(Value::F(6.0)), Some((1.0, 100.0)), "The higher the sharper and thinner the veins."),
+            seed_property(),
+        ]
+    }
+
+    fn read(&mut self, engine: &ScriptEngine) {
+        read_color(engine, "color", &mut self.color);
+        read_int(engine, "frequency", &mut self.frequency);
+        read_float(engine, "turbulence", &mut self.turbulence);
+        read_float(engine, "sharpness", &mut self.sharpness);
+        read_seed(engine, &mut self.seed);
+    }
+
+    fn pattern_at(&self, uv: &UV, _ctx: &Context) -> (F, GF4) {
+        let noise = periodic_fbm(self.seed, uv.world, 4, 4);
+        let phase = TAU * (self.frequency as F * (uv.world.x + uv.world.y)) + self.turbulence * noise * PI;
+        ((1.0 - phase.sin().abs()).powf(self.sharpness), self.color)
+    }
+}
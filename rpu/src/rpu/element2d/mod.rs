@@ -3,6 +3,17 @@ pub mod vertical;
 pub mod color;
 pub mod noise;
 pub mod bricks;
+pub mod checker;
+pub mod gradient;
+pub mod voronoi;
+pub mod tiles;
+pub mod hexagons;
+pub mod stones;
+pub mod planks;
+pub mod marble;
+pub mod stripes;
+pub mod pattern;
+pub mod layer;
 pub mod sprite;
 
 use crate::prelude::*;
@@ -12,6 +23,7 @@ pub trait Element2D : Sync + Send + Script {
     fn new() -> Self where Self: Sized;
 
     fn name(&self) -> String {"".to_string()}
+    fn update(&mut self) {}
     fn render(&mut self, node: usize, ctx: &Context) {}
 
     fn get_color_at(&self, uv: &UV, node: usize, ctx: &Context) -> GF4 { GF4::new(0.0, 0.0, 0.0, 1.0) }
@@ -22,6 +34,7 @@ pub trait Element2D : Sync + Send + Script {
 
     fn get_position(&self) -> Option<GF3> { None }
     fn get_texture(&self) -> Option<usize> { None }
+    fn set_texture(&mut self, texture: Option<usize>) {}
 }
 
 pub struct UV {
@@ -1,9 +1,13 @@
 use crate::prelude::*;
 
+use ::noise::{NoiseFn, MultiFractal, Fbm};
+
 pub struct Noise<'a> {
     engine                  : ScriptEngine<'a>,
     color                   : GF4,
     scale                   : GF2,
+    /// Perlin noise, see procedural::register() for why it is a single octave of Fbm.
+    perlin                  : Fbm,
 }
 
 impl Element2D for Noise<'_> {
@@ -15,6 +19,7 @@ impl Element2D for Noise<'_> {
             engine,
             color           : Vector4::new(1.0, 1.0, 1.0, 1.0),
             scale           : GF2::new(1.0, 1.0),
+            perlin          : Fbm::new().set_octaves(1),
         }
     }
 
@@ -22,16 +27,19 @@ impl Element2D for Noise<'_> {
         "Noise".to_string()
     }
 
-    fn compute_color_at(&self, uv : &UV, color: &mut GF4, _node: usize, ctx: &Context) {
-        use noise::{NoiseFn, Perlin};
+    fn update(&mut self) {
+        if self.engine.execute_block("update".to_string()) {
+            self.read_properties();
+        }
+    }
 
+    fn compute_color_at(&self, uv : &UV, color: &mut GF4, _node: usize, ctx: &Context) {
         let mut uv_local = uv.world + GF2::new(10000.0, 10000.0);
         let rr = ctx.size[0] as F / ctx.size[1] as F;
         uv_local.x *= rr;
         uv_local.y *= rr;
 
-        let value = Perlin::new();
-        let v = value.get([uv_local.x * 20.0 / self.scale.x, uv_local.y * 20.0 / self.scale.y]);
+        let v = self.perlin.get([uv_local.x * 20.0 / self.scale.x, uv_local.y * 20.0 / self.scale.y]);
 
         //println!("{}", v);
         *color = glm::mix(&color, &self.color, (self.color.w * (v / 2.0 + 0.5)).clamp(0.0, 1.0));
@@ -52,14 +60,20 @@ impl Script for Noise<'_> {
         &self.engine
     }
 
-    fn apply_properties(&mut self, props: Vec<Property>) -> Result<(), RPUError> {
-        let rc = self.engine.apply_properties(props);
-        if let Some(color) = self.engine.get_vector4("color") {
-            self.color = color;
-        }
-        if let Some(scale) = self.engine.get_vector2("scale") {
-            self.scale = scale;
-        }
+    fn schema(&self) -> Schema {
+        Schema::new("Noise", vec![
+            PropertyDef::new("color", PropertyType::F4, Some(Value::F4(GF4::new(1.0, 1.0, 1.0, 1.0))), None, "The color of the noise, blended on top of the previous layers."),
+            PropertyDef::new("scale", PropertyType::F2, Some(Value::F2(GF2::new(1.0, 1.0))), None, "The scale of the noise."),
+        ], vec![
+            FunctionSignature::new("shader", "uv, size", "Returns the F4 color of the layer at the given uv."),
+            FunctionSignature::new("update", "", "Called before every frame, can modify the layer properties."),
+        ])
+    }
+
+    fn apply_properties(&mut self, props: Vec<Property>) -> Result<(), Vec<RPUError>> {
+        let schema = self.schema();
+        let rc = self.engine.apply_properties(props, &schema);
+        self.read_properties();
         rc
     }
 
@@ -67,7 +81,31 @@ impl Script for Noise<'_> {
         self.engine.execute(code);
     }
 
+    fn call(&mut self, event: &str, args: &[Value]) -> Result<bool, RPUError> {
+        let rc = self.engine.execute_event(event, args);
+        self.read_properties();
+        rc
+    }
+
+    fn set_property(&mut self, name: &str, value: Value) {
+        self.engine.set_value(name, value);
+        self.read_properties();
+    }
+
     fn set_code_block(&mut self, name: String, code: String) {
         _ = self.engine.set_code_block(name, code);
     }
 }
+
+impl Noise<'_> {
+
+    /// Copies the color and the scale out of the scope, compute_color_at() is called for every pixel.
+    fn read_properties(&mut self) {
+        if let Some(color) = self.engine.get_vector4("color") {
+            self.color = color;
+        }
+        if let Some(scale) = self.engine.get_vector2("scale") {
+            self.scale = scale;
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use crate::prelude::*;
+use crate::rpu::script::procedural::{hash, unit};
+
+/// A procedural pattern drawn by a PatternLayer. The parameters of the pattern are the typed properties of
+/// its layer, they are copied out of the scope once per frame and not for every pixel.
+pub trait Pattern : Sync + Send {
+    /// The name of the layer, i.e. "Checker".
+    const NAME: &'static str;
+    /// The values of the String property "kind" which selects the variant of the pattern, empty if the
+    /// pattern has no variants. The kind property restricts its values to them.
+    const KINDS: &'static [&'static str] = &[];
+
+    fn new() -> Self where Self: Sized;
+
+    /// The properties of the layer, all of them with a default.
+    fn properties() -> Vec<PropertyDef>;
+
+    /// Copies the parameters out of the scope of the layer.
+    fn read(&mut self, engine: &ScriptEngine);
+
+    /// The coverage of the pattern in 0..1 and its color at the uv, called for every pixel.
+    fn pattern_at(&self, uv: &UV, ctx: &Context) -> (F, GF4);
+}
+
+/// A layer which blends a Pattern on top of the layers below, i.e. Checker or Bricks.
+pub struct PatternLayer<'a, P: Pattern> {
+    engine                  : ScriptEngine<'a>,
+    pattern                 : P,
+}
+
+impl<P: Pattern> Element2D for PatternLayer<'_, P> {
+    fn new() -> Self {
+
+        let mut layer = Self {
+            engine          : ScriptEngine::new(),
+            pattern         : P::new(),
+        };
+
+        layer.schema().apply_defaults(&mut layer.engine);
+        layer.pattern.read(&layer.engine);
+        layer
+    }
+
+    fn name(&self) -> String {
+        P::NAME.to_string()
+    }
+
+    fn update(&mut self) {
+        if self.engine.execute_block("update".to_string()) {
+            self.pattern.read(&self.engine);
+        }
+    }
+
+    fn compute_color_at(&self, uv : &UV, color: &mut GF4, _node: usize, ctx: &Context) {
+        let (m, c) = self.pattern.pattern_at(uv, ctx);
+        *color = glm::mix(color, &c, (c.w * m).clamp(0.0, 1.0));
+        self.engine.execute_shader(uv, color);
+    }
+}
+
+impl<P: Pattern> Script for PatternLayer<'_, P> {
+
+    fn get_scope<'a>(&mut self) -> &'a Scope {
+        self.engine.get_scope()
+    }
+
+    fn get_engine<'a>(&self) -> &'a ScriptEngine {
+        &self.engine
+    }
+
+    fn schema(&self) -> Schema {
+        Schema::new(P::NAME, P::properties(), vec![
+            FunctionSignature::new("shader", "uv, size", "Returns the F4 color of the layer at the given uv."),
+            FunctionSignature::new("update", "", "Called before every frame, can modify the layer properties."),
+        ])
+    }
+
+    fn apply_properties(&mut self, props: Vec<Property>) -> Result<(), Vec<RPUError>> {
+        let schema = self.schema();
+        let rc = self.engine.apply_properties(props, &schema);
+        self.pattern.read(&self.engine);
+        rc
+    }
+
+    fn execute(&mut self, code: String) {
+        self.engine.execute(code);
+    }
+
+    fn call(&mut self, event: &str, args: &[Value]) -> Result<bool, RPUError> {
+        let rc = self.engine.execute_event(event, args);
+        self.pattern.read(&self.engine);
+        rc
+    }
+
+    fn set_property(&mut self, name: &str, value: Value) {
+        self.engine.set_value(name, value);
+        self.pattern.read(&self.engine);
+    }
+
+    fn set_code_block(&mut self, name: String, code: String) {
+        _ = self.engine.set_code_block(name, code);
+    }
+}
+
+// The helpers of the patterns. Most patterns repeat after one unit of the world uv, so textures tile on the
+// faces of voxels. Random values are hashed from the cells wrapped at the number of cells per unit.
+
+/// The seed property shared by the patterns with random cells.
+pub fn seed_property() -> PropertyDef {
+    PropertyDef::new("seed", PropertyType::Int, Some(Value::Int(0)), Some((0.0, I::MAX as F)), "The seed of the random values.")
+}
+
+/// Copies the color property into the value, keeps the value if the property is not set.
+pub fn read_color(engine: &ScriptEngine, name: &str, value: &mut GF4) {
+    if let Some(color) = engine.get_vector4(name) {
+        *value = color;
+    }
+}
+
+/// Copies the Int property into the value, keeps the value if the property is not set.
+pub fn read_int(engine: &ScriptEngine, name: &str, value: &mut I) {
+    if let Some(int) = engine.get_int(name) {
+        *value = int;
+    }
+}
+
+/// Copies the F property into the value, keeps the value if the property is not set.
+pub fn read_float(engine: &ScriptEngine, name: &str, value: &mut F) {
+    if let Some(float) = engine.get_float(name) {
+        *value = float;
+    }
+}
+
+/// Copies the seed property into the value.
+pub fn read_seed(engine: &ScriptEngine, value: &mut u32) {
+    if let Some(seed) = engine.get_int("seed") {
+        *value = seed as u32;
+    }
+}
+
+/// Scales the rgb of the color by the factor, the alpha is kept.
+pub fn shade(color: GF4, factor: F) -> GF4 {
+    GF4::new(color.x * factor, color.y * factor, color.z * factor, color.w)
+}
+
+/// A random value in 0..1 for the cell, the cell is wrapped at the period.
+pub fn cell_random(seed: u32, cell: [I; 2], period: [I; 2], k: I) -> F {
+    unit(hash(seed, &[cell[0].rem_euclid(period[0].max(1)), cell[1].rem_euclid(period[1].max(1)), k]))
+}
+
+/// The cells of a jittered grid with the given number of cells per unit. Returns the distance to the closest
+/// point, the distance to the border of its cell and the random value of the cell, distances in cells.
+pub fn voronoi(p: GF2, cells: I, jitter: F, seed: u32) -> (F, F, F) {
+    let p = p * cells as F;
+    let cell = p.map(|x| x.floor());
+    let period = [cells, cells];
+
+    let point = |x: I, y: I| {
+        let c = [cell.x as I + x, cell.y as I + y];
+        GF2::new(c[0] as F + 0.5 + jitter * (cell_random(seed, c, period, 0) - 0.5), c[1] as F + 0.5 + jitter * (cell_random(seed, c, period, 1) - 0.5)) - p
+    };
+
+    let (mut closest, mut distance, mut id) = (GF2::new(0.0, 0.0), F::MAX, 0.0);
+    for y in -1..=1 {
+        for x in -1..=1 {
+            let r = point(x, y);
+            if r.norm_squared() < distance {
+                distance = r.norm_squared();
+                closest = r;
+                id = cell_random(seed, [cell.x as I + x, cell.y as I + y], period, 2);
+            }
+        }
+    }
+
+    // The distance to the border is measured against the neighbours of the closest point (iq)
+    let mut border = F::MAX;
+    for y in -2..=2 {
+        for x in -2..=2 {
+            let r = point(x, y);
+            if (closest - r).norm_squared() > 0.00001 {
+                border = border.min((0.5 * (closest + r)).dot(&(r - closest).normalize()));
+            }
+        }
+    }
+
+    (distance.sqrt(), border, id)
+}
+
+/// Value noise in -1..1 which repeats after one unit, the lattice has the given number of cells per unit.
+pub fn periodic_noise(seed: u32, p: GF2, frequency: I) -> F {
+    let p = p * frequency as F;
+    let (x, y) = (p.x.floor() as I, p.y.floor() as I);
+    let f = p.map(|x| x.rem_euclid(1.0));
+    let s = f.component_mul(&f).component_mul(&(GF2::new(3.0, 3.0) - 2.0 * f));
+
+    let period = [frequency, frequency];
+    let v = |dx: I, dy: I| cell_random(seed, [x + dx, y + dy], period, 3);
+    let a = v(0, 0) + (v(1, 0) - v(0, 0)) * s.x;
+    let b = v(0, 1) + (v(1, 1) - v(0, 1)) * s.x;
+    (a + (b - a) * s.y) * 2.0 - 1.0
+}
+
+/// Fractal periodic noise, the frequency doubles with every octave.
+pub fn periodic_fbm(seed: u32, p: GF2, frequency: I, octaves: I) -> F {
+    let (mut sum, mut total, mut amplitude, mut frequency) = (0.0, 0.0, 1.0, frequency);
+    for _ in 0..octaves.max(1) {
+        sum += periodic_noise(seed, p, frequency) * amplitude;
+        total += amplitude;
+        amplitude *= 0.5;
+        frequency *= 2;
+    }
+    sum / total
+}
+
+/// The bevel of a pattern, 0 at the border of a shape, 1 at the given distance inside of it.
+pub fn bevel(distance: F, width: F) -> F {
+    if width > 0.0 {
+        (distance / width).clamp(0.0, 1.0)
+    } else
+    if distance > 0.0 { 1.0 } else { 0.0 }
+}
+
+/// The index of the kind selected by the String property, 0 if it is not set.
+pub fn kind_index(engine: &ScriptEngine, name: &str, kinds: &[&str]) -> usize {
+    engine.get_string(name).and_then(|value| kinds.iter().position(|k| *k == value)).unwrap_or(0)
+}
@@ -0,0 +1,74 @@
+use crate::prelude::*;
+use super::pattern::{cell_random, read_color, read_float, read_int, read_seed, seed_property, shade, Pattern, PatternLayer};
+
+use std::f64::consts::TAU;
+
+/// Rows of wooden planks in the color with a wood grain, the gaps between them show the layers below. The
+/// seams of the rows are offset randomly.
+pub type Planks<'a> = PatternLayer<'a, PlanksPattern>;
+
+pub struct PlanksPattern {
+    color                   : GF4,
+    planks                  : I,
+    boards                  : I,
+    gap                     : F,
+    grain                   : F,
+    variation               : F,
+    seed                    : u32,
+}
+
+impl Pattern for PlanksPattern {
+    const NAME: &'static str = "Planks";
+
+    fn new() -> Self {
+        Self {
+            color           : GF4::new(0.54, 0.35, 0.17, 1.0),
+            planks          : 4,
+            boards          : 1,
+            gap             : 0.05,
+            grain           : 0.3,
+            variation       : 0.2,
+            seed            : 0,
+        }
+    }
+
+    fn properties() -> Vec<PropertyDef> {
+        vec![
+            PropertyDef::new("color", PropertyType::F4, Some(Value::F4(GF4::new(0.54, 0.35, 0.17, 1.0))), None, "The color of the planks."),
+            PropertyDef::new("planks", PropertyType::Int, Some(Value::Int(4)), Some((1.0, 1024.0)), "The number of rows of planks per unit."),
+            PropertyDef::new("boards", PropertyType::Int, Some(Value::Int(1)), Some((1.0, 1024.0)), "The number of boards per unit in a row."),
+            PropertyDef::new("gap", PropertyType::F, Some(Value::F(0.05)), Some((0.0, 0.5)), "The width of the gap between two planks, relative to the width of a plank."),
+            PropertyDef::new("grain", PropertyType::F, Some(Value::F(0.3)), Some((0.0, 1.0)), "The strength of the wood grain."),
+            PropertyDef::new("variation", PropertyType::F, Some(Value::F(0.2)), Some((0.0, 1.0)), "How much the brightness of the boards varies."),
+            seed_property(),
+        ]
+    }
+
+    fn read(&mut self, engine: &ScriptEngine) {
+        read_color(engine, "color", &mut self.color);
+        read_int(engine, "planks", &mut self.planks);
+        read_int(engine, "boards", &mut self.boards);
+        read_float(engine, "gap", &mut self.gap);
+        read_float(engine, "grain", &mut self.grain);
+        read_float(engine, "variation", &mut self.variation);
+        read_seed(engine, &mut self.seed);
+    }
+
+    fn pattern_at(&self, uv: &UV, _ctx: &Context) -> (F, GF4) {
+        let row = (uv.world.y * self.planks as F).floor() as I;
+        let offset = cell_random(self.seed, [row, 0], [self.planks, 1], 0);
+        let u = (uv.world.x + offset) * self.boards as F;
+        let v = (uv.world.y * self.planks as F).rem_euclid(1.0);
+        let board = [u.floor() as I, row];
+        let random = cell_random(self.seed, board, [self.boards, self.planks], 1);
+
+        // The gaps along the rows and the seams between the boards, in the width of a plank
+        let along = u.rem_euclid(1.0);
+        let distance = v.min(1.0 - v).min(along.min(1.0 - along) * self.planks as F / self.boards as F);
+        let m = if distance > self.gap * 0.5 { 1.0 } else { 0.0 };
+
+        let wave = (TAU * (uv.world.x * 2.0 + random)).sin();
+        let grain = 0.5 + 0.5 * (TAU * (v * 6.0 + 0.15 * wave) + random * 10.0).sin();
+        (m, shade(self.color, (1.0 - self.variation * random) * (1.0 - self.grain * grain * 0.5)))
+    }
+}
@@ -23,6 +23,12 @@ impl Element2D for Sprite<'_> {
         "Noise".to_string()
     }
 
+    fn update(&mut self) {
+        if self.engine.execute_block("update".to_string()) {
+            self.read_properties();
+        }
+    }
+
     fn get_position(&self) -> Option<GF3> {
         Some(self.position)
      }
@@ -31,6 +37,10 @@ impl Element2D for Sprite<'_> {
         self.texture
     }
 
+    fn set_texture(&mut self, texture: Option<usize>) {
+        self.texture = texture;
+    }
+
     fn compute_color_at(&self, uv : &UV, color: &mut GF4, _node: usize, _ctx: &Context) {
 
 
@@ -48,11 +58,20 @@ impl Script for Sprite<'_> {
         &self.engine
     }
 
-    fn apply_properties(&mut self, props: Vec<Property>) -> Result<(), RPUError> {
-        let rc = self.engine.apply_properties(props);
-        if let Some(position) = self.engine.get_vector3("position") {
-            self.position = position;
-        }
+    fn schema(&self) -> Schema {
+        Schema::new("Sprite", vec![
+            PropertyDef::new("texture", PropertyType::String, None, None, "The name of the texture of the sprite."),
+            PropertyDef::new("position", PropertyType::F3, Some(Value::F3(GF3::new(0.0, 0.0, 0.0))), None, "The position of the sprite in the layout."),
+        ], vec![
+            FunctionSignature::new("shader", "uv, size", "Returns the F4 color of the sprite at the given uv."),
+            FunctionSignature::new("update", "", "Called before every frame, can modify the sprite properties."),
+        ])
+    }
+
+    fn apply_properties(&mut self, props: Vec<Property>) -> Result<(), Vec<RPUError>> {
+        let schema = self.schema();
+        let rc = self.engine.apply_properties(props, &schema);
+        self.read_properties();
         rc
     }
 
@@ -60,7 +79,28 @@ impl Script for Sprite<'_> {
         self.engine.execute(code);
     }
 
+    fn call(&mut self, event: &str, args: &[Value]) -> Result<bool, RPUError> {
+        let rc = self.engine.execute_event(event, args);
+        self.read_properties();
+        rc
+    }
+
+    fn set_property(&mut self, name: &str, value: Value) {
+        self.engine.set_value(name, value);
+        self.read_properties();
+    }
+
     fn set_code_block(&mut self, name: String, code: String) {
         _ = self.engine.set_code_block(name, code);
     }
-}
\ No newline at end of file
+}
+
+impl Sprite<'_> {
+
+    /// Copies the position out of the scope, the layout places the sprite with it.
+    fn read_properties(&mut self) {
+        if let Some(position) = self.engine.get_vector3("position") {
+            self.position = position;
+        }
+    }
+}
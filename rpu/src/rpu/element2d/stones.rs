@@ -0,0 +1,57 @@
+use crate::prelude::*;
+use super::pattern::{bevel, read_color, read_float, read_int, read_seed, seed_property, shade, voronoi, Pattern, PatternLayer};
+
+/// Cobblestones in the color, rounded towards their edges. The gaps between them show the layers below.
+pub type Stones<'a> = PatternLayer<'a, StonesPattern>;
+
+pub struct StonesPattern {
+    color                   : GF4,
+    cells                   : I,
+    gap                     : F,
+    roundness               : F,
+    variation               : F,
+    seed                    : u32,
+}
+
+impl Pattern for StonesPattern {
+    const NAME: &'static str = "Stones";
+
+    fn new() -> Self {
+        Self {
+            color           : GF4::new(0.6, 0.6, 0.6, 1.0),
+            cells           : 4,
+            gap             : 0.08,
+            roundness       : 0.3,
+            variation       : 0.3,
+            seed            : 0,
+        }
+    }
+
+    fn properties() -> Vec<PropertyDef> {
+        vec![
+            PropertyDef::new("color", PropertyType::F4, Some(Value::F4(GF4::new(0.6, 0.6, 0.6, 1.0))), None, "The color of the stones."),
+            PropertyDef::new("cells", PropertyType::Int, Some(Value::Int(4)), Some((1.0, 1024.0)), "The number of stones per unit."),
+            PropertyDef::new("gap", PropertyType::F, Some(Value::F(0.08)), Some((0.0, 0.5)), "The width of the gap between two stones, relative to the size of a stone."),
+            PropertyDef::new("roundness", PropertyType::F, Some(Value::F(0.3)), Some((0.0, 1.0)), "How far from their edges the stones are shaded round."),
+            PropertyDef::new("variation", PropertyType::F, Some(Value::F(0.3)), Some((0.0, 1.0)), "How much the brightness of the stones varies."),
+            seed_property(),
+        ]
+    }
+
+    fn read(&mut self, engine: &ScriptEngine) {
+        read_color(engine, "color", &mut self.color);
+        read_int(engine, "cells", &mut self.cells);
+        read_float(engine, "gap", &mut self.gap);
+        read_float(engine, "roundness", &mut self.roundness);
+        read_float(engine, "variation", &mut self.variation);
+        read_seed(engine, &mut self.seed);
+    }
+
+    fn pattern_at(&self, uv: &UV, _ctx: &Context) -> (F, GF4) {
+        let (_, border, random) = voronoi(uv.world, self.cells, 0.8, self.seed);
+        let distance = border - self.gap * 0.5;
+        let m = if distance > 0.0 { 1.0 } else { 0.0 };
+        let round = 0.6 + 0.4 * bevel(distance, self.roundness * 0.5);
+        (m, shade(self.color, (1.0 - self.variation * random) * round))
+    }
+}
@@ -0,0 +1,53 @@
+use crate::prelude::*;
+use super::pattern::{kind_index, read_color, read_float, read_int, Pattern, PatternLayer};
+
+/// Horizontal, vertical or diagonal stripes in the color, the gaps show the layers below.
+pub type Stripes<'a> = PatternLayer<'a, StripesPattern>;
+
+pub struct StripesPattern {
+    color                   : GF4,
+    kind                    : usize,
+    stripes                 : I,
+    width                   : F,
+}
+
+impl Pattern for StripesPattern {
+    const NAME: &'static str = "Stripes";
+    const KINDS: &'static [&'static str] = &["horizontal", "vertical", "diagonal"];
+
+    fn new() -> Self {
+        Self {
+            color           : GF4::new(1.0, 1.0, 1.0, 1.0),
+            kind            : 0,
+            stripes         : 4,
+            width           : 0.5,
+        }
+    }
+
+    fn properties() -> Vec<PropertyDef> {
+        vec![
+            PropertyDef::new("color", PropertyType::F4, Some(Value::F4(GF4::new(1.0, 1.0, 1.0, 1.0))), None, "The color of the stripes."),
+            PropertyDef::new("kind", PropertyType::String, Some(Value::String("horizontal".to_string())), None, "The direction of the stripes, \"horizontal\", \"vertical\" or \"diagonal\".").with_values(Self::KINDS),
+            PropertyDef::new("stripes", PropertyType::Int, Some(Value::Int(4)), Some((1.0, 1024.0)), "The number of stripes per unit."),
+            PropertyDef::new("width", PropertyType::F, Some(Value::F(0.5)), Some((0.0, 1.0)), "The width of a stripe, relative to the distance between two stripes."),
+        ]
+    }
+
+    fn read(&mut self, engine: &ScriptEngine) {
+        read_color(engine, "color", &mut self.color);
+        self.kind = kind_index(engine, "kind", Self::KINDS);
+        read_int(engine, "stripes", &mut self.stripes);
+        read_float(engine, "width", &mut self.width);
+    }
+
+    fn pattern_at(&self, uv: &UV, _ctx: &Context) -> (F, GF4) {
+        let p = uv.world;
+        let s = match self.kind {
+            1 => p.x,
+            2 => p.x + p.y,
+            _ => p.y,
+        } * self.stripes as F;
+        let m = if s.rem_euclid(1.0) < self.width { 1.0 } else { 0.0 };
+        (m, self.color)
+    }
+}
@@ -24,10 +24,17 @@ impl Element2D for Texture<'_> {
         "Texture".to_string()
     }
 
+    fn update(&mut self) {
+        if self.engine.execute_block("update".to_string()) {
+            self.read_properties();
+        }
+    }
+
     fn render(&mut self, node_index: usize, ctx: &Context) {
         let static_size = self.engine.get_vector2("size");
 
-        if let Some(static_size) = static_size {
+        // Textures with image data show the image
+        if let (Some(static_size), None) = (static_size, self.engine.get_string("data")) {
             let width = static_size.x as usize;
             let height = static_size.y as usize;
             let mut color = ColorBuffer::new(width, height, 1.0);
@@ -114,9 +121,22 @@ impl Script for Texture<'_> {
         &self.engine
     }
 
-    fn apply_properties(&mut self, props: Vec<Property>) -> Result<(), RPUError> {
-        let rc = self.engine.apply_properties(props);
-        self.pixelate = self.engine.get_float("pixelate");
+    fn schema(&self) -> Schema {
+        Schema::new("Texture", vec![
+            PropertyDef::new("name", PropertyType::String, None, None, "The name of the texture, objects and sprites reference it via texture = \"Name\"."),
+            PropertyDef::new("pixelate", PropertyType::F, None, Some((0.0, 99.0)), "Pixelates the texture, the higher the value the bigger the pixels."),
+            PropertyDef::new("data", PropertyType::String, None, None, "Base64 encoded PNG image data."),
+            PropertyDef::new("size", PropertyType::F2, None, None, "The size in pixels of the baked texture."),
+        ], vec![
+            FunctionSignature::new("shader", "uv, size", "Returns the F4 color of the texture at the given uv."),
+            FunctionSignature::new("update", "", "Called before every frame, can modify the texture properties."),
+        ])
+    }
+
+    fn apply_properties(&mut self, props: Vec<Property>) -> Result<(), Vec<RPUError>> {
+        let schema = self.schema();
+        let rc = self.engine.apply_properties(props, &schema);
+        self.read_properties();
         if let Some(string_data) = self.engine.get_string("data") {
             if let Some(png_data) = base64::decode(string_data).ok() {
                 let data = std::io::Cursor::new(png_data);
@@ -154,7 +174,27 @@ impl Script for Texture<'_> {
         self.engine.execute(code);
     }
 
+    fn call(&mut self, event: &str, args: &[Value]) -> Result<bool, RPUError> {
+        let rc = self.engine.execute_event(event, args);
+        self.read_properties();
+        rc
+    }
+
+    fn set_property(&mut self, name: &str, value: Value) {
+        self.engine.set_value(name, value);
+        // The image of the data property is decoded again
+        _ = self.apply_properties(vec![]);
+    }
+
     fn set_code_block(&mut self, name: String, code: String) {
         _ = self.engine.set_code_block(name, code);
     }
 }
+
+impl Texture<'_> {
+
+    /// Copies the pixelation out of the scope, compute_color_at() is called for every pixel.
+    fn read_properties(&mut self) {
+        self.pixelate = self.engine.get_float("pixelate");
+    }
+}
@@ -0,0 +1,58 @@
+use crate::prelude::*;
+use super::pattern::{bevel, cell_random, read_color, read_float, read_int, read_seed, seed_property, shade, Pattern, PatternLayer};
+
+/// Square tiles in the color with beveled edges, the gaps between them show the layers below.
+pub type Tiles<'a> = PatternLayer<'a, TilesPattern>;
+
+pub struct TilesPattern {
+    color                   : GF4,
+    cells                   : I,
+    gap                     : F,
+    bevel                   : F,
+    variation               : F,
+    seed                    : u32,
+}
+
+impl Pattern for TilesPattern {
+    const NAME: &'static str = "Tiles";
+
+    fn new() -> Self {
+        Self {
+            color           : GF4::new(1.0, 1.0, 1.0, 1.0),
+            cells           : 4,
+            gap             : 0.05,
+            bevel           : 0.1,
+            variation       : 0.0,
+            seed            : 0,
+        }
+    }
+
+    fn properties() -> Vec<PropertyDef> {
+        vec![
+            PropertyDef::new("color", PropertyType::F4, Some(Value::F4(GF4::new(1.0, 1.0, 1.0, 1.0))), None, "The color of the tiles."),
+            PropertyDef::new("cells", PropertyType::Int, Some(Value::Int(4)), Some((1.0, 1024.0)), "The number of tiles per unit."),
+            PropertyDef::new("gap", PropertyType::F, Some(Value::F(0.05)), Some((0.0, 0.5)), "The width of the gap around a tile, relative to the size of the tile."),
+            PropertyDef::new("bevel", PropertyType::F, Some(Value::F(0.1)), Some((0.0, 0.5)), "The width of the bevel of a tile, relative to the size of the tile."),
+            PropertyDef::new("variation", PropertyType::F, Some(Value::F(0.0)), Some((0.0, 1.0)), "How much the brightness of the tiles varies."),
+            seed_property(),
+        ]
+    }
+
+    fn read(&mut self, engine: &ScriptEngine) {
+        read_color(engine, "color", &mut self.color);
+        read_int(engine, "cells", &mut self.cells);
+        read_float(engine, "gap", &mut self.gap);
+        read_float(engine, "bevel", &mut self.bevel);
+        read_float(engine, "variation", &mut self.variation);
+        read_seed(engine, &mut self.seed);
+    }
+
+    fn pattern_at(&self, uv: &UV, _ctx: &Context) -> (F, GF4) {
+        let p = uv.world * self.cells as F;
+        let t = p.map(|x| x.rem_euclid(1.0)) - GF2::new(0.5, 0.5);
+        let a = GF2::new(0.5 - self.gap, 0.5 - self.gap) - glm::abs(&t);
+        let m = bevel(a.x.min(a.y), self.bevel);
+        let random = cell_random(self.seed, [p.x.floor() as I, p.y.floor() as I], [self.cells, self.cells], 0);
+        (m, shade(self.color, 1.0 - self.variation * random))
+    }
+}
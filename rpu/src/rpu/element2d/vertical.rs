@@ -19,6 +19,10 @@ impl Element2D for Vertical<'_> {
         "Vertical".to_string()
     }
 
+    fn update(&mut self) {
+        self.engine.execute_block("update".to_string());
+    }
+
     fn compute_color_at(&self, uv: &UV, color: &mut GF4, node_index: usize, ctx: &Context) {
         let v_el_size = 1.0 / ctx.nodes[node_index].elements.len() as F;
         let mut y = 0.0;
@@ -51,14 +55,30 @@ impl Script for Vertical<'_> {
         &self.engine
     }
 
-    fn apply_properties(&mut self, props: Vec<Property>) -> Result<(), RPUError> {
-        self.engine.apply_properties(props)
+    fn schema(&self) -> Schema {
+        Schema::new("Vertical", vec![], vec![
+            FunctionSignature::new("shader", "uv, size", "Returns the F4 color of the layout at the given uv."),
+            FunctionSignature::new("update", "", "Called before every frame, can modify the layout properties."),
+        ])
+    }
+
+    fn apply_properties(&mut self, props: Vec<Property>) -> Result<(), Vec<RPUError>> {
+        let schema = self.schema();
+        self.engine.apply_properties(props, &schema)
     }
 
     fn execute(&mut self, code: String) {
         self.engine.execute(code);
     }
 
+    fn call(&mut self, event: &str, args: &[Value]) -> Result<bool, RPUError> {
+        self.engine.execute_event(event, args)
+    }
+
+    fn set_property(&mut self, name: &str, value: Value) {
+        self.engine.set_value(name, value);
+    }
+
     fn set_code_block(&mut self, name: String, code: String) {
         _ = self.engine.set_code_block(name, code);
     }
@@ -0,0 +1,55 @@
+use crate::prelude::*;
+use super::pattern::{read_color, read_float, read_int, read_seed, seed_property, shade, voronoi, Pattern, PatternLayer};
+
+/// Voronoi cells in the color, the lines between the cells show the layers below.
+pub type Voronoi<'a> = PatternLayer<'a, VoronoiPattern>;
+
+pub struct VoronoiPattern {
+    color                   : GF4,
+    cells                   : I,
+    jitter                  : F,
+    edge                    : F,
+    variation               : F,
+    seed                    : u32,
+}
+
+impl Pattern for VoronoiPattern {
+    const NAME: &'static str = "Voronoi";
+
+    fn new() -> Self {
+        Self {
+            color           : GF4::new(1.0, 1.0, 1.0, 1.0),
+            cells           : 6,
+            jitter          : 1.0,
+            edge            : 0.05,
+            variation       : 0.5,
+            seed            : 0,
+        }
+    }
+
+    fn properties() -> Vec<PropertyDef> {
+        vec![
+            PropertyDef::new("color", PropertyType::F4, Some(Value::F4(GF4::new(1.0, 1.0, 1.0, 1.0))), None, "The color of the cells."),
+            PropertyDef::new("cells", PropertyType::Int, Some(Value::Int(6)), Some((1.0, 1024.0)), "The number of cells per unit."),
+            PropertyDef::new("jitter", PropertyType::F, Some(Value::F(1.0)), Some((0.0, 1.0)), "How far the centers of the cells move away from a regular grid."),
+            PropertyDef::new("edge", PropertyType::F, Some(Value::F(0.05)), Some((0.0, 0.5)), "The width of the lines between the cells, relative to the size of a cell."),
+            PropertyDef::new("variation", PropertyType::F, Some(Value::F(0.5)), Some((0.0, 1.0)), "How much the brightness of the cells varies."),
+            seed_property(),
+        ]
+    }
+
+    fn read(&mut self, engine: &ScriptEngine) {
+        read_color(engine, "color", &mut self.color);
+        read_int(engine, "cells", &mut self.cells);
+        read_float(engine, "jitter", &mut self.jitter);
+        read_float(engine, "edge", &mut self.edge);
+        read_float(engine, "variation", &mut self.variation);
+        read_seed(engine, &mut self.seed);
+    }
+
+    fn pattern_at(&self, uv: &UV, _ctx: &Context) -> (F, GF4) {
+        let (_, border, random) = voronoi(uv.world, self.cells, self.jitter, self.seed);
+        let m = if border > self.edge * 0.5 { 1.0 } else { 0.0 };
+        (m, shade(self.color, 1.0 - self.variation * random))
+    }
+}
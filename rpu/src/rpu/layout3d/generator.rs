@@ -0,0 +1,154 @@
+use crate::prelude::*;
+
+use rhai::EvalAltResult;
+use std::sync::{Arc, Mutex};
+
+/// The cells written by a generate() code block, None for carved cells.
+pub type GeneratedCells = HashMap<(i32, i32, i32), Option<String>>;
+
+/// Runs the generate() code block of a layout at compile time. The script places instance symbols or legend
+/// keys with place, fill, line and room and empties cells with carve. Random numbers are derived from the seed
+/// of the layout, the same seed always generates the same map.
+pub struct Generator {
+    engine                  : Engine,
+    cells                   : Arc<Mutex<GeneratedCells>>,
+}
+
+impl Generator {
+
+    pub fn new(seed: I) -> Self {
+        let mut engine = Engine::new();
+        crate::rpu::script::math::register(&mut engine);
+        crate::rpu::script::procedural::register(&mut engine, seed as u32);
+
+        let cells : Arc<Mutex<GeneratedCells>> = Arc::new(Mutex::new(HashMap::new()));
+        let random = Arc::new(Mutex::new(Random::new(seed as u64)));
+
+        let c = cells.clone();
+        engine.register_fn("place", move |x: I, y: I, z: I, symbol: &str| {
+            c.lock().unwrap().insert((x, y, z), Some(symbol.to_string()));
+        });
+
+        let c = cells.clone();
+        engine.register_fn("fill", move |x0: I, y0: I, z0: I, x1: I, y1: I, z1: I, symbol: &str| {
+            let mut cells = c.lock().unwrap();
+            for (x, y, z) in cuboid(x0, y0, z0, x1, y1, z1) {
+                cells.insert((x, y, z), Some(symbol.to_string()));
+            }
+        });
+
+        let c = cells.clone();
+        engine.register_fn("line", move |x0: I, y0: I, z0: I, x1: I, y1: I, z1: I, symbol: &str| {
+            let mut cells = c.lock().unwrap();
+            let steps = (x1 - x0).abs().max((y1 - y0).abs()).max((z1 - z0).abs());
+            for i in 0..=steps {
+                let t = if steps > 0 { i as F / steps as F } else { 0.0 };
+                let x = x0 + ((x1 - x0) as F * t).round() as I;
+                let y = y0 + ((y1 - y0) as F * t).round() as I;
+                let z = z0 + ((z1 - z0) as F * t).round() as I;
+                cells.insert((x, y, z), Some(symbol.to_string()));
+            }
+        });
+
+        let c = cells.clone();
+        engine.register_fn("carve", move |x: I, y: I, z: I| {
+            c.lock().unwrap().insert((x, y, z), None);
+        });
+
+        let c = cells.clone();
+        engine.register_fn("carve", move |x0: I, y0: I, z0: I, x1: I, y1: I, z1: I| {
+            let mut cells = c.lock().unwrap();
+            for (x, y, z) in cuboid(x0, y0, z0, x1, y1, z1) {
+                cells.insert((x, y, z), None);
+            }
+        });
+
+        // Walls around the box on every level, the inside is carved
+        let c = cells.clone();
+        engine.register_fn("room", move |x0: I, y0: I, z0: I, x1: I, y1: I, z1: I, symbol: &str| {
+            let mut cells = c.lock().unwrap();
+            for (x, y, z) in cuboid(x0, y0, z0, x1, y1, z1) {
+                let wall = x == x0.min(x1) || x == x0.max(x1) || z == z0.min(z1) || z == z0.max(z1);
+                cells.insert((x, y, z), if wall { Some(symbol.to_string()) } else { None });
+            }
+        });
+
+        let c = cells.clone();
+        engine.register_fn("get", move |x: I, y: I, z: I| -> String {
+            c.lock().unwrap().get(&(x, y, z)).cloned().flatten().unwrap_or_default()
+        });
+
+        let r = random.clone();
+        engine.register_fn("random", move || -> F {
+            r.lock().unwrap().float()
+        });
+
+        let r = random.clone();
+        engine.register_fn("random", move |min: I, max: I| -> I {
+            r.lock().unwrap().range(min, max)
+        });
+
+        Self {
+            engine,
+            cells,
+        }
+    }
+
+    /// Runs the code, returns the generated cells or the error message of the script.
+    /// Applies the limits of the scripts, the frame budget does not apply at compile time.
+    pub fn set_limits(&mut self, limits: &ScriptLimits) {
+        limits.apply(&mut self.engine);
+    }
+
+    pub fn run(&mut self, code: &str) -> Result<GeneratedCells, Box<EvalAltResult>> {
+        self.engine.run(code)?;
+        Ok(std::mem::take(&mut *self.cells.lock().unwrap()))
+    }
+}
+
+/// All cells of the box between the two corners, inclusive.
+fn cuboid(x0: I, y0: I, z0: I, x1: I, y1: I, z1: I) -> Vec<(I, I, I)> {
+    let mut cells = vec![];
+    for y in y0.min(y1)..=y0.max(y1) {
+        for z in z0.min(z1)..=z0.max(z1) {
+            for x in x0.min(x1)..=x0.max(x1) {
+                cells.push((x, y, z));
+            }
+        }
+    }
+    cells
+}
+
+/// A small deterministic random number generator (SplitMix64).
+pub struct Random {
+    state                   : u64,
+}
+
+impl Random {
+
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state           : seed,
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// A float in the range 0 to 1, exclusive.
+    pub fn float(&mut self) -> F {
+        (self.next_u64() >> 11) as F / (1u64 << 53) as F
+    }
+
+    /// An integer in the range min to max, inclusive.
+    pub fn range(&mut self, min: I, max: I) -> I {
+        let (min, max) = (min.min(max), min.max(max));
+        let span = (max as i64 - min as i64 + 1) as u64;
+        (min as i64 + (self.next_u64() % span) as i64) as I
+    }
+}
@@ -3,25 +3,80 @@ use crate::prelude::*;
 pub struct Grid3D<'a> {
         engine              : ScriptEngine<'a>,
         buffer              : IndexBuffer3D,
+
+        /// The map as set by the compiler.
+        map                 : HashMap<(i32, i32, i32), MapCell>,
+        /// The cells with a rotation or height offset.
+        placements          : HashMap<(i32, i32, i32), Placement>,
+}
+
+/// The modifiers of an object in the map. Objects moved up or down reach into the cell above or below,
+/// which refers to the same placement.
+struct Placement {
+    cell                    : MapCell,
+    /// The map position of the object.
+    origin                  : Vector3<F>,
 }
 
 impl Layout3D for Grid3D<'_> {
 
     fn new() -> Self {
 
-        let engine = ScriptEngine::new();
-
-        Self {
-            engine,
+        let mut grid = Self {
+            engine          : ScriptEngine::new(),
             buffer          : IndexBuffer3D::new(),
-        }
+            map             : HashMap::new(),
+            placements      : HashMap::new(),
+        };
+
+        grid.schema().apply_defaults(&mut grid.engine);
+        grid
+    }
+
+    fn schema(&self) -> Schema {
+        Schema::new("Grid3D", vec![
+            PropertyDef::new("seed", PropertyType::Int, Some(Value::Int(0)), None, "The seed of the random numbers of the generate() code block."),
+        ], vec![
+            FunctionSignature::new("generate", "", "Generates the map at compile time with place, fill, line, room and carve, runs after the map rows are read."),
+        ])
+    }
+
+    fn get_engine<'a>(&self) -> &'a ScriptEngine {
+        &self.engine
+    }
+
+    fn apply_properties(&mut self, props: Vec<Property>) -> Result<(), Vec<RPUError>> {
+        let schema = self.schema();
+        self.engine.apply_properties(props, &schema)
     }
 
-    fn set_map3d(&mut self, map: HashMap<(i32, i32, i32), usize>) {
-        self.buffer.alloc(20, 20, 20);
-        for (i, v) in map {
-            self.buffer.set(i.0 as usize, i.1 as usize, i.2 as usize, v);
+    fn set_map3d(&mut self, map: HashMap<(i32, i32, i32), MapCell>) {
+        // One more level for objects moved up
+        let size = map.keys().fold([1, 2, 1], |size, (x, y, z)| [size[0].max(*x as usize + 1), size[1].max(*y as usize + 2), size[2].max(*z as usize + 1)]);
+        self.buffer.alloc(size[0], size[1], size[2]);
+        self.placements.clear();
+        for (i, cell) in &map {
+            self.buffer.set(i.0 as usize, i.1 as usize, i.2 as usize, cell.node);
+            if cell.is_modified() {
+                self.placements.insert(*i, Placement { cell: *cell, origin: Vector3::new(i.0 as F, i.1 as F, i.2 as F) });
+            }
+        }
+
+        // Empty cells an object is moved into refer to the object
+        for (i, cell) in &map {
+            if cell.height != 0.0 {
+                let y = if cell.height > 0.0 { i.1 + 1 } else { i.1 - 1 };
+                if y >= 0 && (y as usize) < self.buffer.size[1] && !map.contains_key(&(i.0, y, i.2)) {
+                    self.buffer.set(i.0 as usize, y as usize, i.2 as usize, cell.node);
+                    self.placements.insert((i.0, y, i.2), Placement { cell: *cell, origin: Vector3::new(i.0 as F, i.1 as F, i.2 as F) });
+                }
+            }
         }
+        self.map = map;
+    }
+
+    fn get_map3d(&self) -> HashMap<(i32, i32, i32), MapCell> {
+        self.map.clone()
     }
 
     fn traverse3d(&self, ray: &Ray, get_normal: bool, ctx: &Context) -> Option<HitRecord> {
@@ -79,15 +134,24 @@ impl Layout3D for Grid3D<'_> {
                 let dz = mask.z * (side_dist.z - delta_dist.z);
                 let dist = glm::length(&GF3::new(dx, dy, dz));
 
+                let placement = self.placements.get(&(map_pos.x, map_pos.y, map_pos.z));
+
                 match &ctx.nodes[index].object {
 
                     Object::SDF3D(object) => {
-                        let hp = Vector3::new(map_pos.x as F + 0.5, map_pos.y as F + 0.5, map_pos.z as F + 0.5);
+                        let mut hp = Vector3::new(map_pos.x as F + 0.5, map_pos.y as F + 0.5, map_pos.z as F + 0.5);
+                        let mut angle = 0.0;
+                        if let Some(placement) = placement {
+                            hp = placement.origin + Vector3::new(0.5, 0.5 + placement.cell.height, 0.5);
+                            angle = placement.cell.rotation.to_radians();
+                        }
                         let mut t = dist;
                         let t_max = dist + 1.73205;
                         for _i in 0..24 {
                             let p = ro + rd * t;
-                            let d = object.get_distance(&p, &hp);
+                            // Rotating the point the other way around the center rotates the object
+                            let local = hp + rotate_y(&(p - hp), -angle);
+                            let d = object.get_distance(&local, &hp);
                             if d < 0.001 {
 
                                 let uv = get_uv(&p, &mask);
@@ -97,7 +161,7 @@ impl Layout3D for Grid3D<'_> {
                                     node            : index,
                                     hit_point       : p,
                                     mask            : mask,
-                                    normal          : if get_normal { object.get_normal(&p, &hp) } else { Vector3::new(0.0, 0.0, 0.0) },
+                                    normal          : if get_normal { rotate_y(&object.get_normal(&local, &hp), angle) } else { Vector3::new(0.0, 0.0, 0.0) },
                                     uv              : uv.0,
                                     uv_world        : uv.1,
                                 });
@@ -110,6 +174,31 @@ impl Layout3D for Grid3D<'_> {
                     },
                     Object::AnalyticalObject(object) => {
 
+                        if let Some(placement) = placement {
+                            // A moved voxel does not fill the cell, intersect its box
+                            let corner = placement.origin + Vector3::new(0.0, placement.cell.height, 0.0);
+                            if let Some((t, mask)) = intersect_box(ro, rd, &corner) {
+                                let hp = ro + rd * t;
+
+                                // Voxels are turned in steps of 90 degrees, which turns their texture
+                                let angle = (placement.cell.rotation / 90.0).round() * std::f64::consts::FRAC_PI_2;
+                                let center = corner + Vector3::new(0.5, 0.5, 0.5);
+                                let local = center + rotate_y(&(hp - center), -angle) - Vector3::new(0.0, placement.cell.height, 0.0);
+                                let local_mask = glm::abs(&rotate_y(&mask, -angle));
+
+                                let uv = get_uv(&local, &local_mask);
+
+                                return Some( HitRecord {
+                                    distance        : t,
+                                    node            : index,
+                                    hit_point       : hp,
+                                    mask            : mask,
+                                    normal          : Vector3::new(0.0, 0.0, 0.0),
+                                    uv              : uv.0,
+                                    uv_world        : uv.1,
+                                });
+                            }
+                        } else
                         if let Some(_d) = object.get_distance(ray) {
                             let hp = ro + rd * dist;
 
@@ -212,4 +301,37 @@ impl Layout3D for Grid3D<'_> {
     fn set_code_block(&mut self, name: String, code: String) {
         _ = self.engine.set_code_block(name, code);
     }
-}
\ No newline at end of file
+}
+
+/// Rotates the vector around the y axis by the given angle in radians.
+fn rotate_y(v: &Vector3<F>, angle: F) -> Vector3<F> {
+    if angle == 0.0 {
+        return *v;
+    }
+    let (sin, cos) = angle.sin_cos();
+    Vector3::new(v.x * cos + v.z * sin, v.y, -v.x * sin + v.z * cos)
+}
+
+/// Intersects the ray with the unit box at the given corner, returns the distance and the axis of the hit face.
+fn intersect_box(ro: &Vector3<F>, rd: &Vector3<F>, corner: &Vector3<F>) -> Option<(F, GF3)> {
+    let mut t_near = F::MIN;
+    let mut t_far = F::MAX;
+    let mut mask = GF3::new(0.0, 0.0, 0.0);
+
+    for axis in 0..3 {
+        let t0 = (corner[axis] - ro[axis]) / rd[axis];
+        let t1 = (corner[axis] + 1.0 - ro[axis]) / rd[axis];
+        if t0.min(t1) > t_near {
+            t_near = t0.min(t1);
+            mask = GF3::new(0.0, 0.0, 0.0);
+            mask[axis] = 1.0;
+        }
+        t_far = t_far.min(t0.max(t1));
+    }
+
+    if t_near > t_far || t_far < 0.0 {
+        None
+    } else {
+        Some((t_near.max(0.0), mask))
+    }
+}
@@ -1,15 +1,48 @@
 
 //pub mod grid2d;
 pub mod grid3d;
+pub mod generator;
 
 use crate::prelude::*;
 
+/// An instance in a layout map, the node of the object and the modifiers of its cell.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct MapCell {
+    pub node            : usize,
+    /// The rotation around the y axis in degrees.
+    pub rotation        : F,
+    /// The vertical offset in cells.
+    pub height          : F,
+}
+
+impl MapCell {
+
+    pub fn new(node: usize) -> Self {
+        Self {
+            node,
+            rotation    : 0.0,
+            height      : 0.0,
+        }
+    }
+
+    /// True if the cell has a rotation or a height offset.
+    pub fn is_modified(&self) -> bool {
+        self.rotation != 0.0 || self.height != 0.0
+    }
+}
+
 #[allow(unused)]
 pub trait Layout3D : Sync + Send {
     fn new() -> Self where Self: Sized;
 
+    /// The properties and code blocks supported by the layout.
+    fn schema(&self) -> Schema;
+    fn get_engine<'a>(&self) -> &'a ScriptEngine;
+    fn apply_properties(&mut self, props: Vec<Property>) -> Result<(), Vec<RPUError>>;
+
     fn set_map2d(&mut self, map: HashMap<(i32, i32), usize>) {}
-    fn set_map3d(&mut self, map: HashMap<(i32, i32, i32), usize>) {}
+    fn set_map3d(&mut self, map: HashMap<(i32, i32, i32), MapCell>) {}
+    fn get_map3d(&self) -> HashMap<(i32, i32, i32), MapCell> { HashMap::new() }
 
     fn traverse3d(&self, ray: &Ray, get_normal: bool, ctx: &Context) -> Option<HitRecord>;
 
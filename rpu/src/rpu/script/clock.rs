@@ -0,0 +1,36 @@
+use crate::prelude::*;
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI32, AtomicU64, Ordering};
+
+/// The time and the number of the frame being rendered. The clock is a shared handle like the ScriptBudget,
+/// the scripts of all objects of a context see the same time.
+#[derive(Clone, Default)]
+pub struct Clock {
+    /// The time in seconds, stored as the bits of the float.
+    time                        : Arc<AtomicU64>,
+    frame                       : Arc<AtomicI32>,
+}
+
+impl Clock {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the time and the frame of the next rendered frame.
+    pub fn set(&self, time: F, frame: I) {
+        self.time.store(time.to_bits(), Ordering::Relaxed);
+        self.frame.store(frame, Ordering::Relaxed);
+    }
+
+    /// The time in seconds.
+    pub fn time(&self) -> F {
+        F::from_bits(self.time.load(Ordering::Relaxed))
+    }
+
+    /// The number of the frame, counted from 0.
+    pub fn frame(&self) -> I {
+        self.frame.load(Ordering::Relaxed)
+    }
+}
@@ -1,11 +1,83 @@
 use crate::{prelude::*, rpu::compiler::ErrorType};
+
+use rhai::{CallFnOptions, EvalAltResult, ParseError, Position};
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The ids of the engines, the errors of a code block are reported once per engine.
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    /// The scope of the shader calls of the render thread, reused for every pixel. Shaders can not see the
+    /// scope of their object, the call rewinds it.
+    static SHADER_SCOPE: RefCell<Scope<'static>> = RefCell::new(Scope::new());
+}
+
+/// Where a code block is in the source, maps the positions of script errors back to it.
+struct CodeSource {
+    /// The line and the column of the name of the code block.
+    line                    : usize,
+    name_column             : usize,
+    /// The 1 based column on that line where the body starts.
+    column                  : usize,
+    /// The number of characters in front of the body on the first line of the compiled code.
+    offset                  : usize,
+    /// The source lines of the code block, starting with the line of its name.
+    lines                   : Vec<String>,
+}
+
+/// The constants a code block uses, they are constants of the scope while the block runs.
+#[derive(Default)]
+struct Globals {
+    constants               : Vec<(String, rhai::Dynamic)>,
+    time                    : bool,
+    frame                   : bool,
+}
+
+impl Globals {
+
+    /// Pushes the constants, the time and the frame to the scope.
+    fn push(&self, scope: &mut Scope, clock: &Clock) {
+        for (name, value) in &self.constants {
+            scope.push_constant_dynamic(name.clone(), value.clone());
+        }
+        if self.time {
+            scope.push_constant("time", clock.time());
+        }
+        if self.frame {
+            scope.push_constant("frame", clock.frame());
+        }
+    }
+}
+
 pub struct ScriptEngine<'a> {
         engine              : Engine,
         scope               : Scope<'a>,
 
         pub shader          : Option<AST>,
+        /// The color(uv, input) function of a user defined layer.
+        pub layer           : Option<AST>,
+        /// The distance(p) function of a custom SDF.
+        pub distance        : Option<AST>,
+
+        code_blocks         : HashMap<String, String>,
+
+        /// The code blocks as written in the source, the name, the arguments and the body.
+        functions           : Vec<(String, String, String)>,
+
+        /// Receives the errors of the code blocks while rendering.
+        diagnostics         : Option<Diagnostics>,
+        /// The file of the object, None for the main source string.
+        file                : Option<PathBuf>,
+        sources             : HashMap<String, CodeSource>,
+        globals             : HashMap<String, Globals>,
+        id                  : usize,
 
-        code_blocks         : HashMap<String, String>
+        /// The time and the frame of the code blocks.
+        clock               : Clock,
+
+        /// The limits of the scripts and the budget of the current frame.
+        budget              : Option<ScriptBudget>,
 }
 
 impl ScriptEngine<'_> {
@@ -14,7 +86,7 @@ impl ScriptEngine<'_> {
 
         let mut engine = Engine::new();
 
-        engine.register_type_with_name::<F3>("F2")
+        engine.register_type_with_name::<F2>("F2")
             .register_fn("F2", F2::new_1)
             .register_fn("F2", F2::new_2)
             .register_get_set("x", F2::get_x, F2::set_x)
@@ -35,14 +107,27 @@ impl ScriptEngine<'_> {
             .register_get_set("z", F4::get_z, F4::set_z)
             .register_get_set("w", F4::get_w, F4::set_w);
 
-        engine.register_fn("length", |f2: &mut F2| f2.value.norm());
+        super::math::register(&mut engine);
+        super::procedural::register(&mut engine, 0);
+        let clock = Clock::new();
+        register_clock(&mut engine, &clock);
 
         Self {
             engine,
             scope           : Scope::new(),
             code_blocks     : HashMap::new(),
+            functions       : vec![],
+            diagnostics     : None,
+            file            : None,
+            sources         : HashMap::new(),
+            globals         : HashMap::new(),
+            id              : NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            clock,
+            budget          : None,
 
             shader          : None,
+            layer           : None,
+            distance        : None,
         }
     }
 
@@ -50,34 +135,119 @@ impl ScriptEngine<'_> {
         &mut self.scope
     }
 
-    pub fn apply_properties(&mut self, props: Vec<Property>) -> Result<(), RPUError> {
+    /// Evaluates the given properties and checks them against the schema of the owning object.
+    pub fn apply_properties(&mut self, props: Vec<Property>, schema: &Schema) -> Result<(), Vec<RPUError>> {
+        let mut errors = vec![];
+        let mut constants : Vec<(String, Value)> = vec![];
+
         for p in props {
             match p {
-                Property::Property(key, value) => {
-                    let cmd = format!("let {} = {};", key, value);
-                    //println!("prop, {}", cmd);
-                    self.execute(cmd);
+                Property::Constant(name, value) => {
+                    if let ("seed", Value::Int(seed)) = (name.as_str(), &value) {
+                        self.set_seed(*seed);
+                    }
+                    constants.push((name, value));
                 },
-                Property::Function(name, args, body) => {
-                    let code = format!("fn {}({}) {{ {} }};", name, args, body);
-                    //println!("fn, {}", code);
-                    if name == "shader" {
-                        let rc = self.engine.compile(code);
-
-                        if rc.is_ok() {
-                            if let Some(ast) = rc.ok() {
-                                self.shader = Some(ast);
+                Property::Diagnostics(diagnostics, file) => {
+                    self.diagnostics = Some(diagnostics);
+                    self.file = file;
+                },
+                Property::Limits(budget) => {
+                    budget.limits().apply(&mut self.engine);
+                    let frame = budget.clone();
+                    self.engine.on_progress(move |operations| if frame.out_of_time(operations) { Some(rhai::Dynamic::UNIT) } else { None });
+                    self.budget = Some(budget);
+                },
+                Property::Clock(clock) => {
+                    register_clock(&mut self.engine, &clock);
+                    self.clock = clock;
+                },
+                Property::Property(key, value, token) => {
+                    if let Some(def) = schema.get(&key) {
+                        // Constants are only visible to the expression, they do not shadow the properties of the object
+                        let mut scope = self.scope.clone();
+                        for (name, value) in &constants {
+                            scope.push_constant_dynamic(name.clone(), value.to_dynamic());
+                        }
+                        let rc = self.engine.eval_with_scope::<rhai::Dynamic>(&mut scope, value.as_str());
+                        match rc {
+                            Ok(v) => {
+                                if let Some(v) = Value::from_dynamic(&v) {
+                                    match def.check(v) {
+                                        Ok(v) => self.set_value(&def.name, v),
+                                        Err(message) => errors.push(RPUError::at_token(ErrorType::Syntax, message, &token, "".to_string())),
+                                    }
+                                } else {
+                                    let message = format!("Property '{}' expects {}, found {} (e.g. {} = {}).", key, def.kind.name(), v.type_name(), key, def.kind.example());
+                                    errors.push(RPUError::at_token(ErrorType::Syntax, message, &token, "".to_string()));
+                                }
+                            },
+                            Err(err) => {
+                                let message = format!("Invalid value for property '{}': {}.", key, error_message(&err));
+                                errors.push(RPUError::at_token(ErrorType::Syntax, message, &token, "".to_string()));
                             }
-                        } else
-                        if let Some(error) = rc.err() {
-                            let err = RPUError::new(ErrorType::Syntax, error.to_string(), error.1.line().unwrap() as u32);
-                            return Err(err);
                         }
+                    } else {
+                        errors.push(RPUError::at_token(ErrorType::Syntax, schema.unknown_property(&key), &token, "".to_string()));
+                    }
+                },
+                Property::Function(name, args, body, token, source_line) => {
+                    // Event handlers like on_open() are called by the host, all objects support them
+                    if schema.get_function(&name).is_none() && !is_event(&name) {
+                        errors.push(RPUError::at_token(ErrorType::Syntax, schema.unknown_function(&name), &token, "".to_string()));
+                        continue;
+                    }
+
+                    self.functions.retain(|f| f.0 != name);
+                    self.functions.push((name.clone(), args.clone(), body.clone()));
+
+                    // The constants, the time and the frame the code block uses are pushed to the scope when it runs
+                    self.globals.insert(name.clone(), Globals {
+                        constants       : constants.iter().filter(|(n, _)| uses_identifier(&body, n)).map(|(n, v)| (n.clone(), v.to_dynamic())).collect(),
+                        time            : uses_identifier(&body, "time"),
+                        frame           : uses_identifier(&body, "frame"),
+                    });
+
+                    // The body starts after the closing parenthesis behind the name
+                    let after_name = token.column.saturating_sub(1) + name.chars().count();
+                    let column = source_line.chars().enumerate().skip(after_name).find(|(_, c)| *c == ')').map_or(after_name, |(i, _)| i + 1) + 1;
+                    let mut lines = vec![source_line];
+                    lines.extend(body.split('\n').skip(1).map(|l| l.to_string()));
+
+                    // The code blocks called for every pixel or step of the ray marcher are compiled as functions
+                    let function = name == "shader" || name == "color" || name == "distance";
+                    let wrapper = if function { format!("fn {}({}) {{ ", name, args) } else { "".to_string() };
+                    self.sources.insert(name.clone(), CodeSource {
+                        line            : token.line,
+                        name_column     : token.column,
+                        column,
+                        offset          : wrapper.chars().count(),
+                        lines,
+                    });
+
+                    if function {
+                        match self.engine.compile(format!("{}{} }};", wrapper, body)) {
+                            Ok(ast) if name == "shader" => self.shader = Some(ast),
+                            Ok(ast) if name == "color" => self.layer = Some(ast),
+                            Ok(ast) => self.distance = Some(ast),
+                            Err(err) => errors.push(self.syntax_error(&name, &err)),
+                        }
+                    } else {
+                        // Other code blocks run later, their syntax is checked now
+                        if let Err(err) = self.engine.compile(body.as_str()) {
+                            errors.push(self.syntax_error(&name, &err));
+                        }
+                        self.code_blocks.insert(name, body);
                     }
                 }
             }
         }
-        Ok(())
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
     }
 
     pub fn set_code_block(&mut self, name: String, code: String) -> Result<(), RPUError> {
@@ -92,7 +262,6 @@ impl ScriptEngine<'_> {
                 }
             } else
             if let Some(error) = rc.err() {
-                println!("{:?}", error.to_string());
                 let err = RPUError::new(ErrorType::Syntax, error.to_string(), error.1.line().unwrap() as u32);
                 return Err(err);
             }
@@ -103,6 +272,22 @@ impl ScriptEngine<'_> {
         Ok(())
     }
 
+    /// Returns the code blocks as written in the source, the name, the arguments and the body.
+    pub fn get_functions(&self) -> &Vec<(String, String, String)> {
+        &self.functions
+    }
+
+    /// Forgets the source of the code blocks, i.e. when the code belongs to the definition of a user defined
+    /// layer and not to the object itself.
+    pub fn clear_functions(&mut self) {
+        self.functions.clear();
+    }
+
+    /// Returns the body of the given code block.
+    pub fn get_code_block(&self, name: &str) -> Option<&String> {
+        self.code_blocks.get(name)
+    }
+
     pub fn execute(&mut self, code: String) {
         let _rc = self.engine.eval_with_scope::<rhai::Dynamic>(&mut self.scope, code.as_str());
         //println!("{:?}", rc);
@@ -112,34 +297,172 @@ impl ScriptEngine<'_> {
 
         if let Some(ast) = &self.shader {
 
-            let mut scope = Scope::new();
-            let rc = self.engine.call_fn::<F4>(&mut scope, &ast, "shader", (F2::new(uv.p),F2::new_2(uv.rect[2], uv.rect[3]),));
+            let rc = self.call_function::<F4>(ast, "shader", (F2::new(uv.p), F2::new_2(uv.rect[2], uv.rect[3]),));
 
-            if rc.is_ok() {
-                if let Some(out) = rc.ok() {
-                    *color = glm::mix(&color, &out.value, out.value.w);
-                }
-            } else {
-                println!("{:?}", rc);
+            match rc {
+                Ok(out) => *color = glm::mix(color, &out.value, out.value.w),
+                Err(err) => self.report("shader", &err),
+            }
+        }
+    }
+
+    /// Calls the color(uv, input) function of a user defined layer with the world uv of the pixel and the color
+    /// of the layers below, the result replaces the color.
+    pub fn execute_layer(&self, uv: &UV, color: &mut GF4) {
+
+        if let Some(ast) = &self.layer {
+
+            let rc = self.call_function::<F4>(ast, "color", (F2::new(uv.world), F4::new(*color),));
+
+            match rc {
+                Ok(out) => *color = out.value,
+                Err(err) => self.report("color", &err),
             }
         }
     }
 
+    /// Calls the distance(p) function of a custom SDF with the point relative to the object. Without the
+    /// function or on errors the point is far away from the object.
+    pub fn execute_distance(&self, p: &GF3) -> F {
+
+        if let Some(ast) = &self.distance {
+
+            let rc = self.call_function::<F>(ast, "distance", (F3::new(*p),));
+
+            match rc {
+                Ok(d) => return d,
+                Err(err) => self.report("distance", &err),
+            }
+        }
+
+        F::MAX
+    }
+
+    /// Calls the function of a code block called for every pixel or step of the ray marcher with the
+    /// shader scope of the thread.
+    fn call_function<T: Clone + Send + Sync + 'static>(&self, ast: &AST, name: &str, args: impl rhai::FuncArgs) -> Result<T, Box<EvalAltResult>> {
+        SHADER_SCOPE.with(|scope| {
+            let mut scope = scope.borrow_mut();
+            let length = scope.len();
+            if let Some(globals) = self.globals.get(name) {
+                globals.push(&mut scope, &self.clock);
+            }
+
+            // The AST only defines the function, there is nothing to evaluate before the call
+            let options = CallFnOptions::new().eval_ast(false).rewind_scope(true);
+            let rc = self.engine.call_fn_with_options::<T>(options, &mut scope, ast, name, args);
+            scope.rewind(length);
+            rc
+        })
+    }
+
     pub fn execute_block(&mut self, name: String) -> bool {
 
         if let Some(code) = &self.code_blocks.get(&name) {
+            // The constants and the variables defined by the block are gone after the run
+            let length = self.scope.len();
+            if let Some(globals) = self.globals.get(&name) {
+                globals.push(&mut self.scope, &self.clock);
+            }
             let rc = self.engine.eval_with_scope::<rhai::Dynamic>(&mut self.scope, code.as_str());
+            self.scope.rewind(length);
 
-            if rc.is_ok() {
-                return true;
-            } else {
-                println!("{:?}", rc);
+            match rc {
+                Ok(_) => return true,
+                Err(err) => self.report(&name, &err),
             }
         }
 
         false
     }
 
+    /// Runs the on_<event>() code block with the given arguments, called by the host via RPU::call. The
+    /// arguments are variables of the scope while the block runs. Returns false if there is no such block.
+    pub fn execute_event(&mut self, event: &str, args: &[Value]) -> Result<bool, RPUError> {
+        let name = format!("on_{}", event);
+
+        let Some(code) = self.code_blocks.get(&name) else {
+            return Ok(false);
+        };
+        let names : Vec<String> = self.functions.iter().find(|f| f.0 == name)
+            .map(|f| f.1.split(',').map(|a| a.trim().to_string()).filter(|a| !a.is_empty()).collect())
+            .unwrap_or_default();
+        if names.len() != args.len() {
+            let message = format!("{}() expects {} argument(s) but was called with {}.", name, names.len(), args.len());
+            return Err(self.locate(RPUError::new(ErrorType::Script, message, 0), &name, Position::NONE));
+        }
+
+        let length = self.scope.len();
+        if let Some(globals) = self.globals.get(&name) {
+            globals.push(&mut self.scope, &self.clock);
+        }
+        for (arg, value) in names.iter().zip(args) {
+            self.scope.push_dynamic(arg.clone(), value.to_dynamic());
+        }
+        let rc = self.engine.eval_with_scope::<rhai::Dynamic>(&mut self.scope, code.as_str());
+        self.scope.rewind(length);
+
+        match rc {
+            Ok(_) => Ok(true),
+            Err(err) => {
+                self.report(&name, &err);
+                Err(self.script_error(&name, &err))
+            }
+        }
+    }
+
+    /// Reports the first error of the given code block while rendering to the diagnostics. Exceeded limits
+    /// are recorded in the budget and fail the frame, only the first one of a frame is reported.
+    fn report(&self, name: &str, error: &EvalAltResult) {
+        if let (Some(budget), Some(limit)) = (&self.budget, ScriptLimit::of(error)) {
+            let message = format!("Error in {}(): The script exceeded {}.", name, budget.limits().describe(limit));
+            let position = error.unwrap_inner().position();
+            let err = self.locate(RPUError::new(ErrorType::Script, message, 0).with_code("script-limit"), name, position);
+            if !budget.exceed(limit, err.clone()) {
+                return;
+            }
+            if let Some(diagnostics) = &self.diagnostics {
+                diagnostics.push_once(format!("{}:{}", self.id, name), || err);
+            }
+        } else
+        if let Some(diagnostics) = &self.diagnostics {
+            diagnostics.push_once(format!("{}:{}", self.id, name), || self.script_error(name, error));
+        }
+    }
+
+    /// The error of a code block while running, located in the source of the code block.
+    pub fn script_error(&self, name: &str, error: &EvalAltResult) -> RPUError {
+        let position = error.unwrap_inner().position();
+        self.locate(RPUError::new(ErrorType::Script, format!("Error in {}(): {}.", name, error_message(error)), 0), name, position)
+    }
+
+    /// The syntax error of a code block, located in the source of the code block.
+    fn syntax_error(&self, name: &str, error: &ParseError) -> RPUError {
+        self.locate(RPUError::new(ErrorType::Syntax, format!("Syntax error in {}(): {}.", name, error.0), 0), name, error.1)
+    }
+
+    /// Moves the error to the source position of the given position in the compiled code block, errors without
+    /// a position point at the name of the code block.
+    fn locate(&self, mut error: RPUError, name: &str, position: Position) -> RPUError {
+        if let (Some(source), None) = (self.sources.get(name), position.line()) {
+            error.line = source.line as u32;
+            error.column = source.name_column as u32;
+            error.source_line = source.lines[0].clone();
+        } else
+        if let (Some(source), Some(line)) = (self.sources.get(name), position.line()) {
+            let column = position.position().unwrap_or(1);
+            if line == 1 {
+                error.line = source.line as u32;
+                error.column = (source.column + column.saturating_sub(source.offset + 1)) as u32;
+            } else {
+                error.line = (source.line + line - 1) as u32;
+                error.column = column as u32;
+            }
+            error.source_line = source.lines.get(line - 1).cloned().unwrap_or_default();
+        }
+        error.in_file(self.file.clone())
+    }
+
     pub fn get_vector2(&self, name: &str) -> Option<Vector2<F>> {
         if let Some(v) = self.scope.get_value::<F2>(name) {
             return Some(v.value);
@@ -195,6 +518,54 @@ impl ScriptEngine<'_> {
         self.scope.set_value(name, v);
     }
 
+    pub fn get_value(&self, name: &str) -> Option<Value> {
+        if let Some(v) = self.scope.get_value::<rhai::Dynamic>(name) {
+            return Value::from_dynamic(&v);
+        }
+        None
+    }
+
+    pub fn set_value(&mut self, name: &str, v: Value) {
+        self.scope.set_value(name.to_string(), v.to_dynamic());
+    }
+
+    /// Seeds the noise and hash functions, the top level constant "seed" seeds the scripts of a document.
+    pub fn set_seed(&mut self, seed: I) {
+        super::procedural::register(&mut self.engine, seed as u32);
+    }
+
+    /// Evaluates the value and defines it as a variable of the scope, returns the value.
+    pub fn eval_constant(&mut self, name: &str, code: &str) -> Result<Value, String> {
+        match self.engine.eval_with_scope::<rhai::Dynamic>(&mut self.scope, code) {
+            Ok(v) => {
+                if let Some(value) = Value::from_dynamic(&v) {
+                    if let ("seed", Value::Int(seed)) = (name, &value) {
+                        self.set_seed(*seed);
+                    }
+                    self.set_value(name, value.clone());
+                    Ok(value)
+                } else {
+                    Err(format!("Constant '{}' has the unsupported type {}, expected a number, F2, F3, F4 or string.", name, v.type_name()))
+                }
+            },
+            Err(err) => Err(format!("Invalid value for constant '{}': {}.", name, error_message(&err))),
+        }
+    }
+
+    /// Evaluates the value with the variables of the scope, the scope is not changed.
+    pub fn eval(&self, code: &str) -> Result<Value, String> {
+        let mut scope = self.scope.clone();
+        match self.engine.eval_with_scope::<rhai::Dynamic>(&mut scope, code) {
+            Ok(v) => Value::from_dynamic(&v).ok_or(format!("unsupported type {}", v.type_name())),
+            Err(err) => Err(err.to_string()),
+        }
+    }
+
+    /// Returns all variables of the scope with a property type, in the order they were defined.
+    pub fn get_values(&self) -> Vec<(String, Value)> {
+        self.scope.iter().filter_map(|(name, _, v)| Value::from_dynamic(&v).map(|v| (name.to_string(), v))).collect()
+    }
+
     pub fn get_string(&self, name: &str) -> Option<String> {
         if let Some(v) = self.scope.get_value::<String>(name) {
             return Some(v);
@@ -202,3 +573,33 @@ impl ScriptEngine<'_> {
         None
     }
 }
+
+/// Registers time() and frame(), which read the given clock.
+fn register_clock(engine: &mut Engine, clock: &Clock) {
+    let time = clock.clone();
+    engine.register_fn("time", move || time.time());
+    let frame = clock.clone();
+    engine.register_fn("frame", move || frame.frame());
+}
+
+/// The message of the script error without the position, the caller reports the position in the source.
+fn error_message(error: &EvalAltResult) -> String {
+    let inner = error.unwrap_inner();
+    let message = inner.to_string();
+    message.trim_end_matches(&format!(" ({})", inner.position())).to_string()
+}
+
+/// True if the code block is an event handler like on_open().
+fn is_event(name: &str) -> bool {
+    name.len() > 3 && name.starts_with("on_")
+}
+
+/// True if the code contains the given identifier as a whole word.
+fn uses_identifier(code: &str, name: &str) -> bool {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    code.match_indices(name).any(|(index, _)| {
+        let before = code[..index].chars().next_back();
+        let after = code[index + name.len()..].chars().next();
+        !before.is_some_and(is_word) && !after.is_some_and(is_word)
+    })
+}
@@ -0,0 +1,185 @@
+use crate::prelude::*;
+
+use rhai::EvalAltResult;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// The limits of the scripts of a scene, they keep a broken code block, i.e. an endless loop in a shader,
+/// from hanging the renderer.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ScriptLimits {
+    /// The maximum number of operations of a single run of a code block.
+    pub max_operations          : u64,
+    /// The maximum depth of nested function calls.
+    pub max_call_depth          : usize,
+    /// The maximum length of a string in characters.
+    pub max_string_size         : usize,
+    /// The maximum number of elements of an array.
+    pub max_array_size          : usize,
+    /// The time all scripts of a frame may take together, None for no limit. The operation limit already
+    /// stops endless loops, the budget keeps slow scripts from blocking an interactive application.
+    pub frame_budget            : Option<Duration>,
+}
+
+impl Default for ScriptLimits {
+    fn default() -> Self {
+        Self {
+            max_operations      : 1_000_000,
+            max_call_depth      : 32,
+            max_string_size     : 10_000,
+            max_array_size      : 10_000,
+            frame_budget        : None,
+        }
+    }
+}
+
+impl ScriptLimits {
+
+    /// Applies the limits to the given engine, except the frame budget which is checked by ScriptBudget.
+    pub fn apply(&self, engine: &mut Engine) {
+        engine.set_max_operations(self.max_operations);
+        engine.set_max_call_levels(self.max_call_depth);
+        engine.set_max_string_size(self.max_string_size);
+        engine.set_max_array_size(self.max_array_size);
+    }
+
+    /// Describes the exceeded limit for error messages.
+    pub fn describe(&self, limit: ScriptLimit) -> String {
+        match limit {
+            ScriptLimit::Operations => format!("the limit of {} operations", self.max_operations),
+            ScriptLimit::CallDepth => format!("the limit of {} nested calls", self.max_call_depth),
+            ScriptLimit::DataSize => format!("the size limit of {} characters per string and {} elements per array", self.max_string_size, self.max_array_size),
+            ScriptLimit::FrameBudget => format!("the frame budget of {} ms", self.frame_budget.unwrap_or_default().as_millis()),
+        }
+    }
+}
+
+/// The limit a script exceeded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScriptLimit {
+    Operations,
+    CallDepth,
+    DataSize,
+    FrameBudget,
+}
+
+impl ScriptLimit {
+
+    /// The limit which stopped the script with the given error, None for other errors.
+    pub fn of(error: &EvalAltResult) -> Option<ScriptLimit> {
+        match error.unwrap_inner() {
+            EvalAltResult::ErrorTooManyOperations(..) => Some(ScriptLimit::Operations),
+            EvalAltResult::ErrorStackOverflow(..) => Some(ScriptLimit::CallDepth),
+            EvalAltResult::ErrorDataTooLarge(..) => Some(ScriptLimit::DataSize),
+            EvalAltResult::ErrorTerminated(..) => Some(ScriptLimit::FrameBudget),
+            _ => None,
+        }
+    }
+}
+
+/// A frame which could not be rendered completely because a script exceeded its limits. The frame is still
+/// rendered, without the contributions of the stopped scripts.
+#[derive(Clone, Debug)]
+pub struct RenderError {
+    pub limit                   : ScriptLimit,
+    /// The error of the first code block which exceeded the limit, located in the source.
+    pub error                   : RPUError,
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.error.description)
+    }
+}
+
+impl std::error::Error for RenderError {}
+
+/// The limits of a context together with the state of the current frame. The budget is a shared handle,
+/// the script engines of all objects of a context check the same frame deadline.
+#[derive(Clone)]
+pub struct ScriptBudget {
+    limits                      : ScriptLimits,
+
+    epoch                       : Instant,
+    /// The end of the current frame in nanoseconds since the epoch, 0 outside of a frame.
+    deadline                    : Arc<AtomicU64>,
+    /// Set when a script exceeded a limit or the frame budget ran out, all scripts stop at their next operation.
+    stopped                     : Arc<AtomicBool>,
+    /// The first limit exceeded in the current frame.
+    exceeded                    : Arc<Mutex<Option<RenderError>>>,
+}
+
+impl Default for ScriptBudget {
+    fn default() -> Self {
+        Self::new(ScriptLimits::default())
+    }
+}
+
+impl ScriptBudget {
+
+    pub fn new(limits: ScriptLimits) -> Self {
+        Self {
+            limits,
+
+            epoch               : Instant::now(),
+            deadline            : Arc::new(AtomicU64::new(0)),
+            stopped             : Arc::new(AtomicBool::new(false)),
+            exceeded            : Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn limits(&self) -> &ScriptLimits {
+        &self.limits
+    }
+
+    /// Starts the clock of the frame budget.
+    pub fn start_frame(&self) {
+        let deadline = match self.limits.frame_budget {
+            Some(budget) => (self.epoch.elapsed() + budget).as_nanos().max(1) as u64,
+            None => 0,
+        };
+        self.deadline.store(deadline, Ordering::Relaxed);
+        self.stopped.store(false, Ordering::Relaxed);
+        *self.exceeded.lock().unwrap() = None;
+    }
+
+    /// Ends the frame, returns the first limit a script exceeded during the frame.
+    pub fn finish_frame(&self) -> Result<(), RenderError> {
+        self.deadline.store(0, Ordering::Relaxed);
+        self.stopped.store(false, Ordering::Relaxed);
+        match self.exceeded.lock().unwrap().take() {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    /// Called by the engines while a script runs, true if the script has to stop as a limit was exceeded or
+    /// the frame budget ran out. The clock is only read every 1024 operations and at the start of a script.
+    pub fn out_of_time(&self, operations: u64) -> bool {
+        if self.stopped.load(Ordering::Relaxed) {
+            return true;
+        }
+        if operations == 1 || operations.is_multiple_of(1024) {
+            let deadline = self.deadline.load(Ordering::Relaxed);
+            if deadline != 0 && self.epoch.elapsed().as_nanos() as u64 > deadline {
+                self.stopped.store(true, Ordering::Relaxed);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Records an exceeded limit and stops all scripts of the frame, the frame fails anyway. Returns false if
+    /// the frame already exceeded a limit, the scripts stopped by it are not reported.
+    pub fn exceed(&self, limit: ScriptLimit, error: RPUError) -> bool {
+        let mut exceeded = self.exceeded.lock().unwrap();
+        if exceeded.is_some() {
+            return false;
+        }
+        *exceeded = Some(RenderError { limit, error });
+        self.stopped.store(true, Ordering::Relaxed);
+        true
+    }
+}
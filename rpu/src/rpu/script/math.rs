@@ -0,0 +1,225 @@
+use crate::prelude::*;
+
+use rhai::EvalAltResult;
+
+/// Registers the operators and the component wise functions of a vector type. Scalars can be floats or
+/// integers, i.e. uv * 2 and uv * 2.0 both work.
+macro_rules! register_vector {
+    ($engine:ident, $T:ident) => {
+        $engine.register_fn("+", |a: $T, b: $T| $T::new(a.value + b.value));
+        $engine.register_fn("-", |a: $T, b: $T| $T::new(a.value - b.value));
+        $engine.register_fn("*", |a: $T, b: $T| $T::new(a.value.component_mul(&b.value)));
+        $engine.register_fn("/", |a: $T, b: $T| $T::new(a.value.component_div(&b.value)));
+        $engine.register_fn("-", |a: $T| $T::new(-a.value));
+        $engine.register_fn("==", |a: $T, b: $T| a == b);
+        $engine.register_fn("!=", |a: $T, b: $T| a != b);
+
+        $engine.register_fn("+", |a: $T, s: F| $T::new(a.value.add_scalar(s)));
+        $engine.register_fn("-", |a: $T, s: F| $T::new(a.value.add_scalar(-s)));
+        $engine.register_fn("*", |a: $T, s: F| $T::new(a.value * s));
+        $engine.register_fn("/", |a: $T, s: F| $T::new(a.value / s));
+        $engine.register_fn("+", |s: F, a: $T| $T::new(a.value.add_scalar(s)));
+        $engine.register_fn("-", |s: F, a: $T| $T::new((-a.value).add_scalar(s)));
+        $engine.register_fn("*", |s: F, a: $T| $T::new(a.value * s));
+        $engine.register_fn("/", |s: F, a: $T| $T::new(a.value.map(|x| s / x)));
+
+        $engine.register_fn("+", |a: $T, s: I| $T::new(a.value.add_scalar(s as F)));
+        $engine.register_fn("-", |a: $T, s: I| $T::new(a.value.add_scalar(-s as F)));
+        $engine.register_fn("*", |a: $T, s: I| $T::new(a.value * s as F));
+        $engine.register_fn("/", |a: $T, s: I| $T::new(a.value / s as F));
+        $engine.register_fn("*", |s: I, a: $T| $T::new(a.value * s as F));
+
+        $engine.register_fn("length", |a: $T| a.value.norm());
+        $engine.register_fn("distance", |a: $T, b: $T| (a.value - b.value).norm());
+        $engine.register_fn("dot", |a: $T, b: $T| a.value.dot(&b.value));
+        $engine.register_fn("normalize", |a: $T| $T::new(a.value.normalize()));
+
+        $engine.register_fn("fract", |a: $T| $T::new(a.value.map(|x| x - x.floor())));
+        $engine.register_fn("floor", |a: $T| $T::new(a.value.map(|x| x.floor())));
+        $engine.register_fn("ceil", |a: $T| $T::new(a.value.map(|x| x.ceil())));
+        $engine.register_fn("round", |a: $T| $T::new(a.value.map(|x| x.round())));
+        $engine.register_fn("abs", |a: $T| $T::new(a.value.abs()));
+        $engine.register_fn("sign", |a: $T| $T::new(a.value.map(sign)));
+        $engine.register_fn("sqrt", |a: $T| $T::new(a.value.map(|x| x.sqrt())));
+        $engine.register_fn("sin", |a: $T| $T::new(a.value.map(|x| x.sin())));
+        $engine.register_fn("cos", |a: $T| $T::new(a.value.map(|x| x.cos())));
+        $engine.register_fn("pow", |a: $T, y: F| $T::new(a.value.map(|x| x.powf(y))));
+        $engine.register_fn("mod", |a: $T, y: F| $T::new(a.value.map(|x| modulo(x, y))));
+
+        $engine.register_fn("min", |a: $T, b: $T| $T::new(a.value.inf(&b.value)));
+        $engine.register_fn("max", |a: $T, b: $T| $T::new(a.value.sup(&b.value)));
+        $engine.register_fn("min", |a: $T, s: F| $T::new(a.value.map(|x| x.min(s))));
+        $engine.register_fn("max", |a: $T, s: F| $T::new(a.value.map(|x| x.max(s))));
+        $engine.register_fn("clamp", |a: $T, lo: F, hi: F| $T::new(a.value.map(|x| x.max(lo).min(hi))));
+        $engine.register_fn("clamp", |a: $T, lo: $T, hi: $T| $T::new(a.value.sup(&lo.value).inf(&hi.value)));
+
+        $engine.register_fn("mix", |a: $T, b: $T, t: F| $T::new(a.value + (b.value - a.value) * t));
+        $engine.register_fn("mix", |a: $T, b: $T, t: $T| $T::new(a.value + (b.value - a.value).component_mul(&t.value)));
+        $engine.register_fn("step", |edge: F, a: $T| $T::new(a.value.map(|x| step(edge, x))));
+        $engine.register_fn("smoothstep", |e0: F, e1: F, a: $T| $T::new(a.value.map(|x| smoothstep(e0, e1, x))));
+    };
+}
+
+/// Registers the GLSL style math library: the operators of the vector types, the common math functions
+/// for floats and vectors (component wise) and the color helpers.
+pub fn register(engine: &mut Engine) {
+    // Newer rhai versions compare floats with an epsilon, which makes inf == inf and nan != nan false. The
+    // registered exact comparisons below are only used when the built-in operators are not.
+    engine.set_fast_operators(false);
+
+    register_scalar(engine);
+
+    register_vector!(engine, F2);
+    register_vector!(engine, F3);
+    register_vector!(engine, F4);
+
+    engine.register_fn("cross", |a: F3, b: F3| F3::new(a.value.cross(&b.value)));
+
+    // Swizzles
+    engine.register_get("xy", |v: &mut F3| F2::new_2(v.value.x, v.value.y));
+    engine.register_get("xy", |v: &mut F4| F2::new_2(v.value.x, v.value.y));
+    engine.register_get("xyz", |v: &mut F4| F3::new_3(v.value.x, v.value.y, v.value.z));
+    engine.register_get("rgb", |v: &mut F4| F3::new_3(v.value.x, v.value.y, v.value.z));
+    engine.register_fn("F3", |xy: F2, z: F| F3::new_3(xy.value.x, xy.value.y, z));
+    engine.register_fn("F4", |xyz: F3, w: F| F4::new_4(xyz.value.x, xyz.value.y, xyz.value.z, w));
+
+    register_color(engine);
+}
+
+/// Registers a comparison operator for floats and for floats mixed with integers.
+macro_rules! register_comparison {
+    ($engine:ident, $op:tt) => {
+        $engine.register_fn(stringify!($op), |a: F, b: F| a $op b);
+        $engine.register_fn(stringify!($op), |a: F, b: I| a $op b as F);
+        $engine.register_fn(stringify!($op), |a: I, b: F| (a as F) $op b);
+    };
+}
+
+fn register_scalar(engine: &mut Engine) {
+    // Exact IEEE comparisons like in GLSL
+    register_comparison!(engine, ==);
+    register_comparison!(engine, !=);
+    register_comparison!(engine, <);
+    register_comparison!(engine, >);
+    register_comparison!(engine, <=);
+    register_comparison!(engine, >=);
+
+    engine.register_fn("fract", |x: F| x - x.floor());
+    engine.register_fn("floor", |x: F| x.floor());
+    engine.register_fn("ceil", |x: F| x.ceil());
+    engine.register_fn("round", |x: F| x.round());
+    engine.register_fn("abs", |x: F| x.abs());
+    engine.register_fn("sign", |x: F| sign(x));
+    engine.register_fn("sqrt", |x: F| x.sqrt());
+    engine.register_fn("exp", |x: F| x.exp());
+    engine.register_fn("log", |x: F| x.ln());
+    engine.register_fn("pow", |x: F, y: F| x.powf(y));
+    engine.register_fn("sin", |x: F| x.sin());
+    engine.register_fn("cos", |x: F| x.cos());
+    engine.register_fn("tan", |x: F| x.tan());
+    engine.register_fn("asin", |x: F| x.asin());
+    engine.register_fn("acos", |x: F| x.acos());
+    engine.register_fn("atan", |x: F| x.atan());
+    engine.register_fn("atan", |y: F, x: F| y.atan2(x));
+    engine.register_fn("radians", |x: F| x.to_radians());
+    engine.register_fn("degrees", |x: F| x.to_degrees());
+    engine.register_fn("min", |x: F, y: F| x.min(y));
+    engine.register_fn("max", |x: F, y: F| x.max(y));
+    engine.register_fn("mod", |x: F, y: F| modulo(x, y));
+    engine.register_fn("clamp", |x: F, lo: F, hi: F| x.max(lo).min(hi));
+    engine.register_fn("mix", |a: F, b: F, t: F| a + (b - a) * t);
+    engine.register_fn("step", |edge: F, x: F| step(edge, x));
+    engine.register_fn("smoothstep", |e0: F, e1: F, x: F| smoothstep(e0, e1, x));
+}
+
+fn register_color(engine: &mut Engine) {
+    engine.register_fn("hsv", |h: F, s: F, v: F| {
+        let rgb = hsv_to_rgb(GF3::new(h, s, v));
+        F4::new_4(rgb.x, rgb.y, rgb.z, 1.0)
+    });
+    engine.register_fn("hsv_to_rgb", |c: F3| F3::new(hsv_to_rgb(c.value)));
+    engine.register_fn("rgb_to_hsv", |c: F3| F3::new(rgb_to_hsv(c.value)));
+    engine.register_fn("hsv_to_rgb", |c: F4| {
+        let rgb = hsv_to_rgb(c.value.xyz());
+        F4::new_4(rgb.x, rgb.y, rgb.z, c.value.w)
+    });
+    engine.register_fn("rgb_to_hsv", |c: F4| {
+        let hsv = rgb_to_hsv(c.value.xyz());
+        F4::new_4(hsv.x, hsv.y, hsv.z, c.value.w)
+    });
+
+    engine.register_fn("rgb", |r: I, g: I, b: I| F4::new_4(r as F / 255.0, g as F / 255.0, b as F / 255.0, 1.0));
+    engine.register_fn("hex", |color: I| hex_to_color(color));
+    engine.register_fn("hex", |color: &str| -> Result<F4, Box<EvalAltResult>> {
+        let digits = color.trim_start_matches('#');
+        match I::from_str_radix(digits, 16) {
+            Ok(value) if digits.len() == 6 => Ok(hex_to_color(value)),
+            _ => Err(format!("Invalid hex color '{}', expected i.e. \"#a9957b\"", color).into()),
+        }
+    });
+    engine.register_fn("to_hex", |c: F4| {
+        let channel = |x: F| (x.clamp(0.0, 1.0) * 255.0).round() as u8;
+        format!("#{:02x}{:02x}{:02x}", channel(c.value.x), channel(c.value.y), channel(c.value.z))
+    });
+}
+
+fn sign(x: F) -> F {
+    if x > 0.0 { 1.0 } else if x < 0.0 { -1.0 } else { 0.0 }
+}
+
+/// The GLSL modulo, the result has the sign of y.
+fn modulo(x: F, y: F) -> F {
+    x - y * (x / y).floor()
+}
+
+fn step(edge: F, x: F) -> F {
+    if x < edge { 0.0 } else { 1.0 }
+}
+
+fn smoothstep(e0: F, e1: F, x: F) -> F {
+    let t = ((x - e0) / (e1 - e0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Converts a color given as 0xRRGGBB.
+fn hex_to_color(color: I) -> F4 {
+    let channel = |shift: I| ((color >> shift) & 0xff) as F / 255.0;
+    F4::new_4(channel(16), channel(8), channel(0), 1.0)
+}
+
+/// Converts hue, saturation and value, all in 0..1, to RGB.
+pub fn hsv_to_rgb(hsv: GF3) -> GF3 {
+    let h = modulo(hsv.x, 1.0) * 6.0;
+    let (s, v) = (hsv.y, hsv.z);
+    let c = v * s;
+    let x = c * (1.0 - (modulo(h, 2.0) - 1.0).abs());
+    let (r, g, b) = match h as I {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    GF3::new(r + v - c, g + v - c, b + v - c)
+}
+
+/// Converts RGB to hue, saturation and value, all in 0..1.
+pub fn rgb_to_hsv(rgb: GF3) -> GF3 {
+    let max = rgb.max();
+    let min = rgb.min();
+    let d = max - min;
+    let h = if d == 0.0 {
+        0.0
+    } else
+    if max == rgb.x {
+        modulo((rgb.y - rgb.z) / d, 6.0)
+    } else
+    if max == rgb.y {
+        (rgb.z - rgb.x) / d + 2.0
+    } else {
+        (rgb.x - rgb.y) / d + 4.0
+    };
+    let s = if max == 0.0 { 0.0 } else { d / max };
+    GF3::new(h / 6.0, s, max)
+}
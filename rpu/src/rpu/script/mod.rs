@@ -3,14 +3,26 @@ use crate::prelude::*;
 use std::iter::once;
 use rhai::FuncArgs;
 
+pub mod clock;
 pub mod engine;
+pub mod limits;
+pub mod math;
+pub mod procedural;
+pub mod schema;
+
 pub trait Script : Sync + Send {
 
     fn get_scope<'a>(&mut self) -> &'a Scope;
     fn get_engine<'a>(&self) -> &'a ScriptEngine;
-    fn apply_properties(&mut self, props: Vec<Property>) -> Result<(), RPUError>;
+    /// The properties and code blocks supported by the object.
+    fn schema(&self) -> Schema;
+    fn apply_properties(&mut self, props: Vec<Property>) -> Result<(), Vec<RPUError>>;
     fn set_code_block(&mut self, name: String, code: String);
     fn execute(&mut self, code: String);
+    /// Runs the on_<event>() code block of the object with the given arguments, false if there is none.
+    fn call(&mut self, event: &str, args: &[Value]) -> Result<bool, RPUError>;
+    /// Sets a property to a value already checked against the schema and updates the object.
+    fn set_property(&mut self, name: &str, value: Value);
 }
 
 // F2
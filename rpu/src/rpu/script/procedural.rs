@@ -0,0 +1,119 @@
+use crate::prelude::*;
+
+use ::noise::{NoiseFn, Seedable, MultiFractal, Fbm, OpenSimplex, Value as ValueNoise};
+
+/// Registers the noise and hash functions, all of them are deterministic for the given seed. Noise values
+/// are in -1..1 (roughly for fbm), worley distances start at 0 and hashes are positive integers.
+pub fn register(engine: &mut Engine, seed: u32) {
+    // A single octave of Fbm is the Perlin noise of the seed. noise 0.7 exports two structs named Perlin,
+    // the name itself is ambiguous.
+    let perlin = Fbm::new().set_octaves(1).set_seed(seed);
+    let simplex = OpenSimplex::new().set_seed(seed);
+    let value = ValueNoise::new().set_seed(seed);
+
+    let p = perlin.clone();
+    engine.register_fn("perlin2", move |uv: F2| p.get([uv.value.x, uv.value.y]));
+    let p = perlin.clone();
+    engine.register_fn("perlin3", move |v: F3| p.get([v.value.x, v.value.y, v.value.z]));
+
+    engine.register_fn("simplex", move |uv: F2| simplex.get([uv.value.x, uv.value.y]));
+    engine.register_fn("simplex", move |v: F3| simplex.get([v.value.x, v.value.y, v.value.z]));
+
+    engine.register_fn("value_noise", move |uv: F2| value.get([uv.value.x, uv.value.y]));
+    engine.register_fn("value_noise", move |v: F3| value.get([v.value.x, v.value.y, v.value.z]));
+
+    let p = perlin.clone();
+    engine.register_fn("fbm", move |uv: F2, octaves: I, lacunarity: F, gain: F| fbm(|f| p.get([uv.value.x * f, uv.value.y * f]), octaves, lacunarity, gain));
+    let p = perlin.clone();
+    engine.register_fn("fbm", move |v: F3, octaves: I, lacunarity: F, gain: F| fbm(|f| p.get([v.value.x * f, v.value.y * f, v.value.z * f]), octaves, lacunarity, gain));
+    engine.register_fn("fbm", move |uv: F2| fbm(|f| perlin.get([uv.value.x * f, uv.value.y * f]), 5, 2.0, 0.5));
+
+    engine.register_fn("worley", move |uv: F2| voronoi2(seed, uv.value).x);
+    engine.register_fn("worley", move |v: F3| worley3(seed, v.value));
+    engine.register_fn("voronoi", move |uv: F2| F3::new(voronoi2(seed, uv.value)));
+
+    engine.register_fn("hash", move |x: I| positive(hash(seed, &[x])));
+    engine.register_fn("hash", move |x: I, y: I| positive(hash(seed, &[x, y])));
+    engine.register_fn("hash", move |x: I, y: I, z: I| positive(hash(seed, &[x, y, z])));
+    engine.register_fn("hash01", move |x: I| unit(hash(seed, &[x])));
+    engine.register_fn("hash01", move |x: I, y: I| unit(hash(seed, &[x, y])));
+    engine.register_fn("hash01", move |x: I, y: I, z: I| unit(hash(seed, &[x, y, z])));
+}
+
+/// Fractal noise, the sum of the octaves of the given noise with rising frequency and falling amplitude.
+fn fbm(noise: impl Fn(F) -> F, octaves: I, lacunarity: F, gain: F) -> F {
+    let mut sum = 0.0;
+    let mut frequency = 1.0;
+    let mut amplitude = 1.0;
+    let mut total = 0.0;
+    for _ in 0..octaves.max(1) {
+        sum += noise(frequency) * amplitude;
+        total += amplitude;
+        frequency *= lacunarity;
+        amplitude *= gain;
+    }
+    sum / total
+}
+
+/// The distance to the closest and the random value of the closest feature point and the distance between
+/// the closest and the second closest point, small on the edges of the cells.
+fn voronoi2(seed: u32, p: GF2) -> GF3 {
+    let cell = p.map(|x| x.floor());
+    let (mut f1, mut f2, mut id) = (F::MAX, F::MAX, 0.0);
+    for y in -1..=1 {
+        for x in -1..=1 {
+            let (cx, cy) = (cell.x as I + x, cell.y as I + y);
+            let point = GF2::new(cx as F + unit(hash(seed, &[cx, cy, 0])), cy as F + unit(hash(seed, &[cx, cy, 1])));
+            let d = (point - p).norm();
+            if d < f1 {
+                f2 = f1;
+                f1 = d;
+                id = unit(hash(seed, &[cx, cy, 2]));
+            } else
+            if d < f2 {
+                f2 = d;
+            }
+        }
+    }
+    GF3::new(f1, id, f2 - f1)
+}
+
+/// The distance to the closest feature point.
+fn worley3(seed: u32, p: GF3) -> F {
+    let cell = p.map(|x| x.floor());
+    let mut f1 = F::MAX;
+    for z in -1..=1 {
+        for y in -1..=1 {
+            for x in -1..=1 {
+                let (cx, cy, cz) = (cell.x as I + x, cell.y as I + y, cell.z as I + z);
+                let point = GF3::new(cx as F + unit(hash(seed, &[cx, cy, cz, 0])), cy as F + unit(hash(seed, &[cx, cy, cz, 1])), cz as F + unit(hash(seed, &[cx, cy, cz, 2])));
+                f1 = f1.min((point - p).norm());
+            }
+        }
+    }
+    f1
+}
+
+/// Hashes the integers with the seed (murmur3 finalizer).
+pub fn hash(seed: u32, values: &[I]) -> u32 {
+    let mut h = seed ^ 0x9e3779b9;
+    for v in values {
+        h ^= (*v as u32).wrapping_mul(0xcc9e2d51);
+        h = h.rotate_left(13).wrapping_mul(5).wrapping_add(0xe6546b64);
+        h ^= h >> 16;
+        h = h.wrapping_mul(0x85ebca6b);
+        h ^= h >> 13;
+        h = h.wrapping_mul(0xc2b2ae35);
+        h ^= h >> 16;
+    }
+    h
+}
+
+fn positive(h: u32) -> I {
+    (h >> 1) as I
+}
+
+/// The hash as a float in 0..1.
+pub fn unit(h: u32) -> F {
+    h as F / (u32::MAX as F + 1.0)
+}
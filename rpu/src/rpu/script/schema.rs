@@ -0,0 +1,316 @@
+use crate::prelude::*;
+
+use rhai::Dynamic;
+
+/// The type of a property value.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum PropertyType {
+    Int,
+    F,
+    F2,
+    F3,
+    F4,
+    String,
+}
+
+impl PropertyType {
+
+    /// The name of the type as written in RPU source.
+    pub fn name(&self) -> &'static str {
+        match self {
+            PropertyType::Int => "integer",
+            PropertyType::F => "float",
+            PropertyType::F2 => "F2",
+            PropertyType::F3 => "F3",
+            PropertyType::F4 => "F4",
+            PropertyType::String => "string",
+        }
+    }
+
+    /// An example value of the type, used in error messages.
+    pub fn example(&self) -> &'static str {
+        match self {
+            PropertyType::Int => "1",
+            PropertyType::F => "1.0",
+            PropertyType::F2 => "F2(1.0, 1.0)",
+            PropertyType::F3 => "F3(1.0, 1.0, 1.0)",
+            PropertyType::F4 => "F4(1.0, 1.0, 1.0, 1.0) or #ffffff",
+            PropertyType::String => "\"Name\"",
+        }
+    }
+}
+
+/// A typed property value.
+#[derive(PartialEq, Clone, Debug)]
+pub enum Value {
+    Int(I),
+    F(F),
+    F2(GF2),
+    F3(GF3),
+    F4(GF4),
+    String(String),
+}
+
+impl Value {
+
+    /// The type of the value.
+    pub fn get_type(&self) -> PropertyType {
+        match self {
+            Value::Int(_) => PropertyType::Int,
+            Value::F(_) => PropertyType::F,
+            Value::F2(_) => PropertyType::F2,
+            Value::F3(_) => PropertyType::F3,
+            Value::F4(_) => PropertyType::F4,
+            Value::String(_) => PropertyType::String,
+        }
+    }
+
+    /// Converts a script value, None if the value is not of a property type.
+    pub fn from_dynamic(value: &Dynamic) -> Option<Value> {
+        if let Some(v) = value.clone().try_cast::<I>() {
+            Some(Value::Int(v))
+        } else
+        if let Some(v) = value.clone().try_cast::<F>() {
+            Some(Value::F(v))
+        } else
+        if let Some(v) = value.clone().try_cast::<F2>() {
+            Some(Value::F2(v.value))
+        } else
+        if let Some(v) = value.clone().try_cast::<F3>() {
+            Some(Value::F3(v.value))
+        } else
+        if let Some(v) = value.clone().try_cast::<F4>() {
+            Some(Value::F4(v.value))
+        } else
+        if value.is::<rhai::ImmutableString>() {
+            Some(Value::String(value.to_string()))
+        } else {
+            None
+        }
+    }
+
+    /// Converts the value into a script value.
+    pub fn to_dynamic(&self) -> Dynamic {
+        match self {
+            Value::Int(v) => Dynamic::from(*v),
+            Value::F(v) => Dynamic::from(*v),
+            Value::F2(v) => Dynamic::from(F2::new(*v)),
+            Value::F3(v) => Dynamic::from(F3::new(*v)),
+            Value::F4(v) => Dynamic::from(F4::new(*v)),
+            Value::String(v) => Dynamic::from(v.clone()),
+        }
+    }
+
+    /// The value as a float, integers are converted.
+    pub fn as_float(&self) -> Option<F> {
+        match self {
+            Value::Int(v) => Some(*v as F),
+            Value::F(v) => Some(*v),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Value {
+    /// Writes the value as RPU source.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Int(v) => write!(f, "{}", v),
+            Value::F(v) => write!(f, "{}", float(*v)),
+            Value::F2(v) => write!(f, "F2({}, {})", float(v.x), float(v.y)),
+            Value::F3(v) => write!(f, "F3({}, {}, {})", float(v.x), float(v.y), float(v.z)),
+            Value::F4(v) => write!(f, "F4({}, {}, {}, {})", float(v.x), float(v.y), float(v.z), float(v.w)),
+            Value::String(v) => {
+                let escaped = v.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n").replace('\t', "\\t").replace('\r', "\\r");
+                write!(f, "\"{}\"", escaped)
+            },
+        }
+    }
+}
+
+/// Writes a float as RPU source, infinite and NaN values are written as the divisions which give them.
+fn float(v: F) -> String {
+    if v.is_nan() {
+        return "0.0 / 0.0".to_string();
+    }
+    if v.is_infinite() {
+        return if v > 0.0 { "1.0 / 0.0".to_string() } else { "-1.0 / 0.0".to_string() };
+    }
+    format!("{:?}", v)
+}
+
+/// Describes a property of an element, object, camera or layout.
+#[derive(Clone, Debug)]
+pub struct PropertyDef {
+    pub name                : String,
+    pub kind                : PropertyType,
+    pub default             : Option<Value>,
+    /// The inclusive range of numeric values.
+    pub range               : Option<(F, F)>,
+    /// The allowed values of a String property, empty if any string is allowed.
+    pub values              : &'static [&'static str],
+    pub help                : &'static str,
+}
+
+impl PropertyDef {
+
+    pub fn new(name: &str, kind: PropertyType, default: Option<Value>, range: Option<(F, F)>, help: &'static str) -> Self {
+        Self {
+            name            : name.to_string(),
+            kind,
+            default,
+            range,
+            values          : &[],
+            help,
+        }
+    }
+
+    /// Restricts a String property to the given values, i.e. the kinds of a pattern.
+    pub fn with_values(mut self, values: &'static [&'static str]) -> Self {
+        self.values = values;
+        self
+    }
+
+    /// Checks the given value against the definition and returns it converted to the property type.
+    pub fn check(&self, value: Value) -> Result<Value, String> {
+        let value = match (self.kind, &value) {
+            // Integers are fine for float properties
+            (PropertyType::F, Value::Int(v)) => Value::F(*v as F),
+            (kind, v) if kind == v.get_type() => value,
+            (kind, v) => {
+                return Err(format!("Property '{}' expects {}, found {} (e.g. {} = {}).", self.name, kind.name(), v.get_type().name(), self.name, kind.example()));
+            }
+        };
+
+        if let Some((min, max)) = self.range {
+            if let Some(v) = value.as_float() {
+                // NaN is not in any range
+                if !(min..=max).contains(&v) {
+                    return Err(format!("Property '{}' must be in the range {} to {}, found {}.", self.name, min, max, v));
+                }
+            }
+        }
+
+        if let Value::String(v) = &value {
+            if !self.values.is_empty() && !self.values.contains(&v.as_str()) {
+                return Err(format!("Property '{}' expects one of \"{}\", found \"{}\".", self.name, self.values.join("\", \""), v));
+            }
+        }
+        Ok(value)
+    }
+}
+
+/// Describes a code block an object can define, like shader(uv, size).
+#[derive(Clone, Debug)]
+pub struct FunctionSignature {
+    pub name                : &'static str,
+    pub args                : &'static str,
+    pub help                : &'static str,
+}
+
+impl FunctionSignature {
+
+    pub fn new(name: &'static str, args: &'static str, help: &'static str) -> Self {
+        Self {
+            name,
+            args,
+            help,
+        }
+    }
+}
+
+/// The allowed properties and code blocks of an element, object, camera or layout.
+#[derive(Clone, Debug)]
+pub struct Schema {
+    pub name                : String,
+    pub properties          : Vec<PropertyDef>,
+    pub functions           : Vec<FunctionSignature>,
+}
+
+impl Schema {
+
+    pub fn new(name: &str, properties: Vec<PropertyDef>, functions: Vec<FunctionSignature>) -> Self {
+        Self {
+            name            : name.to_string(),
+            properties,
+            functions,
+        }
+    }
+
+    /// Returns the definition of the given property.
+    pub fn get(&self, name: &str) -> Option<&PropertyDef> {
+        self.properties.iter().find(|p| p.name == name)
+    }
+
+    /// Returns the definition of the given code block.
+    pub fn get_function(&self, name: &str) -> Option<&FunctionSignature> {
+        self.functions.iter().find(|f| f.name == name)
+    }
+
+    /// The error message for an unknown property, suggests the closest known property.
+    pub fn unknown_property(&self, name: &str) -> String {
+        let names : Vec<&str> = self.properties.iter().map(|p| p.name.as_str()).collect();
+        let mut message = format!("Unknown property '{}' for {}.", name, self.name);
+        if let Some(closest) = closest_match(name, &names) {
+            message += format!(" Did you mean '{}'?", closest).as_str();
+        } else
+        if names.is_empty() {
+            message += format!(" {} has no properties.", self.name).as_str();
+        } else {
+            message += format!(" Expected one of: {}.", names.join(", ")).as_str();
+        }
+        message
+    }
+
+    /// The error message for an unknown code block.
+    pub fn unknown_function(&self, name: &str) -> String {
+        let functions : Vec<String> = self.functions.iter().map(|f| format!("{}({})", f.name, f.args)).collect();
+        if functions.is_empty() {
+            format!("Unknown function '{}', {} does not support code blocks.", name, self.name)
+        } else {
+            format!("Unknown function '{}' for {}. Expected one of: {}.", name, self.name, functions.join(", "))
+        }
+    }
+
+    /// Sets the default values of all properties in the given engine.
+    pub fn apply_defaults(&self, engine: &mut ScriptEngine) {
+        for p in &self.properties {
+            if let Some(default) = &p.default {
+                engine.set_value(&p.name, default.clone());
+            }
+        }
+    }
+}
+
+/// Returns the closest candidate to the given name, if it is close enough to be a likely typo.
+pub fn closest_match<'a>(name: &str, candidates: &[&'a str]) -> Option<&'a str> {
+    let lower = name.to_lowercase();
+    let mut best : Option<(usize, &str)> = None;
+    for c in candidates {
+        let d = edit_distance(&lower, &c.to_lowercase());
+        if best.is_none() || d < best.unwrap().0 {
+            best = Some((d, c));
+        }
+    }
+    match best {
+        Some((d, c)) if d <= 2.max(name.chars().count() / 3) => Some(c),
+        _ => None,
+    }
+}
+
+/// The Levenshtein distance between two strings.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b : Vec<char> = b.chars().collect();
+    let mut row : Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for j in 0..b.len() {
+            let old = row[j + 1];
+            row[j + 1] = if ca == b[j] { prev } else { 1 + prev.min(row[j]).min(row[j + 1]) };
+            prev = old;
+        }
+    }
+    row[b.len()]
+}
@@ -3,30 +3,36 @@ use crate::prelude::*;
 #[derive()]
 pub struct SDF3DCube<'a> {
         engine              : ScriptEngine<'a>,
+
+        position            : GF3,
+        size                : GF3,
 }
 
 impl SDF3D for SDF3DCube<'_> {
 
     fn new() -> Self {
 
-        let mut engine = ScriptEngine::new();
-        engine.set_vector3("position", Vector3::new(0.0, 0.0, 0.0));
-        engine.set_vector3("size", Vector3::new(0.5, 0.5, 0.5));
-        engine.set_vector3("rotation", Vector3::new(0.0, 0.0, 0.0));
+        let mut cube = Self {
+            engine          : ScriptEngine::new(),
+
+            position        : GF3::new(0.0, 0.0, 0.0),
+            size            : GF3::new(0.5, 0.5, 0.5),
+        };
 
-        Self {
-            engine,
+        cube.schema().apply_defaults(&mut cube.engine);
+        cube
+    }
+
+    fn update(&mut self) {
+        if self.engine.execute_block("update".to_string()) {
+            self.read_properties();
         }
     }
 
     #[inline(always)]
     fn get_distance(&self, x: &Vector3<F>, instance: &Vector3<F>) -> F {
 
-        let position = self.engine.get_vector3("position").unwrap();
-        let size = self.engine.get_vector3("size").unwrap();
-        //let rotation = self.engine.get_vector3("rotation").unwrap();
-
-        let q : GF3 = glm::convert((x - position - instance).abs() - size);
+        let q : GF3 = glm::convert((x - self.position - instance).abs() - self.size);
         glm::length(&glm::max(&q, 0.0)) + q.x.max(q.y.max(q.z)).min(0.0)
 
         //vec3 q = abs(p) - b;
@@ -44,15 +50,53 @@ impl Script for SDF3DCube<'_> {
         &self.engine
     }
 
-    fn apply_properties(&mut self, props: Vec<Property>) -> Result<(), RPUError> {
-        self.engine.apply_properties(props)
+    fn schema(&self) -> Schema {
+        Schema::new("sdfCube", vec![
+            PropertyDef::new("texture", PropertyType::String, None, None, "The name of the texture of the cube."),
+            PropertyDef::new("position", PropertyType::F3, Some(Value::F3(GF3::new(0.0, 0.0, 0.0))), None, "The position of the cube inside its cell."),
+            PropertyDef::new("size", PropertyType::F3, Some(Value::F3(GF3::new(0.5, 0.5, 0.5))), None, "The half size of the cube."),
+            PropertyDef::new("rotation", PropertyType::F3, Some(Value::F3(GF3::new(0.0, 0.0, 0.0))), None, "The rotation of the cube."),
+        ], vec![
+            FunctionSignature::new("update", "", "Called before every frame, can modify the cube properties."),
+        ])
+    }
+
+    fn apply_properties(&mut self, props: Vec<Property>) -> Result<(), Vec<RPUError>> {
+        let schema = self.schema();
+        let rc = self.engine.apply_properties(props, &schema);
+        self.read_properties();
+        rc
     }
 
     fn execute(&mut self, code: String) {
         self.engine.execute(code);
     }
 
+    fn call(&mut self, event: &str, args: &[Value]) -> Result<bool, RPUError> {
+        let rc = self.engine.execute_event(event, args);
+        self.read_properties();
+        rc
+    }
+
+    fn set_property(&mut self, name: &str, value: Value) {
+        self.engine.set_value(name, value);
+        self.read_properties();
+    }
+
     fn set_code_block(&mut self, name: String, code: String) {
         _ = self.engine.set_code_block(name, code);
     }
-}
\ No newline at end of file
+}
+
+impl SDF3DCube<'_> {
+
+    /// Copies the position and the size out of the scope, get_distance() is called for every step of a ray.
+    fn read_properties(&mut self) {
+        if let Some(position) = self.engine.get_vector3("position") {
+            self.position = position;
+        }
+        if let Some(size) = self.engine.get_vector3("size") {
+            self.size = size;
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use crate::prelude::*;
+
+/// An SDF defined by its distance(p) code block, i.e. for props like barrels or pillars.
+pub struct SDF3DCustom<'a> {
+        engine              : ScriptEngine<'a>,
+
+        position            : GF3,
+}
+
+impl SDF3D for SDF3DCustom<'_> {
+
+    fn new() -> Self {
+
+        let mut custom = Self {
+            engine          : ScriptEngine::new(),
+
+            position        : GF3::new(0.0, 0.0, 0.0),
+        };
+
+        custom.schema().apply_defaults(&mut custom.engine);
+        custom
+    }
+
+    fn update(&mut self) {
+        if self.engine.execute_block("update".to_string()) {
+            self.read_properties();
+        }
+    }
+
+    #[inline(always)]
+    fn get_distance(&self, x: &Vector3<F>, instance: &Vector3<F>) -> F {
+        self.engine.execute_distance(&(x - self.position - instance))
+    }
+}
+
+impl Script for SDF3DCustom<'_> {
+
+    fn get_scope<'a>(&mut self) -> &'a Scope {
+        self.engine.get_scope()
+    }
+
+    fn get_engine<'a>(&self) -> &'a ScriptEngine {
+        &self.engine
+    }
+
+    fn schema(&self) -> Schema {
+        Schema::new("sdfCustom", vec![
+            PropertyDef::new("texture", PropertyType::String, None, None, "The name of the texture of the object."),
+            PropertyDef::new("position", PropertyType::F3, Some(Value::F3(GF3::new(0.0, 0.0, 0.0))), None, "The position of the object inside its cell."),
+        ], vec![
+            FunctionSignature::new("distance", "p", "Returns the signed distance from the point p, relative to the position, to the surface of the object."),
+            FunctionSignature::new("update", "", "Called before every frame, can modify the object properties."),
+        ])
+    }
+
+    fn apply_properties(&mut self, props: Vec<Property>) -> Result<(), Vec<RPUError>> {
+        let schema = self.schema();
+        let rc = self.engine.apply_properties(props, &schema);
+        self.read_properties();
+        rc
+    }
+
+    fn execute(&mut self, code: String) {
+        self.engine.execute(code);
+    }
+
+    fn call(&mut self, event: &str, args: &[Value]) -> Result<bool, RPUError> {
+        let rc = self.engine.execute_event(event, args);
+        self.read_properties();
+        rc
+    }
+
+    fn set_property(&mut self, name: &str, value: Value) {
+        self.engine.set_value(name, value);
+        self.read_properties();
+    }
+
+    fn set_code_block(&mut self, name: String, code: String) {
+        _ = self.engine.set_code_block(name, code);
+    }
+}
+
+impl SDF3DCustom<'_> {
+
+    /// Copies the position out of the scope, get_distance() is called for every step of a ray.
+    fn read_properties(&mut self) {
+        if let Some(position) = self.engine.get_vector3("position") {
+            self.position = position;
+        }
+    }
+}
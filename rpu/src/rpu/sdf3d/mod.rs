@@ -1,11 +1,14 @@
 pub mod sphere;
 pub mod cube;
+pub mod custom;
 
 use crate::prelude::*;
 
 pub trait SDF3D : Sync + Send + Script {
     fn new() -> Self where Self: Sized;
 
+    fn update(&mut self) {}
+
     fn get_distance(&self, x: &Vector3<F>, instance: &Vector3<F>) -> F;
     fn get_normal(&self, x: &Vector3<F>, instance: &Vector3<F>) -> Vector3<F> {
 
@@ -3,28 +3,36 @@ use crate::prelude::*;
 #[derive()]
 pub struct SDF3DSphere<'a> {
         engine              : ScriptEngine<'a>,
+
+        position            : GF3,
+        radius              : F,
 }
 
 impl SDF3D for SDF3DSphere<'_> {
 
     fn new() -> Self {
 
-        let mut engine = ScriptEngine::new();
-        engine.set_vector3("position", Vector3::new(0.0, 0.0, 0.0));
-        engine.set_float("radius", 0.5);
+        let mut sphere = Self {
+            engine          : ScriptEngine::new(),
+
+            position        : GF3::new(0.0, 0.0, 0.0),
+            radius          : 0.5,
+        };
 
-        Self {
-            engine,
+        sphere.schema().apply_defaults(&mut sphere.engine);
+        sphere
+    }
+
+    fn update(&mut self) {
+        if self.engine.execute_block("update".to_string()) {
+            self.read_properties();
         }
     }
 
     #[inline(always)]
     fn get_distance(&self, x: &Vector3<F>, instance: &Vector3<F>) -> F {
 
-        let position = self.engine.get_vector3("position").unwrap();
-        let radius = self.engine.get_float("radius").unwrap();
-
-        let p = (x - position - instance).norm() - radius;
+        let p = (x - self.position - instance).norm() - self.radius;
 
         return p;
     }
@@ -40,15 +48,52 @@ impl Script for SDF3DSphere<'_> {
         &self.engine
     }
 
-    fn apply_properties(&mut self, props: Vec<Property>) -> Result<(), RPUError> {
-        self.engine.apply_properties(props)
+    fn schema(&self) -> Schema {
+        Schema::new("sdfSphere", vec![
+            PropertyDef::new("texture", PropertyType::String, None, None, "The name of the texture of the sphere."),
+            PropertyDef::new("position", PropertyType::F3, Some(Value::F3(GF3::new(0.0, 0.0, 0.0))), None, "The position of the sphere inside its cell."),
+            PropertyDef::new("radius", PropertyType::F, Some(Value::F(0.5)), Some((0.0, F::MAX)), "The radius of the sphere."),
+        ], vec![
+            FunctionSignature::new("update", "", "Called before every frame, can modify the sphere properties."),
+        ])
+    }
+
+    fn apply_properties(&mut self, props: Vec<Property>) -> Result<(), Vec<RPUError>> {
+        let schema = self.schema();
+        let rc = self.engine.apply_properties(props, &schema);
+        self.read_properties();
+        rc
     }
 
     fn execute(&mut self, code: String) {
         self.engine.execute(code);
     }
 
+    fn call(&mut self, event: &str, args: &[Value]) -> Result<bool, RPUError> {
+        let rc = self.engine.execute_event(event, args);
+        self.read_properties();
+        rc
+    }
+
+    fn set_property(&mut self, name: &str, value: Value) {
+        self.engine.set_value(name, value);
+        self.read_properties();
+    }
+
     fn set_code_block(&mut self, name: String, code: String) {
         _ = self.engine.set_code_block(name, code);
     }
-}
\ No newline at end of file
+}
+
+impl SDF3DSphere<'_> {
+
+    /// Copies the position and the radius out of the scope, get_distance() is called for every step of a ray.
+    fn read_properties(&mut self) {
+        if let Some(position) = self.engine.get_vector3("position") {
+            self.position = position;
+        }
+        if let Some(radius) = self.engine.get_float("radius") {
+            self.radius = radius;
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use rpu::prelude::*;
+
+const SCENE: &str = r#"Pinhole
+    origin = F3(3.0, 0.5001, 9.5)
+    center = F3(3.0, 0.5, 0.0)
+
+Texture
+    name = "Torch"
+    Color
+        color = #e07a1f
+        shader(uv, size)
+            F4(F3(0.5 + 0.5 * sin(time * 3.0)), 1.0)
+
+Voxel'a
+    texture = "Torch"
+
+sdfSphere'b
+    texture = "Torch"
+    radius = 0.2
+    update()
+        radius = 0.2 + 0.2 * frame
+
+Grid3D
+:    a
+:aaa a
+:
+: a  a
+: ab a
+"#;
+
+fn render_at(rpu: &mut RPU, time: F) -> Vec<u8> {
+    let mut frame = vec![0; 100 * 400 * 4];
+    assert!(rpu.render_at(time, &mut frame, (0, 0, 100, 400), 100).is_ok());
+    assert!(rpu.diagnostics().iter().all(|d| d.severity != Severity::Error));
+    frame
+}
+
+fn compile(source: &str) -> RPU {
+    let mut rpu = RPU::new(100, 400);
+    assert!(rpu.compile(source.to_string()).is_ok());
+    rpu
+}
+
+#[test]
+fn shaders_see_the_time() {
+    let source = SCENE.replace("    update()\n        radius = 0.2 + 0.2 * frame\n", "");
+    let mut rpu = compile(&source);
+    let first = render_at(&mut rpu, 0.0);
+    let later = render_at(&mut rpu, 1.0);
+    assert!(first != later);
+    assert!(first == render_at(&mut rpu, 0.0));
+}
+
+#[test]
+fn update_blocks_run_before_every_frame() {
+    let source = SCENE.replace("sin(time * 3.0)", "0.0");
+    let mut rpu = compile(&source);
+    let first = render_at(&mut rpu, 0.0);
+    let second = render_at(&mut rpu, 0.0);
+    assert!(first != second);
+
+    // The frame counts the rendered frames, not the time
+    let mut rpu = compile(&source);
+    assert!(first == render_at(&mut rpu, 0.0));
+}
@@ -0,0 +1,52 @@
+use rpu::prelude::*;
+
+const SCENE: &str = r#"let big = 1.0 / 0.0
+let nan = 0.0 / 0.0
+let level = 0.5
+
+Texture*
+    Color
+        color = #444444
+        shader(uv, size)
+            F4(if big > 1.0 { level } else { 0.0 }, if nan != nan { 1.0 } else { 0.0 }, 0.0, 1.0)
+"#;
+
+fn render(source: &str) -> (RPU, Vec<u8>) {
+    let mut rpu = RPU::new(100, 400);
+    assert!(rpu.compile(source.to_string()).is_ok());
+    let mut frame = vec![0; 100 * 400 * 4];
+    assert!(rpu.render(&mut frame, (0, 0, 100, 400), 100).is_ok());
+    (rpu, frame)
+}
+
+fn errors(rpu: &RPU) -> Vec<RPUError> {
+    rpu.diagnostics().into_iter().filter(|d| d.severity == Severity::Error).collect()
+}
+
+#[test]
+fn constants_keep_infinite_and_nan_values() {
+    let (rpu, frame) = render(SCENE);
+    assert!(errors(&rpu).is_empty());
+
+    let (_, expected) = render(&SCENE.replace("if big > 1.0 { level } else { 0.0 }, if nan != nan { 1.0 } else { 0.0 }", "0.5, 1.0"));
+    assert!(frame == expected);
+}
+
+#[test]
+fn script_errors_are_not_shifted_by_constants() {
+    let (rpu, _) = render(&SCENE.replace("0.0, 1.0)\n", "missing, 1.0)\n"));
+
+    let errors = errors(&rpu);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].description, "Error in shader(): Variable not found: missing.");
+    assert_eq!((errors[0].line, errors[0].column), (9, 89));
+}
+
+#[test]
+fn invalid_constants_are_reported_at_let() {
+    let mut rpu = RPU::new(100, 400);
+    let errors = rpu.compile(SCENE.replace("let level = 0.5", "let level = wall")).err().unwrap();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].description, "Invalid value for constant 'level': Variable not found: wall.");
+    assert_eq!((errors[0].line, errors[0].column), (3, 1));
+}
@@ -0,0 +1,133 @@
+use rpu::prelude::*;
+
+const SCENE: &str = r#"Texture*
+    Color
+        color = #444444
+        shader(uv, size)
+            F4(missing, 0.0, 0.0, 1.0)
+
+Texture
+    name = "Stone"
+    Color
+        color = #a9957b
+"#;
+
+fn codes(rpu: &RPU, severity: Severity) -> Vec<&'static str> {
+    rpu.diagnostics().iter().filter(|d| d.severity == severity).map(|d| d.code).collect()
+}
+
+#[test]
+fn warning_levels() {
+    let mut rpu = RPU::new(100, 400);
+    assert!(rpu.compile(SCENE.to_string()).is_ok());
+    assert_eq!(codes(&rpu, Severity::Warning), vec!["unused-texture"]);
+    assert_eq!(codes(&rpu, Severity::Info), vec!["summary"]);
+
+    rpu.set_warning_level("unused-texture", WarningLevel::Allow);
+    assert!(rpu.compile(SCENE.to_string()).is_ok());
+    assert!(codes(&rpu, Severity::Warning).is_empty());
+
+    rpu.set_warning_level("unused-texture", WarningLevel::Deny);
+    assert!(rpu.compile(SCENE.to_string()).is_err());
+    assert_eq!(codes(&rpu, Severity::Error), vec!["unused-texture"]);
+}
+
+#[test]
+fn script_errors_are_reported_once() {
+    let mut rpu = RPU::new(100, 400);
+    assert!(rpu.compile(SCENE.to_string()).is_ok());
+    assert!(codes(&rpu, Severity::Error).is_empty());
+
+    let mut frame = vec![0; 100 * 400 * 4];
+    assert!(rpu.render(&mut frame, (0, 0, 100, 400), 100).is_ok());
+    assert_eq!(codes(&rpu, Severity::Error), vec!["script"]);
+
+    // The error points at the variable in the RPU source
+    let error = rpu.diagnostics().into_iter().find(|d| d.severity == Severity::Error).unwrap();
+    assert_eq!((error.line, error.column), (5, 16));
+    assert_eq!(error.source_line.trim(), "F4(missing, 0.0, 0.0, 1.0)");
+}
+
+#[test]
+fn syntax_errors_point_into_the_source() {
+    let mut rpu = RPU::new(100, 400);
+    let errors = rpu.compile(SCENE.replace("F4(missing,", "F4(1.0 +,")).err().unwrap();
+    assert_eq!(errors[0].code, "syntax");
+    assert_eq!((errors[0].line, errors[0].column), (5, 21));
+}
+
+#[test]
+fn all_errors_of_a_source_are_reported() {
+    let source = r#"Texture
+    name = "Bricks"
+    Color
+        colr = #a9957b
+    Noise
+        scale = 2.0
+        shader(uv, size)
+            F4(1.0 +, 0.0, 0.0, 1.0)
+
+Voxel'a
+    texture = "Brick"
+
+Grid3D
+:a
+"#;
+
+    let mut rpu = RPU::new(100, 400);
+    let errors = rpu.compile(source.to_string()).err().unwrap();
+    let errors : Vec<(&str, u32, u32, &str)> = errors.iter().map(|e| (e.code, e.line, e.column, e.description.as_str())).collect();
+    assert_eq!(errors, vec![
+        ("syntax", 4, 9, "Unknown property 'colr' for Color. Did you mean 'color'?"),
+        ("syntax", 6, 9, "Property 'scale' expects F2, found float (e.g. scale = F2(1.0, 1.0))."),
+        ("syntax", 8, 21, "Syntax error in shader(): Unexpected ','."),
+        ("unknown-texture", 11, 5, "Unknown texture 'Brick'. Did you mean 'Bricks'?"),
+    ]);
+}
+
+#[test]
+fn texture_name_diagnostics() {
+    let source = r#"Texture
+    name = "Bricks"
+    Color
+        color = #a9957b
+
+Texture
+    name = "Bricks"
+    Color
+        color = #bc4a3c
+
+Texture
+    name = "Stone"
+    Color
+        color = #808080
+
+Texture
+    name = "Moss"
+    Color
+        color = #22aa22
+
+Voxel'a
+    texture = "Bricks"
+
+Voxel'b
+    texture = "Ston"
+
+Voxel'c
+    texture = "Moss"
+
+Grid3D
+:abc
+"#;
+
+    let mut rpu = RPU::new(100, 400);
+    assert!(rpu.compile(source.to_string()).is_err());
+    let diagnostics = rpu.diagnostics();
+    let diagnostics : Vec<(Severity, &str, u32, u32, &str)> = diagnostics.iter().map(|d| (d.severity, d.code, d.line, d.column, d.description.as_str())).collect();
+    assert_eq!(diagnostics, vec![
+        (Severity::Error, "duplicate-texture", 7, 5, "A texture named 'Bricks' is already defined at line 2, column 5."),
+        (Severity::Error, "ambiguous-texture", 22, 5, "Texture 'Bricks' is ambiguous, 2 textures have this name."),
+        (Severity::Error, "unknown-texture", 25, 5, "Unknown texture 'Ston'. Did you mean 'Stone'?"),
+        (Severity::Warning, "unused-texture", 12, 5, "Texture 'Stone' is never used."),
+    ]);
+}
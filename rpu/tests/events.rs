@@ -0,0 +1,69 @@
+use rpu::prelude::*;
+
+const SCENE: &str = r#"Pinhole
+    origin = F3(3.0, 0.5001, 9.5)
+    center = F3(3.0, 0.5, 0.0)
+
+Texture
+    name = "Wood"
+    Color
+        color = #8a5a2b
+
+Voxel'a
+    texture = "Wood"
+
+sdfSphere'door
+    texture = "Wood"
+    radius = 0.2
+    on_open(amount)
+        radius += amount
+
+Grid3D
+    Legend
+        D = 'door
+:    a
+:aaa a
+:
+: a  a
+: aD a
+"#;
+
+fn render(rpu: &mut RPU) -> Vec<u8> {
+    let mut frame = vec![0; 100 * 400 * 4];
+    assert!(rpu.render_at(0.0, &mut frame, (0, 0, 100, 400), 100).is_ok());
+    frame
+}
+
+fn compile(source: &str) -> RPU {
+    let mut rpu = RPU::new(100, 400);
+    assert!(rpu.compile(source.to_string()).is_ok());
+    rpu
+}
+
+#[test]
+fn events_change_the_properties_of_the_object() {
+    let mut rpu = compile(SCENE);
+    let closed = render(&mut rpu);
+
+    assert!(rpu.call("door", "open", &[Value::F(0.3)]).is_ok());
+    let open = render(&mut rpu);
+    assert!(closed != open);
+
+    let bigger = SCENE.replace("radius = 0.2", "radius = 0.5");
+    assert!(open == render(&mut compile(&bigger)));
+}
+
+#[test]
+fn events_report_unknown_targets_and_arguments() {
+    let mut rpu = compile(SCENE);
+
+    let err = rpu.call("window", "open", &[Value::F(0.3)]).err().unwrap();
+    assert_eq!(err.description, "No object with the instance symbol 'window' and no texture named 'window'.");
+
+    let err = rpu.call("door", "close", &[]).err().unwrap();
+    assert_eq!(err.description, "'door' has no on_close() code block.");
+
+    let err = rpu.call("door", "open", &[]).err().unwrap();
+    assert_eq!(err.description, "on_open() expects 1 argument(s) but was called with 0.");
+    assert_eq!(err.line, 16);
+}
@@ -0,0 +1,76 @@
+use rpu::prelude::*;
+
+const SOURCE: &str = "// The wall color
+let wall   =  #A9957B // warm
+
+Texture
+  name = \"Bricks\"
+  // Layers
+  Color
+      color = wall
+  Bricks
+      color=#BC4A3C
+      shader(uv, size)
+          F4(uv.x,0.2, 0.1, 1.0)
+
+Voxel'a
+  texture = \"Bricks\"   // the bricks
+
+Grid3D
+:  a
+:a a  a
+:
+::  a //x
+";
+
+const FORMATTED: &str = "// The wall color
+let wall = #a9957b // warm
+
+Texture
+    name = \"Bricks\"
+    // Layers
+    Color
+        color = wall
+    Bricks
+        color = #bc4a3c
+        shader(uv, size)
+            F4(uv.x,0.2, 0.1, 1.0)
+
+Voxel'a
+    texture = \"Bricks\" // the bricks
+
+Grid3D
+:  a
+:a a  a
+:
+::  a //x
+";
+
+fn format(code: &str) -> String {
+    Formatter::new().format(code.to_string(), None).ok().unwrap()
+}
+
+#[test]
+fn comments_are_kept() {
+    assert_eq!(format(SOURCE), FORMATTED);
+}
+
+#[test]
+fn map_rows_are_unchanged() {
+    // Trailing spaces, tabs and comment like text are part of the map
+    let source = SOURCE.to_string() + ":  a   \n:a\ta  // x\n";
+    let rows = |code: &str| code.lines().filter(|l| l.starts_with(':')).map(|l| l.to_string()).collect::<Vec<String>>();
+    let formatted = format(&source);
+    assert_eq!(rows(&formatted), rows(&source));
+    assert!(formatted.ends_with(":  a   \n:a\ta  // x\n"));
+}
+
+#[test]
+fn formatting_is_idempotent() {
+    let formatted = format(SOURCE);
+    assert_eq!(format(&formatted), formatted);
+
+    let scene = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/../main.rpu")).unwrap();
+    let formatted = format(&scene);
+    assert_eq!(format(&formatted), formatted);
+}
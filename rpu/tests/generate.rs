@@ -0,0 +1,48 @@
+use rpu::prelude::*;
+
+const SCENE: &str = r#"Texture
+    name = "Bricks"
+    Color
+        color = #a9957b
+
+Voxel'a
+    texture = "Bricks"
+
+Grid3D
+    seed = 7
+    generate()
+        for i in 0..20 {
+            place(random(0, 9), 0, random(0, 9), "a");
+        }
+"#;
+
+/// The cells of the generated map.
+fn cells(source: &str) -> Vec<(i32, i32, i32)> {
+    let context = Compiler::new().compile(source.to_string()).ok().unwrap();
+    let Object::Layout3D(layout) = &context.layouts[0] else {
+        panic!("Expected a layout.");
+    };
+    let mut cells : Vec<(i32, i32, i32)> = layout.get_map3d().into_keys().collect();
+    cells.sort();
+    cells
+}
+
+#[test]
+fn seeded_maps_are_deterministic() {
+    let map = cells(SCENE);
+    assert!(map.len() > 10);
+    assert_eq!(cells(SCENE), map);
+    assert!(cells(&SCENE.replace("seed = 7", "seed = 8")) != map);
+}
+
+#[test]
+fn generate_stops_at_the_operation_limit() {
+    let source = SCENE.replace("for i in 0..20 {", "loop {");
+
+    let mut rpu = RPU::new(100, 400);
+    rpu.set_script_limits(ScriptLimits { max_operations: 1000, ..Default::default() });
+    let errors = rpu.compile(source).err().unwrap();
+    assert_eq!(errors.len(), 1);
+    assert_eq!((errors[0].code, errors[0].line), ("script", 13));
+    assert_eq!(errors[0].description, "Error in generate(): Too many operations.");
+}
@@ -0,0 +1,65 @@
+use rpu::prelude::*;
+
+/// Writes the files into a new temporary directory and returns the path of the first one.
+fn write_files(test: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rpu-imports-{}-{}", test, std::process::id()));
+    _ = std::fs::remove_dir_all(&dir);
+    for (name, code) in files {
+        let path = dir.join(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, code).unwrap();
+    }
+    dir.join(files[0].0).canonicalize().unwrap()
+}
+
+#[test]
+fn import_cycles_are_errors() {
+    let main = write_files("cycle", &[
+        ("main.rpu", "Import \"a.rpu\"\n"),
+        ("a.rpu", "Import \"b.rpu\"\n"),
+        ("b.rpu", "\nImport \"a.rpu\"\n"),
+    ]);
+    let dir = main.parent().unwrap();
+
+    let errors = Compiler::new().compile_from_path(main.clone()).err().unwrap();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].description, format!("Import cycle detected: {} -> {} -> {}.", dir.join("a.rpu").display(), dir.join("b.rpu").display(), dir.join("a.rpu").display()));
+    assert_eq!(errors[0].file, Some(dir.join("b.rpu")));
+    assert_eq!(errors[0].line, 2);
+}
+
+#[test]
+fn shared_imports_are_loaded_once_relative_to_the_importing_file() {
+    // Both libraries import lib/common.rpu, the main file has no common.rpu next to it
+    let main = write_files("diamond", &[
+        ("main.rpu", "Import \"lib/left.rpu\"\nImport \"lib/right.rpu\"\n\nVoxel'a\n    texture = \"Left\"\n\nVoxel'b\n    texture = \"Right\"\n\nGrid3D\n:ab\n"),
+        ("lib/left.rpu", "Import \"common.rpu\"\n\nTexture\n    name = \"Left\"\n    Color\n        color = #ff0000\n"),
+        ("lib/right.rpu", "Import \"common.rpu\"\n\nTexture\n    name = \"Right\"\n    Color\n        color = #0000ff\n"),
+        ("lib/common.rpu", "Texture\n    name = \"Common\"\n    Color\n        color = #808080\n"),
+    ]);
+
+    // A second load would define the texture Common twice
+    let context = Compiler::new().compile_from_path(main);
+    assert!(context.is_ok());
+    assert_eq!(context.ok().unwrap().textures.len(), 3);
+}
+
+#[test]
+fn errors_name_the_imported_file() {
+    let main = write_files("errors", &[
+        ("main.rpu", "Import \"lib/broken.rpu\"\nImport \"lib/missing.rpu\"\n"),
+        ("lib/broken.rpu", "Texture\n    name = \"Broken\"\n    Color\n        colr = #ffffff\n"),
+    ]);
+    let dir = main.parent().unwrap();
+
+    let errors = Compiler::new().compile_from_path(main.clone()).err().unwrap();
+    assert_eq!(errors.len(), 2);
+
+    assert_eq!(errors[0].description, "Unknown property 'colr' for Color. Did you mean 'color'?");
+    assert_eq!(errors[0].file, Some(dir.join("lib/broken.rpu")));
+    assert_eq!((errors[0].line, errors[0].column), (4, 9));
+
+    assert_eq!(errors[1].description, format!("Imported file '{}' not found.", dir.join("lib/missing.rpu").display()));
+    assert_eq!(errors[1].file, Some(main));
+    assert_eq!(errors[1].line, 2);
+}
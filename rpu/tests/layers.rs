@@ -0,0 +1,61 @@
+use rpu::prelude::*;
+
+const SCENE: &str = r#"let dark = #202020
+
+Layer
+    name = "Bands"
+    tint = #ffffff
+    width = 0.25
+    color(uv, input)
+        if fract(uv.x / width) < 0.5 { tint } else { input }
+
+Texture*
+    Color
+        color = dark
+    Bands
+        tint = #ff0000
+        width = 0.1
+"#;
+
+fn color_at(context: &Context, x: F) -> GF4 {
+    let index = context.textures[0];
+    let uv = UV::new(GF2::new(x, 0.0), GF4::new(0.0, 0.0, 100.0, 100.0), GF2::new(x, 0.0));
+    match &context.nodes[index].object {
+        Object::Element2D(texture) => texture.get_color_at(&uv, index, context),
+        _ => panic!("not a texture"),
+    }
+}
+
+fn compile_errors(source: &str) -> Vec<String> {
+    Compiler::new().compile(source.to_string()).err().unwrap().iter().map(|e| e.description.clone()).collect()
+}
+
+#[test]
+fn layers_are_used_like_built_in_layers() {
+    let context = Compiler::new().compile(SCENE.to_string()).ok().unwrap();
+
+    // The parameters of the use override the defaults of the definition, input is the color below
+    assert_eq!(color_at(&context, 0.02), GF4::new(1.0, 0.0, 0.0, 1.0));
+    assert!((color_at(&context, 0.07) - GF4::new(0.125, 0.125, 0.125, 1.0)).norm() < 0.01);
+
+    let defaults = SCENE.replace("        tint = #ff0000\n        width = 0.1\n", "");
+    let context = Compiler::new().compile(defaults).ok().unwrap();
+    assert_eq!(color_at(&context, 0.1), GF4::new(1.0, 1.0, 1.0, 1.0));
+    assert!(color_at(&context, 0.2) != GF4::new(1.0, 1.0, 1.0, 1.0));
+}
+
+#[test]
+fn layer_errors() {
+    let errors = compile_errors(&SCENE.replace("width = 0.1", "widht = 0.1\n        tint = 1"));
+    assert_eq!(errors, vec![
+        "Unknown property 'widht' for Bands. Did you mean 'width'?".to_string(),
+        "Property 'tint' expects F4, found integer (e.g. tint = F4(1.0, 1.0, 1.0, 1.0) or #ffffff).".to_string(),
+    ]);
+
+    let (layer, texture) = (SCENE.find("Layer").unwrap(), SCENE.find("Texture*").unwrap());
+    let errors = compile_errors(&format!("{}{}\n{}", &SCENE[..layer], &SCENE[texture..], &SCENE[layer..texture]));
+    assert_eq!(errors, vec!["Layer 'Bands' is used before it is defined.".to_string()]);
+
+    let errors = compile_errors(&SCENE.replace("name = \"Bands\"", "name = \"Color\""));
+    assert_eq!(errors[0], "'Color' is a built in element and can not be the name of a layer.");
+}
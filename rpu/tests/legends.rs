@@ -0,0 +1,62 @@
+use rpu::prelude::*;
+
+const SCENE: &str = r#"Texture
+    name = "Bricks"
+    Color
+        color = #a9957b
+
+Voxel'wall
+    texture = "Bricks"
+
+Voxel'door
+    texture = "Bricks"
+
+Grid3D
+    Legend
+        ## = 'wall
+        🧱 = 'wall
+        D> = 'door, rotation = 90
+        ^^ = 'wall, height = 0.5
+:##🧱 D>
+:^^  ##
+"#;
+
+/// A cell of the map with the instance symbol of its object, the rotation and the height.
+type Cell = ((i32, i32, i32), String, F, F);
+
+fn cells(context: &Context) -> Vec<Cell> {
+    let Object::Layout3D(layout) = &context.layouts[0] else {
+        panic!("Expected a layout.");
+    };
+    let symbol = |node: usize| context.symbols_node_index.iter().find(|(_, index)| **index == node).map(|(symbol, _)| symbol.clone()).unwrap();
+    let mut cells : Vec<Cell> = layout.get_map3d().into_iter().map(|(key, cell)| (key, symbol(cell.node), cell.rotation, cell.height)).collect();
+    cells.sort_by_key(|cell| cell.0);
+    cells
+}
+
+#[test]
+fn legend_keys_and_modifiers() {
+    let context = Compiler::new().compile(SCENE.to_string()).ok().unwrap();
+
+    // Keys are two characters wide, the single glyph is followed by a space
+    assert_eq!(cells(&context), vec![
+        ((0, 0, 0), "wall".to_string(), 0.0, 0.0),
+        ((0, 0, 1), "wall".to_string(), 0.0, 0.5),
+        ((1, 0, 0), "wall".to_string(), 0.0, 0.0),
+        ((2, 0, 0), "door".to_string(), 90.0, 0.0),
+        ((2, 0, 1), "wall".to_string(), 0.0, 0.0),
+    ]);
+}
+
+#[test]
+fn invalid_legend_entries_are_errors() {
+    let source = SCENE.replace("rotation = 90", "rotaton = 90").replace("height = 0.5", "height = 2.0").replace("## = 'wall", "## = 'wal");
+
+    let errors = Compiler::new().compile(source).err().unwrap();
+    let errors : Vec<(u32, &str)> = errors.iter().map(|e| (e.line, e.description.as_str())).collect();
+    assert_eq!(errors, vec![
+        (14, "Undefined instance symbol 'wal'."),
+        (16, "Unknown legend modifier 'rotaton'. Did you mean 'rotation'?"),
+        (17, "Modifier 'height' must be in the range -1 to 1, found 2."),
+    ]);
+}
@@ -0,0 +1,49 @@
+use rpu::prelude::*;
+
+use std::time::{Duration, Instant};
+
+const SCENE: &str = r#"Texture*
+    Color
+        color = #444444
+        shader(uv, size)
+            let i = 0;
+            while i < count { i += 1; }
+            F4(uv.x, 0.0, 0.0, 1.0)
+"#;
+
+fn scene(count: &str) -> String {
+    SCENE.replace("count", count)
+}
+
+fn render(rpu: &mut RPU) -> Result<(), RenderError> {
+    let mut frame = vec![0; 100 * 400 * 4];
+    rpu.render(&mut frame, (0, 0, 100, 400), 100)
+}
+
+#[test]
+fn endless_loops_fail_the_frame() {
+    let mut rpu = RPU::new(100, 400);
+    rpu.set_script_limits(ScriptLimits { max_operations: 1000, ..Default::default() });
+    assert!(rpu.compile(scene("10")).is_ok());
+    assert!(render(&mut rpu).is_ok());
+
+    assert!(rpu.compile(scene("i + 1")).is_ok());
+    let error = render(&mut rpu).err().unwrap();
+    assert_eq!(error.limit, ScriptLimit::Operations);
+    assert_eq!((error.error.code, error.error.line), ("script-limit", 4));
+
+    let reported : Vec<RPUError> = rpu.diagnostics().into_iter().filter(|d| d.code == "script-limit").collect();
+    assert_eq!(reported.len(), 1);
+}
+
+#[test]
+fn frame_budget_stops_all_scripts() {
+    let mut rpu = RPU::new(100, 400);
+    rpu.set_script_limits(ScriptLimits { frame_budget: Some(Duration::from_millis(50)), ..Default::default() });
+    assert!(rpu.compile(scene("5000")).is_ok());
+
+    let start = Instant::now();
+    let error = render(&mut rpu).err().unwrap();
+    assert_eq!(error.limit, ScriptLimit::FrameBudget);
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
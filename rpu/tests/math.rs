@@ -0,0 +1,37 @@
+use rpu::prelude::*;
+
+const SCENE: &str = r##"let sum = F2(1.0, 2.0) + F2(0.5, 0.5) * 2
+let unit = normalize(F3(3.0, 0.0, 4.0))
+let up = cross(F3(1.0, 0.0, 0.0), F3(0.0, 1.0, 0.0))
+let blend = mix(F4(0.0), F4(1.0), smoothstep(0.0, 1.0, 0.5))
+let wrapped = fract(F2(1.25, -0.25)) + clamp(F2(-1.0, 2.0), 0.0, 1.0)
+let angle = atan(1.0, 1.0) * 4.0
+let red = hsv(0.0, 1.0, 1.0)
+let green = rgb_to_hsv(hex("#00ff00")).x
+let brick = if hex(0xbc4a3c) == hex("#bc4a3c") { 1 } else { 0 }
+let name = to_hex(rgb(169, 149, 123))
+
+Texture*
+    Color
+        color = red
+"##;
+
+fn constant(context: &Context, name: &str) -> Value {
+    context.constants.iter().find(|(n, _)| n == name).map(|(_, v)| v.clone()).unwrap()
+}
+
+#[test]
+fn math_and_color_functions() {
+    let context = Compiler::new().compile(SCENE.to_string()).ok().unwrap();
+
+    assert_eq!(constant(&context, "sum"), Value::F2(GF2::new(2.0, 3.0)));
+    assert_eq!(constant(&context, "unit"), Value::F3(GF3::new(0.6, 0.0, 0.8)));
+    assert_eq!(constant(&context, "up"), Value::F3(GF3::new(0.0, 0.0, 1.0)));
+    assert_eq!(constant(&context, "blend"), Value::F4(GF4::new(0.5, 0.5, 0.5, 0.5)));
+    assert_eq!(constant(&context, "wrapped"), Value::F2(GF2::new(0.25, 1.75)));
+    assert_eq!(constant(&context, "angle"), Value::F(std::f64::consts::PI));
+    assert_eq!(constant(&context, "red"), Value::F4(GF4::new(1.0, 0.0, 0.0, 1.0)));
+    assert_eq!(constant(&context, "green"), Value::F(1.0 / 3.0));
+    assert_eq!(constant(&context, "brick"), Value::Int(1));
+    assert_eq!(constant(&context, "name"), Value::String("#a9957b".to_string()));
+}
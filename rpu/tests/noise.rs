@@ -0,0 +1,44 @@
+use rpu::prelude::*;
+
+const SCENE: &str = r#"let seed = 7
+let perlin = perlin2(F2(0.3, 0.7)) + perlin3(F3(0.3, 0.7, 0.1))
+let simplex = simplex(F2(0.3, 0.7))
+let value = value_noise(F3(0.3, 0.7, 0.1))
+let fbm = fbm(F2(0.3, 0.7), 4, 2.0, 0.5)
+let cell = voronoi(F2(3.3, 1.7))
+let worley = worley(F3(3.3, 1.7, 0.2))
+let hash = hash(1, 2, 3)
+
+Texture*
+    Color
+        shader(uv, size)
+            F4(perlin2(uv * 8.0) * 0.5 + 0.5, hash01(5), 0.0, 1.0)
+"#;
+
+fn constants(source: &str) -> Vec<(String, Value)> {
+    Compiler::new().compile(source.to_string()).ok().unwrap().constants
+}
+
+fn render(source: &str) -> Vec<u8> {
+    let mut rpu = RPU::new(100, 400);
+    assert!(rpu.compile(source.to_string()).is_ok());
+    let mut frame = vec![0; 100 * 400 * 4];
+    assert!(rpu.render(&mut frame, (0, 0, 100, 400), 100).is_ok());
+    frame
+}
+
+#[test]
+fn noise_depends_on_the_seed_only() {
+    let reseeded = SCENE.replace("let seed = 7", "let seed = 8");
+
+    assert_eq!(constants(SCENE), constants(SCENE));
+    let (a, b) = (constants(SCENE), constants(&reseeded));
+    for name in ["perlin", "simplex", "value", "fbm", "cell", "worley", "hash"] {
+        let value = |constants: &Vec<(String, Value)>| constants.iter().find(|(n, _)| n == name).map(|(_, v)| v.clone()).unwrap();
+        assert!(value(&a) != value(&b), "{} does not depend on the seed", name);
+    }
+
+    let frame = render(SCENE);
+    assert!(frame == render(SCENE));
+    assert!(frame != render(&reseeded));
+}
@@ -0,0 +1,87 @@
+use rpu::prelude::*;
+
+const PATTERNS: [&str; 9] = ["Checker", "Gradient", "Voronoi", "Tiles", "Hexagons", "Stones", "Planks", "Marble", "Stripes"];
+
+fn texture(pattern: &str, properties: &str) -> String {
+    format!("Texture*\n    Color\n        color = #202020\n    {}\n{}", pattern, properties)
+}
+
+fn color_at(context: &Context, p: GF2, world: GF2) -> GF4 {
+    let index = context.textures[0];
+    let uv = UV::new(p, GF4::new(0.0, 0.0, 100.0, 100.0), world);
+    match &context.nodes[index].object {
+        Object::Element2D(texture) => texture.get_color_at(&uv, index, context),
+        _ => panic!("not a texture"),
+    }
+}
+
+fn samples() -> Vec<GF2> {
+    (0..64).map(|i| GF2::new((i % 8) as F / 8.0 + 0.03, (i / 8) as F / 8.0 + 0.07)).collect()
+}
+
+#[test]
+fn patterns_color_the_texture() {
+    for pattern in PATTERNS {
+        let context = Compiler::new().compile(texture(pattern, "")).ok().unwrap();
+        let base = GF4::new(0.125, 0.125, 0.125, 1.0);
+        let changed = samples().iter().filter(|p| (color_at(&context, **p - GF2::new(0.5, 0.5), **p) - base).norm() > 0.01).count();
+        assert!(changed > 0, "{} did not change the color", pattern);
+    }
+}
+
+#[test]
+fn patterns_tile() {
+    for pattern in PATTERNS {
+        let seeded = Compiler::schema(pattern).unwrap().properties.iter().any(|p| p.name == "seed");
+        let context = Compiler::new().compile(texture(pattern, if seeded { "        seed = 7\n" } else { "" })).ok().unwrap();
+        for p in samples() {
+            let a = color_at(&context, p, p);
+            let b = color_at(&context, p, p + GF2::new(1.0, -2.0));
+            assert!((a - b).norm() < 1e-6, "{} does not tile at {:?}: {:?} {:?}", pattern, p, a, b);
+        }
+    }
+}
+
+#[test]
+fn pattern_kinds() {
+    let linear = Compiler::new().compile(texture("Gradient", "")).ok().unwrap();
+    let radial = Compiler::new().compile(texture("Gradient", "        kind = \"radial\"\n")).ok().unwrap();
+    let (bottom, top) = (GF2::new(0.0, -0.5), GF2::new(0.0, 0.5));
+    assert!((color_at(&linear, bottom, bottom) - GF4::new(0.125, 0.125, 0.125, 1.0)).norm() < 0.01);
+    assert_eq!(color_at(&linear, top, top), GF4::new(0.0, 0.0, 0.0, 1.0));
+    assert!((color_at(&radial, bottom, bottom) - color_at(&radial, top, top)).norm() < 1e-6);
+
+    let errors = Compiler::new().compile(texture("Stripes", "        kind = \"diagnoal\"\n")).err().unwrap();
+    assert_eq!(errors[0].description, "Property 'kind' expects one of \"horizontal\", \"vertical\", \"diagonal\", found \"diagnoal\".");
+    assert_eq!(errors[0].line, 5);
+}
+
+#[test]
+fn set_property_checks_the_kind() {
+    let mut context = Compiler::new().compile(texture("Gradient", "").replace("Texture*\n", "Texture*\n    name = \"Sky\"\n")).ok().unwrap();
+
+    let error = context.set_property("Sky/Gradient.kind", Value::String("bogus".to_string())).err().unwrap();
+    assert_eq!(error.description, "Property 'kind' expects one of \"linear\", \"radial\", found \"bogus\".");
+    assert_eq!(context.get_property("Sky/Gradient.kind"), Some(Value::String("linear".to_string())));
+
+    assert!(context.set_property("Sky/Gradient.kind", Value::String("radial".to_string())).is_ok());
+    assert_eq!(context.get_property("Sky/Gradient.kind"), Some(Value::String("radial".to_string())));
+}
+
+#[test]
+fn bricks_are_configurable() {
+    // Bricks scale with the aspect ratio of the render
+    let compile = |properties: &str| {
+        let mut context = Compiler::new().compile(texture("Bricks", properties)).ok().unwrap();
+        context.size = [100, 100];
+        context
+    };
+    let defaults = compile("");
+    let explicit = compile("        cells = 4.0\n        ratio = 2.0\n        offset = 0.5\n        gap = 0.08\n        bevel = 0.07\n        roundness = 0.25\n");
+    let stacked = compile("        offset = 0.0\n        gap = 0.2\n");
+    assert!(samples().iter().all(|p| color_at(&defaults, *p, *p) == color_at(&explicit, *p, *p)));
+    assert!(samples().iter().any(|p| color_at(&defaults, *p, *p) != color_at(&stacked, *p, *p)));
+
+    let errors = Compiler::new().compile(texture("Bricks", "        ratio = 0.0\n")).err().unwrap();
+    assert!(errors[0].description.starts_with("Property 'ratio' must be in the range 0.01 to "));
+}
@@ -0,0 +1,145 @@
+use rpu::prelude::*;
+
+const SCENE: &str = r#"Pinhole
+    origin = F3(3.0, 0.5001, 9.5)
+    center = F3(3.0, 0.5, 0.0)
+
+Layer
+    name = "Bands"
+    tint = #ffffff
+    width = 0.25
+    color(uv, input)
+        if fract(uv.x / width) < 0.5 { tint } else { input }
+
+Texture
+    name = "Wood"
+    Color
+        color = #8a5a2b
+    Noise
+        color = #444444
+        scale = F2(1.0, 1.0)
+
+Texture
+    name = "Stone"
+    Color
+        color = #808080
+    Bands
+        width = 0.1
+
+Voxel'a
+    texture = "Wood"
+
+sdfSphere'b
+    texture = "Stone"
+    radius = 0.3
+
+Grid3D
+:    a
+:aaa a
+:
+: a  a
+: ab a
+"#;
+
+fn compile(source: &str) -> RPU {
+    let mut rpu = RPU::new(100, 400);
+    assert!(rpu.compile(source.to_string()).is_ok());
+    rpu
+}
+
+fn render(rpu: &mut RPU) -> Vec<u8> {
+    let mut frame = vec![0; 100 * 400 * 4];
+    assert!(rpu.render_at(0.0, &mut frame, (0, 0, 100, 400), 100).is_ok());
+    frame
+}
+
+#[test]
+fn properties_are_enumerated_by_path() {
+    let rpu = compile(SCENE);
+    assert_eq!(rpu.paths(), vec!["Pinhole", "Wood", "Wood/Color", "Wood/Noise", "Stone", "Stone/Color", "Stone/Bands", "a", "b"]);
+
+    let properties = rpu.properties("Stone/Bands");
+    let names : Vec<&str> = properties.iter().map(|(def, _)| def.name.as_str()).collect();
+    assert_eq!(names, vec!["tint", "width"]);
+    assert_eq!(properties[1].1, Some(Value::F(0.1)));
+
+    assert_eq!(rpu.get_property("Wood/Noise.scale"), Some(Value::F2(GF2::new(1.0, 1.0))));
+    assert_eq!(rpu.get_property("b.radius"), Some(Value::F(0.3)));
+    assert_eq!(rpu.get_property("Wood/Bricks.color"), None);
+}
+
+#[test]
+fn set_properties_render_like_the_source() {
+    let mut rpu = compile(SCENE);
+    render(&mut rpu);
+
+    assert!(rpu.set_property("Wood/Noise.scale", Value::F2(GF2::new(3.0, 2.0))).is_ok());
+    assert!(rpu.set_property("Stone/Bands.width", Value::F(0.2)).is_ok());
+    assert!(rpu.set_property("b.radius", Value::Int(1)).is_ok());
+    assert!(rpu.set_property("b.texture", Value::String("Wood".to_string())).is_ok());
+    assert_eq!(rpu.get_property("b.radius"), Some(Value::F(1.0)));
+
+    let source = SCENE.replace("scale = F2(1.0, 1.0)", "scale = F2(3.0, 2.0)")
+        .replace("width = 0.1", "width = 0.2")
+        .replace("radius = 0.3", "radius = 1.0")
+        .replace("texture = \"Stone\"", "texture = \"Wood\"");
+    assert!(render(&mut rpu) == render(&mut compile(&source)));
+}
+
+#[test]
+fn set_properties_are_checked() {
+    let mut rpu = compile(SCENE);
+
+    let err = rpu.set_property("Wood/Noise.scale", Value::F(2.0)).err().unwrap();
+    assert_eq!(err.description, "Property 'scale' expects F2, found float (e.g. scale = F2(1.0, 1.0)).");
+
+    let err = rpu.set_property("b.radius", Value::F(-1.0)).err().unwrap();
+    assert!(err.description.starts_with("Property 'radius' must be in the range 0 to "));
+
+    let err = rpu.set_property("Wood/Noise.sclae", Value::F(2.0)).err().unwrap();
+    assert_eq!(err.description, "Unknown property 'sclae' for Noise. Did you mean 'scale'?");
+
+    let err = rpu.set_property("b.texture", Value::String("Wod".to_string())).err().unwrap();
+    assert_eq!(err.description, "Unknown texture 'Wod'.");
+
+    let err = rpu.set_property("Wood/Noise[1].scale", Value::F(2.0)).err().unwrap();
+    assert_eq!(err.description, "'Wood/Noise[1]' is neither a texture, a layer of a texture nor an instance symbol.");
+
+    assert_eq!(rpu.get_property("Wood/Noise.scale"), Some(Value::F2(GF2::new(1.0, 1.0))));
+}
+
+#[test]
+fn set_properties_keep_non_finite_values() {
+    let mut rpu = compile(SCENE);
+
+    // The parameters of a layer have no range, the value is not written as source
+    assert!(rpu.set_property("Stone/Bands.width", Value::F(F::INFINITY)).is_ok());
+    assert_eq!(rpu.get_property("Stone/Bands.width"), Some(Value::F(F::INFINITY)));
+    assert!(rpu.set_property("Stone/Bands.width", Value::F(F::NAN)).is_ok());
+    assert!(matches!(rpu.get_property("Stone/Bands.width"), Some(Value::F(v)) if v.is_nan()));
+    render(&mut rpu);
+
+    let err = rpu.set_property("b.radius", Value::F(F::NAN)).err().unwrap();
+    assert!(err.description.starts_with("Property 'radius' must be in the range 0 to "));
+    assert!(err.description.ends_with(", found NaN."));
+    let err = rpu.set_property("b.radius", Value::F(F::INFINITY)).err().unwrap();
+    assert!(err.description.ends_with(", found inf."));
+    assert_eq!(rpu.get_property("b.radius"), Some(Value::F(0.3)));
+}
+
+#[test]
+fn set_properties_reject_wrong_types() {
+    let mut rpu = compile(SCENE);
+
+    let err = rpu.set_property("b.radius", Value::String("0.5".to_string())).err().unwrap();
+    assert_eq!(err.description, "Property 'radius' expects float, found string (e.g. radius = 1.0).");
+
+    let err = rpu.set_property("Wood/Color.color", Value::F3(GF3::new(1.0, 0.0, 0.0))).err().unwrap();
+    assert_eq!(err.description, "Property 'color' expects F4, found F3 (e.g. color = F4(1.0, 1.0, 1.0, 1.0) or #ffffff).");
+
+    let err = rpu.set_property("b.texture", Value::Int(1)).err().unwrap();
+    assert_eq!(err.description, "Property 'texture' expects string, found integer (e.g. texture = \"Name\").");
+
+    assert_eq!(rpu.get_property("b.radius"), Some(Value::F(0.3)));
+    assert_eq!(rpu.get_property("b.texture"), Some(Value::String("Stone".to_string())));
+}
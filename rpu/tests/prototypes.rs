@@ -0,0 +1,124 @@
+use rpu::prelude::*;
+
+const DERIVED: &str = r#"Pinhole
+    origin = F3(3.0, 0.5001, 9.5)
+    center = F3(3.0, 0.5, 0.0)
+
+Texture
+    name = "Bricks"
+    pixelate = 60.0
+    Color
+        color = #a9957b
+    Bricks
+        color = #bc4a3c
+
+Texture : Bricks
+    name = "Mossy"
+    pixelate = 30.0
+    Noise
+        color = #22aa22
+
+sdfCustom's
+    texture = "Bricks"
+    distance(p)
+        length(p) - 0.3
+
+sdfCustom't : 's
+    texture = "Mossy"
+    distance(p)
+        length(p) - 0.2
+
+Grid3D
+:s t
+"#;
+
+const WRITTEN: &str = r#"Pinhole
+    origin = F3(3.0, 0.5001, 9.5)
+    center = F3(3.0, 0.5, 0.0)
+
+Texture
+    name = "Bricks"
+    pixelate = 60.0
+    Color
+        color = #a9957b
+    Bricks
+        color = #bc4a3c
+
+Texture
+    name = "Mossy"
+    pixelate = 30.0
+    Color
+        color = #a9957b
+    Bricks
+        color = #bc4a3c
+    Noise
+        color = #22aa22
+
+sdfCustom's
+    texture = "Bricks"
+    distance(p)
+        length(p) - 0.3
+
+sdfCustom't
+    texture = "Mossy"
+    distance(p)
+        length(p) - 0.2
+
+Grid3D
+:s t
+"#;
+
+fn compile(source: &str) -> RPU {
+    let mut rpu = RPU::new(100, 400);
+    assert!(rpu.compile(source.to_string()).is_ok());
+    rpu
+}
+
+fn render(rpu: &mut RPU) -> Vec<u8> {
+    let mut frame = vec![0; 100 * 400 * 4];
+    assert!(rpu.render(&mut frame, (0, 0, 100, 400), 100).is_ok());
+    frame
+}
+
+#[test]
+fn derived_blocks_override_their_prototypes() {
+    let mut derived = compile(DERIVED);
+    assert_eq!(derived.paths(), vec!["Pinhole", "Bricks", "Bricks/Color", "Bricks/Bricks", "Mossy", "Mossy/Color", "Mossy/Bricks", "Mossy/Noise", "s", "t"]);
+    assert_eq!(derived.get_property("Mossy.pixelate"), Some(Value::F(30.0)));
+    assert_eq!(derived.get_property("Bricks.pixelate"), Some(Value::F(60.0)));
+    assert_eq!(derived.get_property("t.texture"), Some(Value::String("Mossy".to_string())));
+
+    assert!(render(&mut derived) == render(&mut compile(WRITTEN)));
+    assert!(render(&mut derived) != render(&mut compile(&DERIVED.replace("length(p) - 0.2", "length(p) - 0.4"))));
+}
+
+#[test]
+fn prototype_cycles_are_errors() {
+    let source = r#"Texture : Stone
+    name = "Bricks"
+
+Texture : Bricks
+    name = "Stone"
+    Color
+        color = #808080
+
+Voxel'a : 'b
+    texture = "Bricks"
+
+Voxel'b : 'a
+    texture = "Stone"
+
+Grid3D
+:ab
+"#;
+
+    let mut rpu = RPU::new(100, 400);
+    let errors = rpu.compile(source.to_string()).err().unwrap();
+    let errors : Vec<(u32, u32, &str)> = errors.iter().map(|e| (e.line, e.column, e.description.as_str())).collect();
+    assert_eq!(errors, vec![
+        (1, 11, "Prototype cycle detected: Stone -> Bricks -> Stone."),
+        (4, 11, "Prototype cycle detected: Bricks -> Stone -> Bricks."),
+        (9, 12, "Prototype cycle detected: b -> a -> b."),
+        (12, 12, "Prototype cycle detected: a -> b -> a."),
+    ]);
+}
@@ -0,0 +1,130 @@
+use rpu::prelude::*;
+
+const SCENE: &str = r#"Pinhole
+    origin = F3(3.0, 0.5001, 9.5)
+    center = F3(3.0, 0.5, 0.0)
+
+Texture
+    name = "Bricks"
+    pixelate = 60.0
+    Color
+        color = #a9957b
+    Bricks
+        color = #bc4a3c
+
+Texture
+    name = "Floor"
+    Color
+        color = #444444
+
+Voxel'a
+    texture = "Bricks"
+
+Voxel'b
+    texture = "Floor"
+
+Grid3D
+:    a
+:aaa b
+:
+: a  a
+"#;
+
+fn render(rpu: &mut RPU) -> Vec<u8> {
+    let mut frame = vec![0; 100 * 400 * 4];
+    assert!(rpu.render(&mut frame, (0, 0, 100, 400), 100).is_ok());
+    frame
+}
+
+/// The number of objects moved out of the previous context.
+fn reused(previous: &Context) -> usize {
+    previous.nodes.iter().filter(|node| matches!(node.object, Object::Empty)).count() +
+    previous.layouts.iter().filter(|layout| matches!(layout, Object::Empty)).count()
+}
+
+#[test]
+fn unchanged_blocks_are_reused() {
+    let mut previous = Compiler::new().compile(SCENE.to_string()).ok().unwrap();
+
+    // Only the floor texture and its layer change
+    let changed = SCENE.replace("#444444", "#666666");
+    let context = Compiler::new().recompile(changed, &mut previous);
+
+    assert!(context.is_ok());
+    assert_eq!(reused(&previous), previous.nodes.len() + previous.layouts.len() - 2);
+}
+
+#[test]
+fn failed_recompile_keeps_the_scene() {
+    let mut previous = Compiler::new().compile(SCENE.to_string()).ok().unwrap();
+
+    let broken = SCENE.replace("texture = \"Floor\"", "texture = \"Flor\"");
+    assert!(Compiler::new().recompile(broken, &mut previous).is_err());
+    assert_eq!(reused(&previous), 0);
+}
+
+#[test]
+fn recompiled_scene_renders_like_a_new_one() {
+    let changed = SCENE.replace("#bc4a3c", "#3c4abc").replace(":aaa b", ":aba b");
+
+    let mut rpu = RPU::new(100, 400);
+    assert!(rpu.compile(SCENE.to_string()).is_ok());
+    let before = render(&mut rpu);
+
+    assert!(rpu.compile("Voxel'a\n    texture = 1.0".to_string()).is_err());
+    assert!(render(&mut rpu) == before);
+
+    assert!(rpu.compile(changed.clone()).is_ok());
+    let mut fresh = RPU::new(100, 400);
+    assert!(fresh.compile(changed).is_ok());
+    assert!(render(&mut rpu) == render(&mut fresh));
+    assert!(render(&mut rpu) != before);
+}
+
+#[test]
+fn objects_changed_at_runtime_are_not_reused() {
+    let mut rpu = RPU::new(100, 400);
+    assert!(rpu.compile(SCENE.to_string()).is_ok());
+    assert!(rpu.set_property("Bricks/Bricks.color", Value::F4(GF4::new(0.2, 0.3, 0.7, 1.0))).is_ok());
+    assert!(rpu.set_property("b.texture", Value::String("Bricks".to_string())).is_ok());
+    let changed = render(&mut rpu);
+
+    // Compiling the unchanged source restores the values of the source
+    assert!(rpu.compile(SCENE.to_string()).is_ok());
+    let mut fresh = RPU::new(100, 400);
+    assert!(fresh.compile(SCENE.to_string()).is_ok());
+    let expected = render(&mut fresh);
+    assert!(render(&mut rpu) == expected);
+    assert!(changed != expected);
+}
+
+#[test]
+fn moved_blocks_report_errors_at_their_new_line() {
+    let source = "Texture*\n    Color\n        color = #444444\n        shader(uv, size)\n            F4(missing, 0.0, 0.0, 1.0)\n";
+
+    let mut rpu = RPU::new(100, 400);
+    assert!(rpu.compile(source.to_string()).is_ok());
+    assert!(rpu.compile(format!("\n\n\n{}", source)).is_ok());
+    render(&mut rpu);
+
+    let errors : Vec<(u32, u32)> = rpu.diagnostics().iter().filter(|d| d.severity == Severity::Error).map(|d| (d.line, d.column)).collect();
+    assert_eq!(errors, vec![(8, 16)]);
+}
+
+#[test]
+fn baked_textures_are_reused_and_baked_again_when_changed() {
+    let baked = SCENE.replace("    pixelate = 60.0\n", "    pixelate = 60.0\n    size = F2(16.0, 16.0)\n");
+
+    let mut previous = Compiler::new().compile(baked.clone()).ok().unwrap();
+    assert!(Compiler::new().recompile(baked.clone(), &mut previous).is_ok());
+    assert_eq!(reused(&previous), previous.nodes.len() + previous.layouts.len());
+
+    let mut rpu = RPU::new(100, 400);
+    assert!(rpu.compile(baked.clone()).is_ok());
+    let before = render(&mut rpu);
+    assert!(rpu.set_property("Bricks/Bricks.color", Value::F4(GF4::new(0.2, 0.3, 0.7, 1.0))).is_ok());
+    assert!(render(&mut rpu) != before);
+
+    assert!(rpu.compile(baked).is_ok());
+    assert!(render(&mut rpu) == before);
+}
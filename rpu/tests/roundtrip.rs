@@ -0,0 +1,110 @@
+use rpu::prelude::*;
+
+const SCENE: &str = r#"let wall = #a9957b
+
+Pinhole
+    origin = F3(3.0, 0.5001, 9.5)
+    center = F3(3.0, 0.5, 0.0)
+
+Texture
+    name = "Bricks"
+    pixelate = 60.0
+    Color
+        color = wall
+    Bricks
+        color = #bc4a3c
+    Noise
+        color = #444444
+        scale = F2(1.0, 1.0)
+
+Voxel'a
+    texture = "Bricks"
+
+Layer
+    name = "Bands"
+    tint = #ffffff
+    width = 0.25
+    color(uv, input)
+        if fract(uv.y / width) < 0.5 { tint } else { input }
+
+Texture : Bricks
+    name = "Door"
+    Color
+        shader(uv, size)
+            F4(uv.x, 0.2, 0.1, 1.0)
+    Bands
+        width = 0.1
+
+Voxel'door
+    texture = "Door"
+
+Grid3D
+    seed = 3
+    generate()
+        place(random(0, 4), 0, 6, "a");
+    Legend
+        D> = 'door, rotation = 90
+:        a
+:a a a   a D>
+:
+:  a     a
+::  a    a
+"#;
+
+fn compile(source: &str) -> RPU {
+    let mut rpu = RPU::new(100, 400);
+    if let Err(errors) = rpu.compile(source.to_string()) {
+        panic!("{:?}\n{}", errors.iter().map(|e| e.description.clone()).collect::<Vec<String>>(), source);
+    }
+    rpu
+}
+
+fn render(rpu: &mut RPU) -> Vec<u8> {
+    let mut frame = vec![0; 100 * 400 * 4];
+    assert!(rpu.render(&mut frame, (0, 0, 100, 400), 100).is_ok());
+    frame
+}
+
+#[test]
+fn source_round_trip() {
+    let mut rpu = compile(SCENE);
+    let source = rpu.to_source().unwrap();
+
+    // Writing the recompiled scene gives the same source and the same picture
+    let mut written = compile(&source);
+    assert_eq!(written.to_source().unwrap(), source);
+    assert!(render(&mut rpu) == render(&mut written));
+}
+
+#[test]
+fn json_round_trip() {
+    let rpu = compile(SCENE);
+    let json = rpu.to_json().unwrap();
+
+    let document = Document::from_json(&json).unwrap();
+    assert_eq!(Formatter::new().format_document(&document), rpu.to_source().unwrap());
+
+    let mut from_json = RPU::new(100, 400);
+    assert!(from_json.compile_json(&json).is_ok());
+    assert_eq!(from_json.to_source(), rpu.to_source());
+}
+
+#[test]
+fn written_values_compile_again() {
+    let mut rpu = compile(SCENE);
+    assert!(rpu.set_property("Bricks/Noise.scale", Value::F2(GF2::new(F::INFINITY, F::NEG_INFINITY))).is_ok());
+    assert!(rpu.set_property("Bricks/Color.color", Value::F4(GF4::new(F::NAN, 0.5, 0.5, 1.0))).is_ok());
+    let name = Value::String("Line\nTab\tReturn\r \"Quote\" \\ // Door".to_string());
+    assert!(rpu.set_property("Door.name", name.clone()).is_ok());
+    assert!(rpu.set_property("door.texture", name.clone()).is_ok());
+    let source = rpu.to_source().unwrap();
+
+    let written = compile(&source);
+    assert_eq!(written.to_source().unwrap(), source);
+    assert_eq!(written.get_property("Bricks/Noise.scale"), Some(Value::F2(GF2::new(F::INFINITY, F::NEG_INFINITY))));
+    assert_eq!(written.get_property("door.texture"), Some(name));
+    let Some(Value::F4(color)) = written.get_property("Bricks/Color.color") else {
+        panic!("Expected a color.");
+    };
+    assert!(color.x.is_nan());
+}
@@ -0,0 +1,56 @@
+use rpu::prelude::*;
+
+fn radius() -> PropertyDef {
+    PropertyDef::new("radius", PropertyType::F, Some(Value::F(0.5)), Some((0.0, 10.0)), "The radius.")
+}
+
+#[test]
+fn check_rejects_other_types() {
+    assert_eq!(radius().check(Value::Int(2)), Ok(Value::F(2.0)));
+
+    assert_eq!(radius().check(Value::String("2".to_string())), Err("Property 'radius' expects float, found string (e.g. radius = 1.0).".to_string()));
+
+    let cells = PropertyDef::new("cells", PropertyType::Int, Some(Value::Int(4)), None, "The cells.");
+    assert_eq!(cells.check(Value::F(4.0)), Err("Property 'cells' expects integer, found float (e.g. cells = 1).".to_string()));
+
+    let scale = PropertyDef::new("scale", PropertyType::F2, None, None, "The scale.");
+    assert_eq!(scale.check(Value::F3(GF3::new(1.0, 1.0, 1.0))), Err("Property 'scale' expects F2, found F3 (e.g. scale = F2(1.0, 1.0)).".to_string()));
+}
+
+#[test]
+fn check_rejects_values_out_of_range() {
+    assert_eq!(radius().check(Value::F(0.0)), Ok(Value::F(0.0)));
+    assert_eq!(radius().check(Value::F(10.0)), Ok(Value::F(10.0)));
+
+    assert_eq!(radius().check(Value::F(-0.5)), Err("Property 'radius' must be in the range 0 to 10, found -0.5.".to_string()));
+    assert_eq!(radius().check(Value::Int(11)), Err("Property 'radius' must be in the range 0 to 10, found 11.".to_string()));
+    assert_eq!(radius().check(Value::F(F::NAN)), Err("Property 'radius' must be in the range 0 to 10, found NaN.".to_string()));
+
+    let cells = PropertyDef::new("cells", PropertyType::Int, Some(Value::Int(4)), Some((1.0, 1024.0)), "The cells.");
+    assert_eq!(cells.check(Value::Int(0)), Err("Property 'cells' must be in the range 1 to 1024, found 0.".to_string()));
+}
+
+#[test]
+fn check_rejects_values_not_in_the_list() {
+    let kind = PropertyDef::new("kind", PropertyType::String, Some(Value::String("linear".to_string())), None, "The kind.").with_values(&["linear", "radial"]);
+    assert_eq!(kind.check(Value::String("radial".to_string())), Ok(Value::String("radial".to_string())));
+    assert_eq!(kind.check(Value::String("bogus".to_string())), Err("Property 'kind' expects one of \"linear\", \"radial\", found \"bogus\".".to_string()));
+
+    let name = PropertyDef::new("name", PropertyType::String, None, None, "The name.");
+    assert_eq!(name.check(Value::String("bogus".to_string())), Ok(Value::String("bogus".to_string())));
+}
+
+#[test]
+fn closest_match_suggests_likely_typos() {
+    let names = ["scale", "color", "octaves"];
+    assert_eq!(closest_match("sclae", &names), Some("scale"));
+    assert_eq!(closest_match("Colour", &names), Some("color"));
+    assert_eq!(closest_match("octave", &names), Some("octaves"));
+    assert_eq!(closest_match("radius", &names), None);
+    assert_eq!(closest_match("scale", &[]), None);
+
+    let schema = Schema::new("Noise", vec![radius(), PropertyDef::new("color", PropertyType::F4, None, None, "The color.")], vec![]);
+    assert_eq!(schema.unknown_property("radios"), "Unknown property 'radios' for Noise. Did you mean 'radius'?");
+    assert_eq!(schema.unknown_property("width"), "Unknown property 'width' for Noise. Expected one of: radius, color.");
+    assert_eq!(Schema::new("Empty", vec![], vec![]).unknown_property("width"), "Unknown property 'width' for Empty. Empty has no properties.");
+}
@@ -0,0 +1,49 @@
+use rpu::prelude::*;
+
+const SCENE: &str = r#"Pinhole
+    origin = F3(3.0, 0.5001, 9.5)
+    center = F3(3.0, 0.5, 0.0)
+
+Texture
+    name = "Wood"
+    Color
+        color = #8a5a2b
+
+Voxel'a
+    texture = "Wood"
+
+sdfCustom'b
+    texture = "Wood"
+    distance(p)
+        length(p) - 0.5
+
+Grid3D
+:    a
+:aaa a
+:
+: a  a
+: ab a
+"#;
+
+fn render(source: &str) -> Vec<u8> {
+    let mut rpu = RPU::new(100, 400);
+    assert!(rpu.compile(source.to_string()).is_ok());
+    let mut frame = vec![0; 100 * 400 * 4];
+    assert!(rpu.render(&mut frame, (0, 0, 100, 400), 100).is_ok());
+    assert!(rpu.diagnostics().iter().all(|d| d.severity != Severity::Error));
+    frame
+}
+
+#[test]
+fn custom_sdf_renders_like_the_built_in_one() {
+    let sphere = SCENE.replace("sdfCustom'b", "sdfSphere'b").replace("    distance(p)\n        length(p) - 0.5\n", "");
+    assert!(render(SCENE) == render(&sphere));
+}
+
+#[test]
+fn custom_sdf_needs_a_distance_function() {
+    let source = SCENE.replace("    distance(p)\n        length(p) - 0.5\n", "");
+    let errors = Compiler::new().compile(source).err().unwrap();
+    assert_eq!(errors[0].description, "sdfCustom needs a distance(p) function which returns the distance to its surface.");
+    assert_eq!(errors[0].line, 13);
+}
@@ -31,12 +31,18 @@ fn main() -> Result<(), Error> {
     let mut rpu = RPU::new(width, height);
     let rc = rpu.compile_from_path(path_to_main);
 
-    if let Some(error) = rc.err() {
-        let error_text = format!("Compilation error: {} at line {}.", error.description, error.line);
-        println!("{}", error_text.as_str());
+    if let Some(errors) = rc.err() {
+        for error in &errors {
+            println!("{}", error.report());
+        }
+        println!("Compilation failed with {} error(s).", errors.len());
         return Ok(());
     }
 
+    for warning in rpu.diagnostics().iter().filter(|d| d.severity == rpu::prelude::Severity::Warning) {
+        println!("{}", warning.report());
+    }
+
     println!("RPU file compiled successfully.");
 
     // Write it to file
@@ -61,7 +67,9 @@ fn main() -> Result<(), Error> {
 
         let mut buffer = vec![0;width * height * 4];
         let start = get_time();
-        rpu.render(&mut buffer, (0, 0, width, height), width);
+        if let Err(err) = rpu.render(&mut buffer, (0, 0, width, height), width) {
+            println!("{}", err.error.report());
+        }
         println!("Image rendered in {} ms", get_time() - start);
         writer.write_image_data(&buffer).unwrap(); // Save
 
@@ -93,7 +101,9 @@ fn main() -> Result<(), Error> {
         // Draw the current frame
         if let Event::RedrawRequested(_) = event {
             // let start = get_time();
-            rpu.render(&mut pixels.get_frame()[..], (0, 0, width, height), width);
+            if let Err(err) = rpu.render(&mut pixels.get_frame()[..], (0, 0, width, height), width) {
+                error!("{}", err);
+            }
             // println!("Time: {}", get_time() - start);
             if pixels
                 .render()